@@ -0,0 +1,66 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rug::Rational;
+
+use billiards::billiards::Params;
+use billiards::billiards::orbit::check_path;
+use billiards::billiards::singularity::BaseSingularity::B0;
+use billiards::billiards::turn_path::TurnPath;
+use billiards::vector::V2;
+
+// an apex with large denominators, so that rotations and coordinates have
+// the huge numerators exact stepping runs into.
+fn apex() -> V2<Rational> {
+  V2(Rational::from((19, 32)) + Rational::from((1, 99_991)),
+    Rational::from((5, 16)) + Rational::from((1, 77_773)))
+}
+
+// a rotation by three turns, whose numerators have some 65 digits. squaring
+// it six times, as `bench_mul` does, gives a few thousand.
+fn big_rotation() -> V2<Rational> {
+  Params::new(apex()).turn_vec(B0, 3)
+}
+
+// complex multiplication as `Mul` did it before it borrowed its operands,
+// cloning each component for every product.
+fn cloning_mul(u: &V2<Rational>, v: &V2<Rational>) -> V2<Rational> {
+  V2(
+    u.0.clone() * v.0.clone() + (-u.1.clone() * v.1.clone()),
+    u.0.clone() * v.1.clone() + u.1.clone() * v.0.clone()
+  )
+}
+
+fn bench_mul(c: &mut Criterion) {
+  // squaring doubles the numerators' length each time.
+  for &squarings in &[0, 3, 6] {
+    let mut z = big_rotation();
+    for _ in 0..squarings {
+      z = &z * &z;
+    }
+    let (u, v) = (z.clone(), &z * &big_rotation());
+    c.bench_function(&format!("v2 mul cloning, squared {}x", squarings),
+      |b| b.iter(|| cloning_mul(black_box(&u), black_box(&v))));
+    c.bench_function(&format!("v2 mul by reference, squared {}x", squarings),
+      |b| b.iter(|| black_box(&u) * black_box(&v)));
+  }
+}
+
+fn bench_params(c: &mut Criterion) {
+  c.bench_function("params turn_vec 40", |b| b.iter(|| {
+    let mut params = Params::new(black_box(apex()));
+    params.turn_vec(B0, 40)
+  }));
+}
+
+fn bench_check_path(c: &mut Criterion) {
+  let path: TurnPath = "B0^-3 B1^-2 B0^3 B1^2 B0^-3 B1^-2 B0^3 B1^2".parse().unwrap();
+  c.bench_function("check_path exact", |b| b.iter(|| {
+    check_path(&mut Params::new(black_box(apex())), &path)
+  }));
+  let float_apex = V2(apex().0.to_f64(), apex().1.to_f64());
+  c.bench_function("check_path float", |b| b.iter(|| {
+    check_path(&mut Params::new(black_box(float_apex.clone())), &path)
+  }));
+}
+
+criterion_group!(benches, bench_mul, bench_params, bench_check_path);
+criterion_main!(benches);
@@ -1,3 +1,6 @@
+pub mod interval;
+
+use std::cmp::Ordering;
 use std::ops::{Add, AddAssign, Sub, Neg, Mul, MulAssign, Div};
 
 use rug::Rational;
@@ -34,6 +37,43 @@ impl One for f64 {
   fn one() -> f64 { 1.0 }
 }
 
+/// arithmetic on borrowed values, so that combining big numbers doesn't
+/// mean cloning them first. for rationals, `&a * &b` is one of rug's
+/// incomplete computations, which allocates only for the result.
+pub trait RefOps: Sized {
+  fn add_ref(&self, other: &Self) -> Self;
+  fn sub_ref(&self, other: &Self) -> Self;
+  fn mul_ref(&self, other: &Self) -> Self;
+}
+
+impl RefOps for Rational {
+  fn add_ref(&self, other: &Rational) -> Rational {
+    Rational::from(self + other)
+  }
+
+  fn sub_ref(&self, other: &Rational) -> Rational {
+    Rational::from(self - other)
+  }
+
+  fn mul_ref(&self, other: &Rational) -> Rational {
+    Rational::from(self * other)
+  }
+}
+
+impl RefOps for f64 {
+  fn add_ref(&self, other: &f64) -> f64 {
+    self + other
+  }
+
+  fn sub_ref(&self, other: &f64) -> f64 {
+    self - other
+  }
+
+  fn mul_ref(&self, other: &f64) -> f64 {
+    self * other
+  }
+}
+
 pub trait SquaredNorm {
   type Output;
   fn squared_norm(&self) -> Self::Output;
@@ -51,4 +91,64 @@ impl SquaredNorm for f64 {
   fn squared_norm(&self) -> f64 {
     self * self
   }
-}
\ No newline at end of file
+}
+
+/// comparison of values that may be known only approximately, such as an
+/// interval known to contain the true value. answers are always certain,
+/// so `sign` is `None` when the value might lie on either side of zero.
+/// exact values always know their sign.
+pub trait Compare {
+  /// the sign of the value, if it's certain.
+  fn sign(&self) -> Option<Ordering>;
+
+  /// a value no greater than either `self` or `other`: for exact values,
+  /// the lesser of the two.
+  fn least(self, other: Self) -> Self;
+
+  /// a value no less than either `self` or `other`.
+  fn greatest(self, other: Self) -> Self;
+}
+
+/// how close to zero a float may come before its sign is unknown. floats
+/// here are coordinates of a few units built up by a few hundred
+/// operations, so their rounding errors are far smaller than this.
+pub const TOLERANCE: f64 = 1e-9;
+
+impl Compare for f64 {
+  /// a float within `TOLERANCE` of zero might have either sign once
+  /// rounding is accounted for, except for zero itself, which arithmetic
+  /// on exact values like 0 and 1 gives exactly.
+  fn sign(&self) -> Option<Ordering> {
+    if *self > TOLERANCE {
+      Some(Ordering::Greater)
+    } else if *self < -TOLERANCE {
+      Some(Ordering::Less)
+    } else if *self == 0.0 {
+      Some(Ordering::Equal)
+    } else {
+      None
+    }
+  }
+
+  fn least(self, other: f64) -> f64 {
+    self.min(other)
+  }
+
+  fn greatest(self, other: f64) -> f64 {
+    self.max(other)
+  }
+}
+
+impl Compare for Rational {
+  fn sign(&self) -> Option<Ordering> {
+    Some(self.cmp0())
+  }
+
+  fn least(self, other: Rational) -> Rational {
+    std::cmp::min(self, other)
+  }
+
+  fn greatest(self, other: Rational) -> Rational {
+    std::cmp::max(self, other)
+  }
+}
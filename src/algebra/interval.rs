@@ -0,0 +1,233 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Neg, Mul, Div};
+
+use rug::{Float, Rational};
+use rug::float::{Round, Special};
+use rug::ops::AssignRound;
+
+use crate::algebra::{Compare, Zero, One, RefOps, SquaredNorm};
+
+/// the precision, in bits, of interval endpoints.
+pub const PRECISION: u32 = 128;
+
+/// a closed interval of reals, standing for some unknown value inside it.
+/// every operation rounds its endpoints outward, so the result contains
+/// the result of the operation on any values inside the operands. after
+/// many operations an interval can get too wide to say anything, which
+/// `Compare` reports as an unknown sign.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Interval {
+  lo: Float,
+  hi: Float,
+}
+
+// `val` rounded down and up to an endpoint.
+fn down<T>(val: T) -> Float where Float: AssignRound<T, Round = Round, Ordering = Ordering> {
+  Float::with_val_round(PRECISION, val, Round::Down).0
+}
+
+fn up<T>(val: T) -> Float where Float: AssignRound<T, Round = Round, Ordering = Ordering> {
+  Float::with_val_round(PRECISION, val, Round::Up).0
+}
+
+impl Interval {
+  /// the interval from `lo` to `hi`, which must be in order.
+  pub fn new(lo: &Rational, hi: &Rational) -> Interval {
+    assert!(lo <= hi, "interval endpoints out of order: {} > {}", lo, hi);
+    Interval{lo: down(lo), hi: up(hi)}
+  }
+
+  /// the smallest interval containing `r`, which is just `r` when it's a
+  /// dyadic fraction that fits the precision.
+  pub fn point(r: &Rational) -> Interval {
+    Interval::new(r, r)
+  }
+
+  /// the interval of all reals, e.g. after dividing by an interval that
+  /// contains zero.
+  pub fn everything() -> Interval {
+    Interval{
+      lo: Float::with_val(PRECISION, Special::NegInfinity),
+      hi: Float::with_val(PRECISION, Special::Infinity),
+    }
+  }
+
+  pub fn lo(&self) -> &Float {
+    &self.lo
+  }
+
+  pub fn hi(&self) -> &Float {
+    &self.hi
+  }
+
+  /// hi - lo, rounded up.
+  pub fn width(&self) -> Float {
+    up(&self.hi - &self.lo)
+  }
+
+  pub fn contains(&self, r: &Rational) -> bool {
+    self.lo <= *r && *r <= self.hi
+  }
+
+  fn contains_zero(&self) -> bool {
+    self.lo <= 0 && self.hi >= 0
+  }
+}
+
+impl Zero for Interval {
+  fn zero() -> Interval {
+    Interval{lo: Float::new(PRECISION), hi: Float::new(PRECISION)}
+  }
+}
+
+impl One for Interval {
+  fn one() -> Interval {
+    Interval{lo: Float::with_val(PRECISION, 1), hi: Float::with_val(PRECISION, 1)}
+  }
+}
+
+impl RefOps for Interval {
+  fn add_ref(&self, v: &Interval) -> Interval {
+    Interval{lo: down(&self.lo + &v.lo), hi: up(&self.hi + &v.hi)}
+  }
+
+  fn sub_ref(&self, v: &Interval) -> Interval {
+    Interval{lo: down(&self.lo - &v.hi), hi: up(&self.hi - &v.lo)}
+  }
+
+  fn mul_ref(&self, v: &Interval) -> Interval {
+    // the extremes are among the products of the endpoints.
+    let corners = [(&self.lo, &v.lo), (&self.lo, &v.hi), (&self.hi, &v.lo), (&self.hi, &v.hi)];
+    let lo = corners.iter().map(|(a, b)| down(*a * *b)).reduce(|x, y| x.min(&y)).unwrap();
+    let hi = corners.iter().map(|(a, b)| up(*a * *b)).reduce(|x, y| x.max(&y)).unwrap();
+    Interval{lo, hi}
+  }
+}
+
+impl Add for Interval {
+  type Output = Interval;
+
+  fn add(self, v: Interval) -> Interval {
+    self.add_ref(&v)
+  }
+}
+
+impl<'a> AddAssign<&'a Interval> for Interval {
+  fn add_assign(&mut self, v: &'a Interval) {
+    self.lo = down(&self.lo + &v.lo);
+    self.hi = up(&self.hi + &v.hi);
+  }
+}
+
+impl Neg for Interval {
+  type Output = Interval;
+
+  fn neg(self) -> Interval {
+    Interval{lo: -self.hi, hi: -self.lo}
+  }
+}
+
+impl Mul for Interval {
+  type Output = Interval;
+
+  fn mul(self, v: Interval) -> Interval {
+    self.mul_ref(&v)
+  }
+}
+
+impl Div for Interval {
+  type Output = Interval;
+
+  fn div(self, v: Interval) -> Interval {
+    if v.contains_zero() {
+      return Interval::everything();
+    }
+    let inverse = Interval{lo: down(1 / &v.hi), hi: up(1 / &v.lo)};
+    self * inverse
+  }
+}
+
+impl SquaredNorm for Interval {
+  type Output = Interval;
+
+  fn squared_norm(&self) -> Interval {
+    if self.lo >= 0 {
+      Interval{lo: down(self.lo.square_ref()), hi: up(self.hi.square_ref())}
+    } else if self.hi <= 0 {
+      Interval{lo: down(self.hi.square_ref()), hi: up(self.lo.square_ref())}
+    } else {
+      let hi = up(self.lo.square_ref()).max(&up(self.hi.square_ref()));
+      Interval{lo: Float::new(PRECISION), hi}
+    }
+  }
+}
+
+impl Compare for Interval {
+  fn sign(&self) -> Option<Ordering> {
+    if self.lo > 0 {
+      Some(Ordering::Greater)
+    } else if self.hi < 0 {
+      Some(Ordering::Less)
+    } else if self.lo == 0 && self.hi == 0 {
+      Some(Ordering::Equal)
+    } else {
+      None
+    }
+  }
+
+  fn least(self, other: Interval) -> Interval {
+    Interval{lo: self.lo.min(&other.lo), hi: self.hi.min(&other.hi)}
+  }
+
+  fn greatest(self, other: Interval) -> Interval {
+    Interval{lo: self.lo.max(&other.lo), hi: self.hi.max(&other.hi)}
+  }
+}
+
+impl fmt::Display for Interval {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "[{:.6}, {:.6}]",
+      self.lo.to_f64_round(Round::Down), self.hi.to_f64_round(Round::Up))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn q(n: i32, d: i32) -> Rational {
+    Rational::from((n, d))
+  }
+
+  #[test]
+  fn test_interval() {
+    let third = Interval::point(&q(1, 3));
+    assert!(third.contains(&q(1, 3)));
+    assert!(third.width() > 0, "1/3 isn't dyadic");
+    assert_eq!(Interval::point(&q(3, 4)).width(), 0);
+
+    // outward rounding keeps the true value inside.
+    let sum = third.clone() + third.clone() + third.clone();
+    assert!(sum.contains(&q(1, 1)));
+    assert_eq!(sum.sign(), Some(Ordering::Greater));
+    let difference = sum + -Interval::one();
+    assert!(difference.contains(&q(0, 1)));
+    assert_eq!(difference.sign(), None);
+    assert!(third.sub_ref(&third).contains(&q(0, 1)));
+    assert_eq!(third.sub_ref(&third).sign(), None);
+    assert_eq!((Interval::one() + -Interval::one()).sign(), Some(Ordering::Equal));
+
+    let x = Interval::new(&q(-1, 2), &q(3, 2));
+    let y = Interval::new(&q(2, 1), &q(3, 1));
+    let product = x.clone() * y.clone();
+    assert_eq!((product.lo().to_f64(), product.hi().to_f64()), (-1.5, 4.5));
+    let quotient = y.clone() / y.clone();
+    assert!(quotient.contains(&q(2, 3)) && quotient.contains(&q(3, 2)));
+    assert_eq!(y.clone() / x.clone(), Interval::everything());
+    let square = x.squared_norm();
+    assert_eq!((square.lo().to_f64(), square.hi().to_f64()), (0.0, 2.25));
+    assert_eq!(x.clone().least(y.clone()), x);
+    assert_eq!(x.greatest(y.clone()), y);
+  }
+}
@@ -1,21 +1,27 @@
+pub mod apex_box;
 pub mod base_edge;
 pub mod embedding;
 pub mod homotopy;
+pub mod orbit;
 pub mod singularity;
+pub mod staged;
 pub mod turn_path;
+pub mod words;
 
-use std::cmp::Ord;
-use std::ops::{Add, Sub, Neg, Mul, Div};
+use std::cmp::Ordering;
+use std::ops::{Add, AddAssign, Sub, Neg, Mul, Div};
 
 use rug::Rational;
 
 use crate::util::power_cache::PowerCache;
 use crate::vector::V2;
-use crate::algebra::{Zero, One, SquaredNorm};
-use singularity::{BaseSingularity::{self, B0, B1}, BaseOrientation, BaseValues};
+use crate::algebra::{Compare, Zero, One, RefOps, SquaredNorm};
+use singularity::{BaseSingularity::{self, B0, B1}, BaseOrientation, BaseValues, Singularity};
 
+/// the scalars `Params` and the rest of the billiards core can work over:
+/// exact rationals, or approximations that know when they can't tell.
 pub trait ParamsTrait:
-    Ord +
+    Compare + RefOps + for<'a> AddAssign<&'a Self> +
     SquaredNorm<Output=Self> +
     Add<Output=Self> + Neg<Output=Self> +
     Mul<Output=Self> + Div<Output=Self> +
@@ -28,7 +34,7 @@ pub trait ParamsTrait:
 impl<T> ParamsTrait for T
 where
     T:
-      Ord +
+      Compare + RefOps + for<'a> AddAssign<&'a Self> +
       SquaredNorm<Output=Self> +
       Add<Output=Self> + Neg<Output=Self> +
       Mul<Output=Self> + Div<Output=Self> +
@@ -46,7 +52,20 @@ where
 {
   _apex: V2<K>,
   _rotations: BaseValues<PowerCache<V2<K>>>,
-  _max_turns: BaseValues<u32>,
+  _max_turns: BaseValues<MaxTurn>,
+  // turning around either apex rotates by the same factor, twice the apex
+  // angle.
+  _apex_rotations: PowerCache<V2<K>>,
+  _max_apex_turn: MaxTurn,
+}
+
+// the most turns around a vertex, and whether one more is certainly too
+// many: with approximate scalars the next turn may be too close to a half
+// turn to tell.
+#[derive(Clone, Copy, Debug)]
+struct MaxTurn {
+  turn: u32,
+  certain: bool,
 }
 
 impl<K> Params<K>
@@ -62,50 +81,81 @@ where
     //let left_base = origin.clone();
     let left_edge = apex.clone();
     //let right_base = one.clone();
-    let right_edge = bases[B1].clone() - apex.clone();
+    let right_edge = &bases[B1] - &apex;
     let right_norm = right_edge.squared_norm();
     let left_norm = apex.squared_norm();
-    let left_factor = left_edge.clone() * left_edge / V2::from_real(left_norm);
-    let right_factor = (right_edge.clone() * right_edge / V2::from_real(right_norm)).complex_conjugate();
+    let left_factor = &left_edge * &left_edge / V2::from_real(left_norm);
+    let right_factor = (&right_edge * &right_edge / V2::from_real(right_norm)).complex_conjugate();
+
+    // the triangle's angles sum to a half turn, so twice the apex angle is
+    // a full turn less twice both base angles.
+    let apex_factor = (&left_factor * &right_factor).complex_conjugate();
 
     let left_powers = PowerCache::new(left_factor);
     let right_powers = PowerCache::new(right_factor);
     let mut rotations = BaseValues(left_powers, right_powers);
-    println!("apex: {:#?}", apex);
     let max_turns = BaseValues(
-      Self::_max_turn(&mut rotations[B0]),
-      Self::_max_turn(&mut rotations[B1]));
-    println!("max_turns: {:#?}", max_turns);
+      Self::_max_turn(&mut rotations[B0], 1),
+      Self::_max_turn(&mut rotations[B1], 1));
+    let mut apex_rotations = PowerCache::new(apex_factor);
+    // an apex angle of a right angle or more leaves no room to turn.
+    let max_apex_turn = Self::_max_turn(&mut apex_rotations, 0);
     Params{
       _apex: apex,
       _rotations: rotations,
       _max_turns: max_turns,
+      _apex_rotations: apex_rotations,
+      _max_apex_turn: max_apex_turn,
     }
   }
 
-  fn _max_turn(z: &mut PowerCache<V2<K>>) -> u32 {
-    let mut turn = 1;
+  // the most times `z` can be applied, starting from `least`, before the
+  // rotation reaches a half turn, or might.
+  fn _max_turn(z: &mut PowerCache<V2<K>>, least: u32) -> MaxTurn {
+    let mut turn = least;
     let mut next_turn = turn + 1;
-    while z.get(next_turn).1 > K::zero() {
-      turn = next_turn;
-      next_turn += 1;
+    loop {
+      match z.get(next_turn).1.sign() {
+        Some(Ordering::Greater) => {
+          turn = next_turn;
+          next_turn += 1;
+        },
+        sign => return MaxTurn{turn, certain: sign.is_some()},
+      }
+    }
+  }
+
+  fn _max_turn_of(&self, s: Singularity) -> MaxTurn {
+    match s.base() {
+      Some(base) => self._max_turns[base],
+      None => self._max_apex_turn,
     }
-    turn
   }
 
-  pub fn max_turn_around(&self, s: BaseSingularity) -> u32 {
-    self._max_turns[s]
+  pub fn max_turn_around<S: Into<Singularity>>(&self, s: S) -> u32 {
+    self._max_turn_of(s.into()).turn
+  }
+
+  /// whether turning once more than `max_turn_around(s)` is certainly too
+  /// far, rather than too close to a half turn to tell. always true for
+  /// exact scalars.
+  pub fn max_turn_is_certain<S: Into<Singularity>>(&self, s: S) -> bool {
+    self._max_turn_of(s.into()).certain
   }
 
   pub fn apex(&self) -> &V2<K> {
     &self._apex
   }
 
-  pub fn turn_vec(&mut self, around: BaseSingularity, by: i32) -> V2<K> {
+  pub fn turn_vec<S: Into<Singularity>>(&mut self, around: S, by: i32) -> V2<K> {
+    let rotations = match around.into().base() {
+      Some(base) => &mut self._rotations[base],
+      None => &mut self._apex_rotations,
+    };
     if by < 0 {
-      self._rotations[around].get((-by) as u32).clone().complex_conjugate()
+      rotations.get((-by) as u32).clone().complex_conjugate()
     } else {
-      self._rotations[around].get(by as u32).clone()
+      rotations.get(by as u32).clone()
     }
   }
 }
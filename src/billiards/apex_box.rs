@@ -0,0 +1,161 @@
+use rug::Rational;
+
+use crate::algebra::interval::Interval;
+use crate::billiards::Params;
+use crate::billiards::orbit::{check_path, PathCheck};
+use crate::billiards::turn_path::TurnPath;
+use crate::vector::V2;
+
+// unfolding a path with interval coordinates checks it for a whole box of
+// apexes at once: every interval contains the value it would have for any
+// apex in the box, so a margin that's certainly positive means the path is
+// a periodic orbit throughout. the converse fails, since intervals only
+// grow, so anything else is merely unknown. a box that's unknown may be
+// split into smaller ones, which give tighter intervals.
+
+/// a closed rectangle of apexes [x.0, x.1] x [y.0, y.1].
+#[derive(Clone, PartialEq, Debug)]
+pub struct ApexBox {
+  pub x: (Rational, Rational),
+  pub y: (Rational, Rational),
+}
+
+/// the outcome of checking a path over a whole box of apexes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoxCheck {
+  /// the path is a periodic orbit of the triangle of every apex in the box.
+  Feasible,
+  /// the path may fail somewhere in the box, or the check couldn't tell.
+  Unknown,
+}
+
+impl ApexBox {
+  /// the box with the given ranges, which must be in order and lie above
+  /// the base.
+  pub fn new(x: (Rational, Rational), y: (Rational, Rational)) -> Result<ApexBox, String> {
+    if x.0 > x.1 || y.0 > y.1 {
+      return Err(format!("box bounds out of order: [{}, {}] x [{}, {}]", x.0, x.1, y.0, y.1));
+    }
+    if y.0 <= 0 {
+      return Err(format!("box must lie above the base, but y starts at {}", y.0));
+    }
+    Ok(ApexBox{x, y})
+  }
+
+  /// the apex as an interval vector, standing for every apex in the box.
+  pub fn apex(&self) -> V2<Interval> {
+    V2(Interval::new(&self.x.0, &self.x.1), Interval::new(&self.y.0, &self.y.1))
+  }
+
+  pub fn contains(&self, apex: &V2<Rational>) -> bool {
+    self.x.0 <= apex.0 && apex.0 <= self.x.1 && self.y.0 <= apex.1 && apex.1 <= self.y.1
+  }
+
+  pub fn area(&self) -> Rational {
+    Rational::from(&self.x.1 - &self.x.0) * Rational::from(&self.y.1 - &self.y.0)
+  }
+
+  /// the four boxes made by halving this one in each direction.
+  pub fn quarters(&self) -> Vec<ApexBox> {
+    let halves = |(lo, hi): &(Rational, Rational)| {
+      let mid: Rational = Rational::from(lo + hi) / 2;
+      [(lo.clone(), mid.clone()), (mid, hi.clone())]
+    };
+    let mut quarters = vec![];
+    for x in halves(&self.x).iter() {
+      for y in halves(&self.y).iter() {
+        quarters.push(ApexBox{x: x.clone(), y: y.clone()});
+      }
+    }
+    quarters
+  }
+}
+
+/// unfolds `path` once for every apex in `apex_box`. the result is only
+/// conclusive when it's feasible: see `check_box`.
+pub fn check_path_on_box(path: &TurnPath, apex_box: &ApexBox) -> PathCheck<Interval> {
+  check_path(&mut Params::new(apex_box.apex()), path)
+}
+
+pub fn check_box(path: &TurnPath, apex_box: &ApexBox) -> BoxCheck {
+  if check_path_on_box(path, apex_box).is_feasible() {
+    BoxCheck::Feasible
+  } else {
+    BoxCheck::Unknown
+  }
+}
+
+/// checks `path` on `apex_box`, splitting boxes that come out unknown into
+/// quarters up to `depth` times, and returns the boxes found feasible and
+/// the ones still unknown.
+pub fn check_subdivided(path: &TurnPath, apex_box: &ApexBox, depth: u32) -> (Vec<ApexBox>, Vec<ApexBox>) {
+  let mut feasible = vec![];
+  let mut unknown = vec![];
+  let mut pending = vec![(apex_box.clone(), depth)];
+  while let Some((apex_box, depth)) = pending.pop() {
+    match check_box(path, &apex_box) {
+      BoxCheck::Feasible => feasible.push(apex_box),
+      BoxCheck::Unknown if depth > 0 => {
+        pending.extend(apex_box.quarters().into_iter().map(|quarter| (quarter, depth - 1)));
+      },
+      BoxCheck::Unknown => unknown.push(apex_box),
+    }
+  }
+  (feasible, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn q(n: i32, d: i32) -> Rational {
+    Rational::from((n, d))
+  }
+
+  #[test]
+  fn test_check_box() {
+    // intervals widen quickly as the unfolding goes on, so only small
+    // boxes can be certain.
+    let path: TurnPath = "-2 2 2 -2".parse().unwrap();
+    let around = ApexBox::new(
+      (q(1, 2) - q(1, 100_000), q(1, 2) + q(1, 100_000)),
+      (q(3, 8) - q(1, 100_000), q(3, 8) + q(1, 100_000))).unwrap();
+    assert_eq!(check_box(&path, &around), BoxCheck::Feasible);
+    let margin = check_path_on_box(&path, &around);
+    let exact = check_path(&mut Params::new(V2(q(1, 2), q(3, 8))), &path);
+    assert!(margin.margin().unwrap().contains(exact.margin().unwrap()));
+    let wide = ApexBox::new((q(49, 100), q(51, 100)), (q(37, 100), q(38, 100))).unwrap();
+    assert_eq!(check_box(&path, &wide), BoxCheck::Unknown);
+
+    // a box too big to check at once may pass in pieces.
+    let bigger = ApexBox::new(
+      (q(1, 2) - q(1, 10_000), q(1, 2) + q(1, 10_000)),
+      (q(3, 8) - q(1, 10_000), q(3, 8) + q(1, 10_000))).unwrap();
+    assert_eq!(check_box(&path, &bigger), BoxCheck::Unknown);
+    let (feasible, unknown) = check_subdivided(&path, &bigger, 2);
+    assert!(unknown.is_empty());
+    let total: Rational = feasible.iter().map(ApexBox::area).sum();
+    assert_eq!(total, bigger.area());
+
+    // the orbit fails below y = 0.340625 or so, and boxes containing such
+    // apexes never pass.
+    let failing = V2(q(1, 2), q(3406, 10_000));
+    assert!(!check_path(&mut Params::new(failing.clone()), &path).is_feasible());
+    let across = ApexBox::new(
+      (q(1, 2) - q(1, 100_000), q(1, 2) + q(1, 100_000)),
+      (q(3406, 10_000), q(3407, 10_000))).unwrap();
+    let (_, unknown) = check_subdivided(&path, &across, 1);
+    assert!(unknown.iter().any(|b| b.contains(&failing)));
+
+    // closing up depends on the apex unless the turns cancel around each
+    // vertex, so other paths are never certain, even at a single point.
+    let unstable: TurnPath = "-1 1".parse().unwrap();
+    assert!(check_path(&mut Params::new(V2(q(1, 2), q(3, 8))), &unstable).is_feasible());
+    let point = ApexBox::new((q(1, 2), q(1, 2)), (q(3, 8), q(3, 8))).unwrap();
+    assert_eq!(check_path_on_box(&unstable, &point), PathCheck::Unknown{step: None});
+    assert_eq!(check_box(&path, &point), BoxCheck::Feasible);
+
+    assert!(ApexBox::new((q(1, 2), q(1, 4)), (q(1, 4), q(1, 2))).is_err());
+    assert!(ApexBox::new((q(1, 4), q(1, 2)), (q(0, 1), q(1, 2))).is_err());
+  }
+}
@@ -5,7 +5,8 @@ use crate::vector::V2;
 use crate::algebra::{Zero, One};
 use crate::billiards::{Params, ParamsTrait};
 use crate::billiards::singularity::{
-	BaseSingularity::{self, B0, B1}, BaseOrientation, BaseValues};
+	ApexSingularity::{self, A0, A1}, BaseSingularity::{self, B0, B1}, BaseOrientation, BaseValues,
+	Singularity};
 
 pub struct BaseEdge<'a, R>
 where
@@ -38,42 +39,76 @@ where
   }
 
   pub fn offset(&self) -> V2<K> {
-    self.to_coords() - self.from_coords()
+    &self.coords[self.to()] - &self.coords[self.from()]
   }
 
   pub fn step(&'b mut self, turn: i32) {
-    let degree = turn.abs() as u32;
-    let turn_vec = self.params.turn_vec(self.to(), turn);
-    let new_offset: V2<K> = turn_vec * (-self.offset());
-    
-    let new_to_coords: V2<K> = self.to_coords() + new_offset;
+    let (from, to) = (self.from(), self.to());
+    let turn_vec = self.params.turn_vec(to, turn);
+    // the edge swings around its head, which becomes its tail, so its old
+    // tail moves to the new head.
+    let mut new_to_coords = &self.coords[from] - &self.coords[to];
+    new_to_coords *= &turn_vec;
+    new_to_coords += &self.coords[to];
     self.orientation = self.orientation.reversed();
-    let to = self.to();
-    self.coords[to] = new_to_coords;
+    self.coords[from] = new_to_coords;
   }
 
-  pub fn left_apex(&self) -> V2<K> {
-    let mut apex = self.params.apex().clone();
-    if self.orientation.from() == B1 {
-      apex = apex.complex_conjugate();
+  /// turns the quadrilateral `turn` times around `around`: either the
+  /// vertex the edge points to, as with `step`, or one of the apexes.
+  /// turning around an apex moves both ends of the edge but leaves it
+  /// pointing to the same vertex.
+  pub fn step_around(&'b mut self, around: Singularity, turn: i32) {
+    let apex = match around.apex() {
+      Some(apex) => apex,
+      None => {
+        debug_assert_eq!(around.base(), Some(self.to()), "base turns must be around the next vertex");
+        return self.step(turn);
+      }
+    };
+    let pivot = self.apex_coords(apex);
+    let turn_vec = self.params.turn_vec(apex, turn);
+    for &s in &[B0, B1] {
+      let mut new_coords = &self.coords[s] - &pivot;
+      new_coords *= &turn_vec;
+      new_coords += &pivot;
+      self.coords[s] = new_coords;
     }
-    let offset = self.coords[B1].clone() - &self.coords[B0];
-    self.coords[B0].clone() + apex * offset
   }
 
-  pub fn right_apex(&self) -> V2<K> {
-    let mut apex = self.params.apex().clone();
-    if self.orientation.from() == B0 {
-      apex = apex.complex_conjugate();
+  /// the position of `apex`: A0 is the triangle's apex over the base and A1
+  /// its reflection through the base.
+  pub fn apex_coords(&self, apex: ApexSingularity) -> V2<K> {
+    let mut coords = &self.coords[B1] - &self.coords[B0];
+    match apex {
+      A0 => coords *= self.params.apex(),
+      A1 => coords *= self.params.apex().clone().complex_conjugate(),
+    }
+    coords += &self.coords[B0];
+    coords
+  }
+
+  /// the position of any of the quadrilateral's vertices.
+  pub fn coords(&self, s: Singularity) -> V2<K> {
+    match s.base() {
+      Some(base) => self.coords[base].clone(),
+      None => self.apex_coords(s.apex().unwrap()),
     }
-    let offset = self.coords[B1].clone() - &self.coords[B0];
-    self.coords[B0].clone() + apex * offset
+  }
+
+  pub fn left_apex(&self) -> V2<K> {
+    self.apex_coords(if self.orientation.from() == B0 { A0 } else { A1 })
+  }
+
+  pub fn right_apex(&self) -> V2<K> {
+    self.apex_coords(if self.orientation.from() == B0 { A1 } else { A0 })
   }
 
   pub fn from(&self) -> BaseSingularity {
     self.orientation.from()
   }
 
+  #[allow(clippy::wrong_self_convention)]
   pub fn from_coords(&self) -> V2<K> {
     self.coords[self.orientation.from()].clone()
   }
@@ -144,4 +179,21 @@ mod tests {
     );
 
   }
+
+  #[test]
+  fn test_apex_step() {
+    let mut params = Params::new(
+			V2(Rational::from((1, 2)), Rational::from((1, 2))));
+    let mut edge = BaseEdge::new_default(&mut params);
+    assert_eq!(edge.coords(Singularity::A1), V2(Rational::from((1, 2)), Rational::from((-1, 2))));
+
+    // the apex is a right angle, so turning around it is a half turn.
+    edge.step_around(Singularity::A0, 1);
+    assert_eq!(edge.from(), B0, "apex turns keep the orientation");
+    assert_eq!(edge.from_coords(), V2(Rational::from(1), Rational::from(1)));
+    assert_eq!(edge.to_coords(), V2(Rational::from(0), Rational::from(1)));
+    assert_eq!(edge.left_apex(), V2(Rational::from((1, 2)), Rational::from((1, 2))),
+      "the pivot stays put");
+    assert_eq!(edge.right_apex(), V2(Rational::from((1, 2)), Rational::from((3, 2))));
+  }
 }
\ No newline at end of file
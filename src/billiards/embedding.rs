@@ -1,16 +1,234 @@
-#[macro_use]
-extern crate enum_map;
+use std::cmp::Ordering;
+use std::ops::{Add, Neg, Mul};
 
-use enum_map::EnumMap;
+use enum_map::{enum_map, EnumMap};
 
-mod rational {
+use crate::algebra::{Zero, One, RefOps};
+use crate::billiards::singularity::Singularity::{self, A0, A1, B0, B1};
+use crate::vector::V2;
 
+// the fundamental quadrilateral of the triangle with apex (x, y) is the
+// triangle B0 = (0, 0), B1 = (1, 0), A0 = (x, y) together with its
+// reflection through the base, whose apex is A1 = (x, -y). going
+// widdershins its vertices are B0, A1, B1, A0, and when 0 < x < 1 it's a
+// convex kite.
+
+/// the coordinates of each vertex of a fundamental quadrilateral.
+pub type Embedding<K> = EnumMap<Singularity, V2<K>>;
+
+/// the quadrilateral's vertices in widdershins order, starting from B0.
+pub const WIDDERSHINS: [Singularity; 4] = [B0, A1, B1, A0];
+
+/// the vertex after `s` going widdershins around the quadrilateral.
+pub fn next(s: Singularity) -> Singularity {
+  match s {
+    B0 => A1,
+    A1 => B1,
+    B1 => A0,
+    A0 => B0,
+  }
+}
+
+/// the vertex before `s` going widdershins around the quadrilateral.
+pub fn prev(s: Singularity) -> Singularity {
+  match s {
+    B0 => A0,
+    A1 => B0,
+    B1 => A1,
+    A0 => B1,
+  }
 }
 
-mod float {
-	type Embedding = EnumMap<>
+/// the quadrilateral of the triangle with the given apex.
+pub fn from_apex<K>(apex: &V2<K>) -> Embedding<K>
+where
+    K: Zero + One + Neg<Output=K> + Clone
+{
+  enum_map! {
+    B0 => V2(K::zero(), K::zero()),
+    B1 => V2(K::one(), K::zero()),
+    A0 => apex.clone(),
+    A1 => apex.clone().complex_conjugate(),
+  }
+}
+
+/// the vector along the edge leaving `s` widdershins, to `next(s)`.
+pub fn edge<K>(embedding: &Embedding<K>, s: Singularity) -> V2<K>
+where
+    K: RefOps + Add<Output=K> + Neg<Output=K>
+{
+  &embedding[next(s)] - &embedding[s]
+}
+
+/// every edge, keyed by the vertex it leaves.
+pub fn edges<K>(embedding: &Embedding<K>) -> EnumMap<Singularity, V2<K>>
+where
+    K: RefOps + Add<Output=K> + Neg<Output=K>
+{
+  enum_map! { s => edge(embedding, s) }
+}
+
+// the vectors from `s` to the vertices after and before it.
+fn sides<K>(embedding: &Embedding<K>, s: Singularity) -> (V2<K>, V2<K>)
+where
+    K: RefOps + Add<Output=K> + Neg<Output=K>
+{
+  (edge(embedding, s), &embedding[prev(s)] - &embedding[s])
+}
+
+// the z component of u x v, positive when v is widdershins of u.
+fn cross<K>(u: &V2<K>, v: &V2<K>) -> K
+where
+    K: Add<Output=K> + Neg<Output=K> + Mul<Output=K> + Clone
+{
+  u.0.clone() * v.1.clone() + -(u.1.clone() * v.0.clone())
+}
+
+fn dot<K>(u: &V2<K>, v: &V2<K>) -> K
+where
+    K: Add<Output=K> + Mul<Output=K> + Clone
+{
+  u.0.clone() * v.0.clone() + u.1.clone() * v.1.clone()
+}
+
+/// quadrilaterals with exact coordinates.
+pub mod rational {
+  use super::*;
+  use rug::Rational;
+  use crate::algebra::SquaredNorm;
+
+  pub type Embedding = super::Embedding<Rational>;
+
+  pub fn from_apex(apex: &V2<Rational>) -> Embedding {
+    super::from_apex(apex)
+  }
 
-	pub fn from_apex() -> Embedding {
-		return enum_map! {};
-	}
-}
\ No newline at end of file
+  /// which way the boundary turns at `s`: `Greater` where it turns
+  /// widdershins, as at every vertex of a convex quadrilateral, `Less`
+  /// where it turns clockwise and `Equal` where it runs straight through.
+  pub fn orientation(embedding: &Embedding, s: Singularity) -> Ordering {
+    let (after, before) = sides(embedding, s);
+    cross(&after, &before).cmp(&Rational::from(0))
+  }
+
+  /// whether every vertex turns widdershins, i.e. the apex lies strictly
+  /// over the base.
+  pub fn is_convex(embedding: &Embedding) -> bool {
+    WIDDERSHINS.iter().all(|&s| orientation(embedding, s) == Ordering::Greater)
+  }
+
+  /// the rotation through twice the interior angle at `s`, as a unit
+  /// vector. the angle itself usually has no exact representation, but
+  /// twice it does, and turning around a vertex of the triangle rotates by
+  /// twice the triangle's angle there: this is `Params::turn_vec(A0, 1)`
+  /// at the apexes, while the quadrilateral's angle at a base vertex is
+  /// already twice the triangle's.
+  pub fn double_angle(embedding: &Embedding, s: Singularity) -> V2<Rational> {
+    let (after, before) = sides(embedding, s);
+    // the angle from `after` to `before`, scaled by both their lengths.
+    let w = V2(dot(&after, &before), cross(&after, &before));
+    let norm = w.squared_norm();
+    let w2 = w.clone() * w;
+    V2(w2.0 / &norm, w2.1 / &norm)
+  }
+}
+
+/// quadrilaterals with floating point coordinates, for plotting and for
+/// screening many apexes quickly.
+pub mod float {
+  use super::*;
+  use std::f64::consts::PI;
+  use rug::Rational;
+
+  pub type Embedding = super::Embedding<f64>;
+
+  pub fn from_apex(apex: &V2<f64>) -> Embedding {
+    super::from_apex(apex)
+  }
+
+  /// the nearest floating point quadrilateral to an exact one.
+  pub fn from_exact(embedding: &super::Embedding<Rational>) -> Embedding {
+    enum_map! { s => V2(embedding[s].0.to_f64(), embedding[s].1.to_f64()) }
+  }
+
+  /// which way the boundary turns at `s`, as in `rational::orientation`.
+  /// this is the sign of a rounded product, so it may be wrong for nearly
+  /// straight vertices.
+  pub fn orientation(embedding: &Embedding, s: Singularity) -> Ordering {
+    let (after, before) = sides(embedding, s);
+    let turn = cross(&after, &before);
+    if turn > 0.0 {
+      Ordering::Greater
+    } else if turn < 0.0 {
+      Ordering::Less
+    } else {
+      Ordering::Equal
+    }
+  }
+
+  pub fn is_convex(embedding: &Embedding) -> bool {
+    WIDDERSHINS.iter().all(|&s| orientation(embedding, s) == Ordering::Greater)
+  }
+
+  /// the interior angle at `s` in radians, between 0 and 2pi. the angles
+  /// of the four vertices sum to 2pi.
+  pub fn angle(embedding: &Embedding, s: Singularity) -> f64 {
+    let (after, before) = sides(embedding, s);
+    let angle = cross(&after, &before).atan2(dot(&after, &before));
+    if angle < 0.0 { angle + 2.0 * PI } else { angle }
+  }
+
+  /// the length of the edge leaving `s` widdershins.
+  pub fn edge_length(embedding: &Embedding, s: Singularity) -> f64 {
+    let v = edge(embedding, s);
+    v.0.hypot(v.1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+  use crate::billiards::Params;
+
+  #[test]
+  fn test_embedding() {
+    let apex = V2(Rational::from((1, 3)), Rational::from((1, 2)));
+    let exact = rational::from_apex(&apex);
+    assert_eq!(exact[A1], V2(Rational::from((1, 3)), Rational::from((-1, 2))));
+    assert_eq!(edge(&exact, A0), V2(Rational::from((-1, 3)), Rational::from((-1, 2))));
+    let total = WIDDERSHINS.iter()
+      .map(|&s| edges(&exact)[s].clone())
+      .fold(V2(Rational::from(0), Rational::from(0)), |sum, v| sum + v);
+    assert_eq!(total, V2(Rational::from(0), Rational::from(0)), "the boundary closes up");
+    for &s in &WIDDERSHINS {
+      assert_eq!(prev(next(s)), s);
+    }
+
+    assert!(rational::is_convex(&exact));
+    let mut params = Params::new(apex.clone());
+    assert_eq!(rational::double_angle(&exact, A0), params.turn_vec(A0, 1));
+    assert_eq!(rational::double_angle(&exact, A1), params.turn_vec(A1, 1));
+    assert_eq!(rational::double_angle(&exact, B0), params.turn_vec(B0, 2));
+    let full_turns = WIDDERSHINS.iter()
+      .map(|&s| rational::double_angle(&exact, s))
+      .fold(V2(Rational::from(1), Rational::from(0)), |product, z| product * z);
+    assert_eq!(full_turns, V2(Rational::from(1), Rational::from(0)));
+
+    let float = float::from_exact(&exact);
+    assert_eq!(float[A0], V2(1.0 / 3.0, 0.5));
+    assert!(float::is_convex(&float));
+    let angles: f64 = WIDDERSHINS.iter().map(|&s| float::angle(&float, s)).sum();
+    assert!((angles - 2.0 * std::f64::consts::PI).abs() < 1e-12);
+    assert!((float::angle(&float, A0) - float::angle(&float, A1)).abs() < 1e-12);
+    assert!((float::edge_length(&float, B0) - float::edge_length(&float, A0)).abs() < 1e-12);
+
+    // an apex beyond B1 bends the quadrilateral inward there.
+    let obtuse = rational::from_apex(&V2(Rational::from((3, 2)), Rational::from((1, 2))));
+    assert_eq!(rational::orientation(&obtuse, B1), Ordering::Less);
+    assert!(!rational::is_convex(&obtuse));
+    let on_base = float::from_apex(&V2(1.0, 0.5));
+    assert_eq!(float::orientation(&on_base, B1), Ordering::Equal);
+    assert!((float::angle(&on_base, B1) - std::f64::consts::PI).abs() < 1e-12);
+  }
+}
@@ -0,0 +1,452 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::algebra::{Compare, Zero, One};
+use crate::billiards::{Params, ParamsTrait};
+use crate::billiards::base_edge::BaseEdge;
+use crate::billiards::singularity::{BaseOrientation, BaseSingularity::{B0, B1}, BaseValues, Singularity};
+use crate::billiards::turn_path::{Turn, TurnPath};
+use crate::vector::V2;
+
+// a turn path is a periodic orbit of the triangle when unfolding the
+// triangle along the path ends in a translate of where it started, and a
+// straight line runs through the whole unfolded corridor: past every
+// vertex the path turns around on the correct side. a path that swaps its
+// vertices only gets back to where it started after a second lap around
+// the swapped vertices, so everything is checked over that whole period
+// (see `TurnPath::period`). the line must be parallel to the translation,
+// i.e. to the offset between the first and last copies of the apex, so it
+// suffices to compare the projections of the corridor's two sides onto the
+// normal of that offset.
+
+/// the version of `check_path`'s results. stored results from another
+/// version are computed again, so change this whenever a change to the
+/// check could change its outcome for some path.
+pub const CHECK_VERSION: u32 = 2;
+
+/// the outcome of checking a turn path against a triangle.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PathCheck<K> {
+  /// turn `step` (counting from 0 along the path's `period`, so the
+  /// second lap of a path that swaps starts at its len()) goes further
+  /// around its vertex than the triangle allows, so the path can't be
+  /// unfolded at all.
+  TurnTooLarge{step: usize, turn: Turn, max_turn: u32},
+
+  /// the unfolding doesn't end in a translate of the starting triangle,
+  /// so the path can't repeat.
+  Unclosed,
+
+  /// with approximate scalars, whether turn `step` is too large or
+  /// whether the unfolding closes up was too close to call.
+  Unknown{step: Option<usize>},
+
+  /// the path unfolds into a corridor of the given width. `margin` is
+  /// min(left) - max(right) of the sides' projections onto the normal of
+  /// `offset`, so the corridor's true width is margin / |offset|, and
+  /// the path is feasible exactly when margin > 0.
+  Corridor{margin: K, offset: V2<K>},
+}
+
+impl<K> PathCheck<K>
+where
+    K: ParamsTrait
+{
+  /// whether the path is a periodic orbit. with approximate scalars this
+  /// means certainly, and anything else is unknown.
+  pub fn is_feasible(&self) -> bool {
+    self.feasibility() == Some(true)
+  }
+
+  /// whether the path is a periodic orbit, or `None` if some comparison
+  /// was too close to call. exact scalars always know.
+  pub fn feasibility(&self) -> Option<bool> {
+    match self {
+      PathCheck::TurnTooLarge{..} | PathCheck::Unclosed => Some(false),
+      PathCheck::Unknown{..} => None,
+      PathCheck::Corridor{margin, ..} => margin.sign().map(|sign| sign == Ordering::Greater),
+    }
+  }
+
+  /// the corridor margin, if the path could be unfolded.
+  pub fn margin(&self) -> Option<&K> {
+    match self {
+      PathCheck::TurnTooLarge{..} | PathCheck::Unclosed | PathCheck::Unknown{..} => None,
+      PathCheck::Corridor{margin, ..} => Some(margin),
+    }
+  }
+
+  /// the turn that exceeded its vertex's maximum, if any.
+  pub fn failing_step(&self) -> Option<usize> {
+    match self {
+      PathCheck::TurnTooLarge{step, ..} => Some(*step),
+      PathCheck::Unclosed | PathCheck::Unknown{..} | PathCheck::Corridor{..} => None,
+    }
+  }
+}
+
+impl<K> fmt::Display for PathCheck<K>
+where
+    K: ParamsTrait + fmt::Display
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PathCheck::TurnTooLarge{step, turn, max_turn} => write!(f,
+        "infeasible: turn {} ({}) exceeds the maximum of {} around {:?}",
+        step + 1, turn, max_turn, turn.s),
+      PathCheck::Unclosed => write!(f, "infeasible: the unfolding doesn't close up"),
+      PathCheck::Unknown{step: Some(step)} => write!(f,
+        "unknown: turn {} is too close to the maximum to tell", step + 1),
+      PathCheck::Unknown{step: None} => write!(f,
+        "unknown: the unfolding is too close to closing up to tell"),
+      PathCheck::Corridor{margin, ..} => write!(f, "{}: margin {}",
+        match self.feasibility() {
+          Some(true) => "feasible",
+          Some(false) => "infeasible",
+          None => "unknown",
+        },
+        margin),
+    }
+  }
+}
+
+fn dot<K: ParamsTrait>(u: &V2<K>, v: &V2<K>) -> K {
+  u.0.clone() * v.0.clone() + u.1.clone() * v.1.clone()
+}
+
+/// whether unfolding along `path` ends in a translate of the starting
+/// triangle, i.e. whether the turns' rotations compose to the identity,
+/// or `None` if that's too close to call.
+pub fn closes<K>(params: &mut Params<K>, path: &TurnPath) -> Option<bool>
+where
+    K: ParamsTrait
+{
+  let (b0, b1) = closing_turns(path);
+  turns_close(params, b0, b1)
+}
+
+/// the total turning around B0 and B1 whose rotations must cancel for
+/// `path` to close up. rotations commute, so only these totals matter. a
+/// path that swaps its vertices only closes up after going around a second
+/// time, by which point it has turned around each base vertex by the
+/// path's total base turning. the triangle's angles sum to a half turn, so
+/// a turn around either apex rotates as far as turning back once around
+/// each base vertex, and is counted that way.
+pub fn closing_turns(path: &TurnPath) -> (i64, i64) {
+  let sums = path.turning_by_singularity();
+  let apex = path.apex_turning();
+  if path.swaps() {
+    let base = sums[B0] + sums[B1] - 2 * apex;
+    (base, base)
+  } else {
+    (sums[B0] - apex, sums[B1] - apex)
+  }
+}
+
+/// whether turning `b0` times around B0 and `b1` times around B1 rotates
+/// by a whole number of full turns, or `None` if that's too close to call.
+/// both must fit an i32, as the `closing_turns` of any path do.
+pub fn turns_close<K>(params: &mut Params<K>, b0: i64, b1: i64) -> Option<bool>
+where
+    K: ParamsTrait
+{
+  let narrow = |turns: i64| i32::try_from(turns).expect("turns should fit an i32");
+  let rotation = params.turn_vec(B0, narrow(b0)) * params.turn_vec(B1, narrow(b1));
+  let signs = [(rotation.0 + -K::one()).sign(), rotation.1.sign()];
+  if signs.iter().any(|sign| matches!(sign, Some(Ordering::Less) | Some(Ordering::Greater))) {
+    Some(false)
+  } else if signs.iter().all(|sign| *sign == Some(Ordering::Equal)) {
+    Some(true)
+  } else {
+    None
+  }
+}
+
+/// the position of the unfolded base edge before the first turn or after
+/// one of the turns.
+#[derive(Clone, PartialEq, Debug)]
+pub struct UnfoldStep<K> {
+  // the turn just taken, or none for the starting position.
+  pub turn: Option<Turn>,
+  pub from: V2<K>,
+  pub to: V2<K>,
+  pub left_apex: V2<K>,
+  pub right_apex: V2<K>,
+  // the vertex just turned around: the one the edge now starts from, or
+  // an apex.
+  pub pivot: Option<V2<K>>,
+}
+
+impl<K> UnfoldStep<K>
+where
+    K: ParamsTrait
+{
+  fn new(edge: &BaseEdge<K>, turn: Option<Turn>) -> UnfoldStep<K> {
+    UnfoldStep{
+      turn,
+      from: edge.from_coords(),
+      to: edge.to_coords(),
+      left_apex: edge.left_apex(),
+      right_apex: edge.right_apex(),
+      pivot: turn.map(|turn| edge.coords(turn.s)),
+    }
+  }
+
+  /// whether the vertex just turned around lies on the left side of the
+  /// corridor.
+  pub fn pivot_is_left(&self) -> Option<bool> {
+    self.turn.map(|turn| turn.degree > 0)
+  }
+}
+
+/// unfolds the triangle of `params` along `path`, starting from the base
+/// (0,0) -> (1,0) pointing at the vertex of the first base turn. the
+/// result has the starting position and then one step per turn.
+pub fn unfold<K>(params: &mut Params<K>, path: &TurnPath) -> Vec<UnfoldStep<K>>
+where
+    K: ParamsTrait
+{
+  let forward = match path.first_base() {
+    Some(first) => first == B1,
+    // a path around the apexes alone could run either way along the edge,
+    // so it runs the way that puts its first apex on the side it turns
+    // toward.
+    None => (path.first() == Singularity::A0) == (path.turns()[0].degree > 0),
+  };
+  let orientation = if forward {
+    BaseOrientation::Forward
+  } else {
+    BaseOrientation::Backward
+  };
+  let mut edge = BaseEdge::new(
+    params,
+    BaseValues(
+      V2(K::zero(), K::zero()),
+      V2(K::one(), K::zero()),
+    ),
+    orientation,
+  );
+  let mut steps = vec![UnfoldStep::new(&edge, None)];
+  for turn in path.iter() {
+    // `TurnPath::new` keeps every degree within MAX_TURNING.
+    edge.step_around(turn.s, i32::try_from(turn.degree).unwrap());
+    steps.push(UnfoldStep::new(&edge, Some(*turn)));
+  }
+  steps
+}
+
+/// the points that must lie on each side of the line through an unfolded
+/// path, as (left, right): every copy of the apex, and each vertex the
+/// path turns around on the side it turns toward. an apex turned around on
+/// the wrong side ends up on both, leaving no room for the line.
+pub fn corridor_sides<K>(steps: &[UnfoldStep<K>]) -> (Vec<V2<K>>, Vec<V2<K>>)
+where
+    K: ParamsTrait
+{
+  let mut left_points = vec![];
+  let mut right_points = vec![];
+  for step in steps {
+    left_points.push(step.left_apex.clone());
+    right_points.push(step.right_apex.clone());
+    match (step.pivot_is_left(), &step.pivot) {
+      (Some(true), Some(pivot)) => left_points.push(pivot.clone()),
+      (Some(false), Some(pivot)) => right_points.push(pivot.clone()),
+      _ => {},
+    }
+  }
+  (left_points, right_points)
+}
+
+/// the translation taking the start of an unfolded path to its end.
+pub fn translation<K>(steps: &[UnfoldStep<K>]) -> V2<K>
+where
+    K: ParamsTrait
+{
+  steps.last().unwrap().left_apex.clone() - &steps[0].left_apex
+}
+
+/// the position of `v` across the corridor of an unfolded path with the
+/// given translation, increasing to the left. the corridor's margin is
+/// the least of these over its left side less the greatest over its
+/// right side.
+pub fn projection<K>(v: &V2<K>, offset: &V2<K>) -> K
+where
+    K: ParamsTrait
+{
+  dot(v, &V2(-offset.1.clone(), offset.0.clone()))
+}
+
+/// unfolds the triangle of `params` along one period of `path` and checks
+/// whether the path is a periodic orbit.
+pub fn check_path<K>(params: &mut Params<K>, path: &TurnPath) -> PathCheck<K>
+where
+    K: ParamsTrait
+{
+  let period = path.period();
+  for (step, turn) in period.iter().enumerate() {
+    let max_turn = params.max_turn_around(turn.s);
+    if turn.degree.unsigned_abs() > max_turn as u64 {
+      // only the first turn past the maximum can be in doubt.
+      if turn.degree.unsigned_abs() == max_turn as u64 + 1 && !params.max_turn_is_certain(turn.s) {
+        return PathCheck::Unknown{step: Some(step)};
+      }
+      return PathCheck::TurnTooLarge{step, turn: *turn, max_turn};
+    }
+  }
+  match closes(params, path) {
+    Some(true) => {},
+    Some(false) => return PathCheck::Unclosed,
+    None => return PathCheck::Unknown{step: None},
+  }
+
+  let steps = unfold(params, &period);
+  let (left_points, right_points) = corridor_sides(&steps);
+  let offset = translation(&steps);
+  let left = left_points.iter().map(|v| projection(v, &offset)).reduce(K::least).unwrap();
+  let right = right_points.iter().map(|v| projection(v, &offset)).reduce(K::greatest).unwrap();
+  PathCheck::Corridor{margin: left + -right, offset}
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+  use crate::billiards::singularity::BaseSingularity;
+
+  fn check(x: (i32, i32), y: (i32, i32), path: &str) -> PathCheck<Rational> {
+    let mut params = Params::new(V2(Rational::from(x), Rational::from(y)));
+    check_path(&mut params, &path.parse().unwrap())
+  }
+
+  #[test]
+  fn test_check_path() {
+    let feasible = check((1, 2), (3, 8), "-2 2 2 -2");
+    assert!(feasible.is_feasible(), "{}", feasible);
+    assert!(*feasible.margin().unwrap() > 0);
+    assert_eq!(feasible.failing_step(), None);
+
+    let infeasible = check((1, 2), (5, 16), "-2 2 2 -2");
+    assert!(!infeasible.is_feasible(), "{}", infeasible);
+    assert!(*infeasible.margin().unwrap() <= 0);
+
+    // with the apex (1/2, 1/2) each base angle is 45 degrees, and a second
+    // turn would rotate the edge through a half turn.
+    let too_large = check((1, 2), (1, 2), "1 -2");
+    assert_eq!(too_large, PathCheck::TurnTooLarge{
+      step: 1, turn: Turn::new(-2, BaseSingularity::B0), max_turn: 1});
+    assert_eq!(too_large.failing_step(), Some(1));
+    assert_eq!(too_large.margin(), None);
+
+    // turning around one vertex only never comes back.
+    assert_eq!(check((1, 2), (3, 8), "1"), PathCheck::Unclosed);
+    assert_eq!(check((1, 2), (3, 8), "-2 1"), PathCheck::Unclosed);
+
+    // the unfolding of an orbit ends in a translate of where it started.
+    let mut params = Params::new(V2(Rational::from((1, 2)), Rational::from((3, 8))));
+    let steps = unfold(&mut params, &"-2 2 2 -2".parse().unwrap());
+    assert_eq!(steps.len(), 5);
+    assert_eq!(steps[0].turn, None);
+    assert_eq!(steps[1].pivot_is_left(), Some(false));
+    let offset = translation(&steps);
+    assert_eq!(offset, V2(Rational::from((2304, 625)), Rational::from(0)));
+    assert_eq!(steps[4].from, steps[0].from.clone() + &offset);
+    let (left, right) = corridor_sides(&steps);
+    assert_eq!((left.len(), right.len()), (7, 7));
+    assert_eq!(projection(&steps[0].left_apex, &offset), Rational::from((864, 625)));
+
+    // rotations of a periodic path are the same orbit.
+    for path in &["-1 1", "1 -1", "-1 1 -1 1"] {
+      assert!(check((1, 2), (3, 8), path).is_feasible(), "{}", path);
+    }
+  }
+
+  #[test]
+  fn test_apex_turns() {
+    // an orbit of an acute triangle turning once around each vertex.
+    let path: TurnPath = "B1^1 B0^1 A0^1".parse().unwrap();
+    let feasible = check((1, 2), (3, 4), "B1^1 B0^1 A0^1");
+    assert_eq!(feasible.margin(), Some(&Rational::from((270, 169))));
+    for image in path.symmetries().iter().take(2 * path.len()) {
+      assert_eq!(check((1, 2), (3, 4), &image.to_string()).margin(), feasible.margin(), "{}", image);
+    }
+    // the apex is on the left, so turning right around it can't work.
+    assert!(!check((1, 2), (3, 4), "B1^1 B0^1 A0^-1").is_feasible());
+    assert!(check((1, 2), (3, 4), "A0^1 A1^-1").is_feasible());
+    assert!(check((1, 2), (3, 4), "A0^-1 A1^1").is_feasible());
+
+    // an apex turn rotates like turning back around both base vertices.
+    assert_eq!(closing_turns(&path), (0, 0));
+    assert_eq!(closing_turns(&"B1^1 A0^1 B0^-1 A1^1".parse().unwrap()), (-3, -1));
+
+    // the apex pivot stays put, and lies on the side turned toward.
+    let mut params = Params::new(V2(Rational::from((1, 2)), Rational::from((3, 4))));
+    let steps = unfold(&mut params, &path);
+    assert_eq!(steps[3].pivot.as_ref(), Some(&steps[2].left_apex));
+    assert_eq!(steps[3].pivot_is_left(), Some(true));
+
+    // an obtuse apex leaves no room to turn at all.
+    let mut params = Params::new(V2(Rational::from((1, 2)), Rational::from((1, 4))));
+    assert_eq!(params.max_turn_around(Singularity::A1), 0);
+    assert_eq!(check_path(&mut params, &path), PathCheck::TurnTooLarge{
+      step: 2, turn: Turn::new(1, Singularity::A0), max_turn: 0});
+  }
+
+  #[test]
+  fn test_odd_period() {
+    // an odd word turns around the swapped vertices on its second lap, so
+    // "-4 -1 2 -1 4" turns B0^-4 there, past the limit of 1 around B0.
+    let apex = V2(Rational::from((5, 32)), Rational::from((9, 32)));
+    let path: TurnPath = "-4 -1 2 -1 4".parse().unwrap();
+    let mut params = Params::new(apex.clone());
+    assert_eq!(params.max_turn_around(B0), 1);
+    assert_eq!(check_path(&mut params, &path), PathCheck::TurnTooLarge{
+      step: 5, turn: Turn::new(-4, BaseSingularity::B0), max_turn: 1});
+
+    // checking a path means checking its whole period, which doesn't swap.
+    for word in &["1 1 1", "-2 1 -1", "B1^-1 A1^-1 B0^1 A0^1 B1^2"] {
+      let path: TurnPath = word.parse().unwrap();
+      let period = path.period();
+      assert!(!period.swaps());
+      for apex in &[V2(Rational::from((1, 2)), Rational::from((3, 8))), apex.clone()] {
+        let mut params = Params::new(apex.clone());
+        assert_eq!(check_path(&mut params, &path), check_path(&mut params, &period), "{}", word);
+      }
+    }
+  }
+
+  #[test]
+  fn test_float_check() {
+    // floats agree with exact arithmetic away from the boundaries.
+    let cases = [
+      ((1, 2), (3, 8), "-2 2 2 -2"),
+      ((1, 2), (3, 10), "-2 2 2 -2"),
+      ((19, 32), (5, 16), "B0^-3 B1^-2 B0^3 B1^2"),
+      ((1, 2), (3, 4), "B1^1 B0^1 A0^1"),
+      ((1, 3), (1, 2), "-1 1"),
+      ((1, 2), (1, 2), "1 -2"),
+    ];
+    for (x, y, path) in cases.iter() {
+      let exact = check(*x, *y, path);
+      let apex = V2(Rational::from(*x).to_f64(), Rational::from(*y).to_f64());
+      let float = check_path(&mut Params::new(apex), &path.parse().unwrap());
+      assert_eq!(float.feasibility(), exact.feasibility(), "{}", path);
+      assert_eq!(float.failing_step(), exact.failing_step(), "{}", path);
+      if let (Some(float), Some(exact)) = (float.margin(), exact.margin()) {
+        assert!((float - exact.to_f64()).abs() < 1e-12, "{}", path);
+      }
+    }
+
+    // an apex a hair off the axis of symmetry can't be told apart from
+    // one on it, where "-1 1" closes up.
+    let close = check_path(&mut Params::new(V2(0.5 + 1e-12, 0.375)), &"-1 1".parse().unwrap());
+    assert_eq!(close, PathCheck::Unknown{step: None});
+    assert_eq!(close.feasibility(), None);
+    assert!(!close.is_feasible());
+
+    // base angles of 30 degrees make three turns a half turn, near enough.
+    let mut params = Params::new(V2(0.5, 0.5 / 3f64.sqrt()));
+    assert!(!params.max_turn_is_certain(B0));
+    assert_eq!(check_path(&mut params, &"3 -3".parse().unwrap()), PathCheck::Unknown{step: Some(0)});
+  }
+}
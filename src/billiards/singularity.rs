@@ -1,7 +1,9 @@
 use std::borrow::Borrow;
 use std::ops::{Index, IndexMut};
 
-#[derive(PartialEq, Debug)]
+use enum_map::Enum;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub enum BaseSingularity {
 	B0,
 	B1,
@@ -11,24 +13,40 @@ impl BaseSingularity {
 	/*fn Vals<T>(v0: T, v1: T) {
 
 	}*/
+
+	pub fn other(&self) -> BaseSingularity {
+		match self {
+			BaseSingularity::B0 => BaseSingularity::B1,
+			BaseSingularity::B1 => BaseSingularity::B0,
+		}
+	}
 }
 
 #[derive(Debug)]
 pub struct BaseValues<T>(pub T, pub T);
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub enum ApexSingularity {
 	A0,
 	A1,
 }
 
+impl ApexSingularity {
+	pub fn other(&self) -> ApexSingularity {
+		match self {
+			ApexSingularity::A0 => ApexSingularity::A1,
+			ApexSingularity::A1 => ApexSingularity::A0,
+		}
+	}
+}
+
 // Singularity represents the four distinct singularities induced by the
 // vertices of the fundamental quadrilateral. For obtuse triangular billiards,
 // we label the base vertices B0 and B1, and the apex A0, then reflect through
 // the base to produce the conjugate apex A1. Thus the oriented (widdershins)
 // quadrilateral vertices are B0-A1-B1-A0, while the original triangle is
 // B0-B1-A0.
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Enum)]
 pub enum Singularity {
 	B0,
 	B1,
@@ -36,6 +54,47 @@ pub enum Singularity {
 	A1,
 }
 
+impl Singularity {
+	/// the base vertex this is, if it is one.
+	pub fn base(&self) -> Option<BaseSingularity> {
+		match self {
+			Singularity::B0 => Some(BaseSingularity::B0),
+			Singularity::B1 => Some(BaseSingularity::B1),
+			Singularity::A0 | Singularity::A1 => None,
+		}
+	}
+
+	/// the apex this is, if it is one.
+	pub fn apex(&self) -> Option<ApexSingularity> {
+		match self {
+			Singularity::A0 => Some(ApexSingularity::A0),
+			Singularity::A1 => Some(ApexSingularity::A1),
+			Singularity::B0 | Singularity::B1 => None,
+		}
+	}
+
+	/// the vertex in the same place once the quadrilateral is turned end to
+	/// end: B0 and B1 trade places, and so do A0 and A1.
+	pub fn other(&self) -> Singularity {
+		match self {
+			Singularity::B0 => Singularity::B1,
+			Singularity::B1 => Singularity::B0,
+			Singularity::A0 => Singularity::A1,
+			Singularity::A1 => Singularity::A0,
+		}
+	}
+
+	/// the vertex in the same place once the quadrilateral is reflected
+	/// through its base: the apexes trade places.
+	pub fn conjugate(&self) -> Singularity {
+		match self {
+			Singularity::A0 => Singularity::A1,
+			Singularity::A1 => Singularity::A0,
+			s => *s,
+		}
+	}
+}
+
 impl From<BaseSingularity> for Singularity {
 	fn from(s: BaseSingularity) -> Singularity {
 		match s {
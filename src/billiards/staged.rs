@@ -0,0 +1,196 @@
+use std::fmt;
+use std::ops::AddAssign;
+
+use rug::Rational;
+
+use crate::algebra::{TOLERANCE, SquaredNorm};
+use crate::billiards::Params;
+use crate::billiards::orbit::{check_path, closing_turns, PathCheck};
+use crate::billiards::turn_path::TurnPath;
+use crate::vector::V2;
+
+// checking a path with floats is far faster than with rationals, and for
+// most apexes the answer is nowhere near the boundary, so a staged check
+// unfolds the path with floats first and only unfolds it again exactly
+// when the float check looks close. a float margin is trusted only when
+// it clears `margin_bound`, which grows with the unfolding: the margin is a
+// difference of projections scaled by the length of the translation, so
+// its rounding error grows with the translation's squared length. the
+// bound is a rule of thumb rather than a proven error bound, so a float
+// answer is very likely but not certain to match the exact one.
+
+/// which arithmetic settled a staged check.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+  Float,
+  Exact,
+}
+
+/// the outcome of a staged check, from whichever stage settled it.
+#[derive(Clone, PartialEq, Debug)]
+pub enum StagedCheck {
+  Float(PathCheck<f64>),
+  Exact(PathCheck<Rational>),
+}
+
+impl StagedCheck {
+  /// whether the path is a periodic orbit. this is exact when the check
+  /// fell back to rationals, and otherwise as good as `margin_bound`.
+  pub fn is_feasible(&self) -> bool {
+    match self {
+      StagedCheck::Float(check) => check.is_feasible(),
+      StagedCheck::Exact(check) => check.is_feasible(),
+    }
+  }
+
+  /// the corridor margin, approximately, if the path could be unfolded.
+  pub fn margin(&self) -> Option<f64> {
+    match self {
+      StagedCheck::Float(check) => check.margin().cloned(),
+      StagedCheck::Exact(check) => check.margin().map(Rational::to_f64),
+    }
+  }
+
+  pub fn stage(&self) -> Stage {
+    match self {
+      StagedCheck::Float(_) => Stage::Float,
+      StagedCheck::Exact(_) => Stage::Exact,
+    }
+  }
+}
+
+/// how many staged checks each stage settled.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct StageStats {
+  pub float: u64,
+  // the checks that fell back to exact arithmetic.
+  pub exact: u64,
+}
+
+impl StageStats {
+  pub fn record(&mut self, stage: Stage) {
+    match stage {
+      Stage::Float => self.float += 1,
+      Stage::Exact => self.exact += 1,
+    }
+  }
+
+  pub fn total(&self) -> u64 {
+    self.float + self.exact
+  }
+
+  /// the fraction of checks that fell back to exact arithmetic.
+  pub fn fallback_rate(&self) -> f64 {
+    if self.total() == 0 { 0.0 } else { self.exact as f64 / self.total() as f64 }
+  }
+}
+
+impl AddAssign for StageStats {
+  fn add_assign(&mut self, other: StageStats) {
+    self.float += other.float;
+    self.exact += other.exact;
+  }
+}
+
+impl fmt::Display for StageStats {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} checks, {} of them ({:.2}%) needing exact arithmetic",
+      self.total(), self.exact, 100.0 * self.fallback_rate())
+  }
+}
+
+/// how far from zero a float margin must be to trust its sign, for a
+/// corridor with the given translation. this is a heuristic scaled to the
+/// expected rounding error, not a rigorous bound on it.
+pub fn margin_bound(offset: &V2<f64>) -> f64 {
+  TOLERANCE * offset.squared_norm().max(1.0)
+}
+
+/// the params of one triangle in both floats and rationals. the exact
+/// params are only computed once some check needs them.
+pub struct StagedParams {
+  apex: V2<Rational>,
+  float: Params<f64>,
+  exact: Option<Params<Rational>>,
+}
+
+impl StagedParams {
+  pub fn new(apex: V2<Rational>) -> StagedParams {
+    let float = Params::new(V2(apex.0.to_f64(), apex.1.to_f64()));
+    StagedParams{apex, float, exact: None}
+  }
+
+  pub fn apex(&self) -> &V2<Rational> {
+    &self.apex
+  }
+
+  pub fn exact(&mut self) -> &mut Params<Rational> {
+    let apex = &self.apex;
+    self.exact.get_or_insert_with(|| Params::new(apex.clone()))
+  }
+
+  /// checks `path` with floats, and again exactly if the floats can't
+  /// tell, counting the stage that settled it in `stats`.
+  pub fn check(&mut self, path: &TurnPath, stats: &mut StageStats) -> StagedCheck {
+    let float = check_path(&mut self.float, path);
+    let trusted = match &float {
+      PathCheck::Unknown{..} => false,
+      // unless its turns cancel outright, a path closes up only on some
+      // curve of apexes, such as the axis of symmetry, and the nearest
+      // float apex may lie on it when the exact one doesn't.
+      PathCheck::Corridor{margin, offset} =>
+        closing_turns(path) == (0, 0) && margin.abs() > margin_bound(offset),
+      PathCheck::TurnTooLarge{..} | PathCheck::Unclosed => true,
+    };
+    let check = if trusted {
+      StagedCheck::Float(float)
+    } else {
+      StagedCheck::Exact(check_path(self.exact(), path))
+    };
+    stats.record(check.stage());
+    check
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn q(n: i32, d: i32) -> Rational {
+    Rational::from((n, d))
+  }
+
+  #[test]
+  fn test_staged_check() {
+    let mut stats = StageStats::default();
+    let path: TurnPath = "-2 2 2 -2".parse().unwrap();
+    let mut params = StagedParams::new(V2(q(1, 2), q(3, 8)));
+    let check = params.check(&path, &mut stats);
+    assert_eq!(check.stage(), Stage::Float);
+    assert!(check.is_feasible());
+    let exact = check_path(params.exact(), &path);
+    assert!((check.margin().unwrap() - exact.margin().unwrap().to_f64()).abs() < 1e-12);
+    assert_eq!(params.check(&"1 -1 1".parse().unwrap(), &mut stats).stage(), Stage::Float);
+    assert_eq!(stats, StageStats{float: 2, exact: 0});
+
+    // "-1 1" closes up on the axis of symmetry, which floats can't be sure
+    // the apex lies on.
+    let check = params.check(&"-1 1".parse().unwrap(), &mut stats);
+    assert_eq!(check.stage(), Stage::Exact);
+    assert!(check.is_feasible());
+
+    // the orbit fails below y = 0.3406250193 or so, and near there the
+    // margin is within the bound.
+    let failing = V2(q(1, 2), Rational::from_f64(0.340_625_019_3).unwrap());
+    let check = StagedParams::new(failing.clone()).check(&path, &mut stats);
+    assert_eq!(check.stage(), Stage::Exact);
+    assert_eq!(check.is_feasible(), check_path(&mut Params::new(failing), &path).is_feasible());
+
+    assert_eq!(stats, StageStats{float: 2, exact: 2});
+    assert_eq!(stats.fallback_rate(), 0.5);
+    let mut total = StageStats::default();
+    total += stats;
+    total += stats;
+    assert_eq!(total.total(), 8);
+  }
+}
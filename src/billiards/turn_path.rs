@@ -1,12 +1,517 @@
-use crate::billiards::BaseSingularity;
-/*
-pub struct TurnPath {
-	pub struct Step {
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Sub;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::algebra::One;
+use crate::billiards::singularity::{BaseSingularity::{self, B0, B1}, BaseValues, Singularity};
+use crate::vector::V2;
+
+// a turn path (or word) describes a billiards trajectory by how far it
+// turns around each vertex of the quadrilateral it passes. turns around
+// the base vertices alternate between them, so a path of base turns alone
+// is determined by its degrees and the vertex of its first turn. there
+// are two ways to write one:
+//
+//   "-2 2 2 -2"           plain degrees, the first turn being around B1 as
+//                         for a `BaseEdge` starting from B0 -> B1
+//   "B1^-2 B0^2 B1^2 B0^-2"
+//                         explicit vertices, which may start from either
+//                         and may turn around the apexes A0 and A1 too,
+//                         e.g. "B1^-1 A1^-1 B0^1 A0^1"
+//
+// both forms may separate turns with spaces or commas. paths of base turns
+// starting at B1 are displayed in the plain form, others in the explicit
+// one.
+
+/// the most a path may turn in all, counting every turn's absolute degree.
+/// this keeps each degree, each sum of degrees and the closing turns (at
+/// most twice the total) within the i32 that `BaseEdge::step` takes.
+pub const MAX_TURNING: i64 = (i32::MAX / 2) as i64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Turn {
+	// The vertex this turn rotates around.
+	pub s: Singularity,
+
+	// degree is the exponent of this turn relative to a given triangle's
+	// rotation coefficients: positive degrees turn widdershins and negative
+	// ones clockwise.
+	pub degree: i64,
+}
+
+impl Turn {
+	pub fn new<S: Into<Singularity>>(degree: i64, around: S) -> Turn {
+		Turn{s: around.into(), degree}
+	}
 
+	/// the same turn around the vertex in the same place once the
+	/// quadrilateral is turned end to end.
+	pub fn mirrored(&self) -> Turn {
+		Turn::new(self.degree, self.s.other())
 	}
+}
+
+impl fmt::Display for Turn {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?}^{}", self.s, self.degree)
+	}
+}
 
+/// a nonempty sequence of nonzero turns, each around a different vertex
+/// than the one before, whose turns around base vertices alternate.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TurnPath {
+	turns: Vec<Turn>,
 }
 
 impl TurnPath {
+	/// checks that `turns` is a valid path.
+	pub fn new(turns: Vec<Turn>) -> Result<TurnPath, String> {
+		if turns.is_empty() {
+			return Err("a turn path needs at least one turn".to_string());
+		}
+		let mut last_base: Option<BaseSingularity> = None;
+		let mut turning: u64 = 0;
+		for (i, turn) in turns.iter().enumerate() {
+			if turn.degree == 0 {
+				return Err(format!("turn {} has degree 0", i + 1));
+			}
+			turning = turning.saturating_add(turn.degree.unsigned_abs());
+			if turning > MAX_TURNING as u64 {
+				return Err(format!("the path turns more than {} times in all", MAX_TURNING));
+			}
+			if i > 0 && turn.s == turns[i - 1].s {
+				return Err(format!(
+					"turns {} and {} are both around {:?}, but turns must alternate",
+					i, i + 1, turn.s));
+			}
+			if let Some(base) = turn.s.base() {
+				if last_base == Some(base) {
+					return Err(format!(
+						"turn {} is around {:?} again, but turns around base vertices must alternate",
+						i + 1, base));
+				}
+				last_base = Some(base);
+			}
+		}
+		// the path repeats, so its last turn is followed by its first.
+		let next = if swaps(&turns) { turns[0].mirrored() } else { turns[0] };
+		if next.s == turns[turns.len() - 1].s {
+			return Err(format!(
+				"the last turn is around {:?}, and so is the first when the path repeats",
+				next.s));
+		}
+		Ok(TurnPath{turns})
+	}
+
+	/// the path with the given degrees, starting around `first`.
+	pub fn from_degrees(first: BaseSingularity, degrees: &[i64]) -> Result<TurnPath, String> {
+		let mut s = first;
+		let turns = degrees.iter().map(|&degree| {
+			let turn = Turn::new(degree, s);
+			s = s.other();
+			turn
+		}).collect();
+		TurnPath::new(turns)
+	}
+
+	pub fn from_turns(turns: &[Turn]) -> Result<TurnPath, String> {
+		TurnPath::new(turns.to_vec())
+	}
+
+	pub fn turns(&self) -> &[Turn] {
+		&self.turns
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, Turn> {
+		self.turns.iter()
+	}
+
+	/// the vertex of the first turn.
+	pub fn first(&self) -> Singularity {
+		self.turns[0].s
+	}
+
+	/// the base vertex of the first turn around one, if any.
+	pub fn first_base(&self) -> Option<BaseSingularity> {
+		self.turns.iter().find_map(|t| t.s.base())
+	}
+
+	/// the number of turns.
+	pub fn len(&self) -> usize {
+		self.turns.len()
+	}
+
+	// a valid path always has a turn, but len() deserves an is_empty().
+	pub fn is_empty(&self) -> bool {
+		self.turns.is_empty()
+	}
+
+	pub fn degrees(&self) -> Vec<i64> {
+		self.turns.iter().map(|t| t.degree).collect()
+	}
+
+	/// the signed sum of all degrees.
+	pub fn total_turning(&self) -> i64 {
+		sum(self.turns.iter().map(|t| t.degree))
+	}
+
+	/// the sum of the absolute degrees, i.e. the number of single turns
+	/// the path makes.
+	pub fn absolute_turning(&self) -> i64 {
+		sum(self.turns.iter().map(|t| t.degree.abs()))
+	}
+
+	/// the signed sum of the degrees of the turns around each base vertex.
+	pub fn turning_by_singularity(&self) -> BaseValues<i64> {
+		let mut sums = BaseValues(0, 0);
+		for turn in &self.turns {
+			if let Some(base) = turn.s.base() {
+				sums[base] += turn.degree;
+			}
+		}
+		sums
+	}
+
+	/// the signed sum of the degrees of the turns around either apex.
+	pub fn apex_turning(&self) -> i64 {
+		sum(self.turns.iter()
+			.filter(|t| t.s.apex().is_some())
+			.map(|t| t.degree))
+	}
+
+	/// whether the path ends with the edge pointing the other way from how
+	/// it started, i.e. it turns around the base vertices an odd number of
+	/// times. such a path continues with its vertices swapped.
+	pub fn swaps(&self) -> bool {
+		swaps(&self.turns)
+	}
+
+	/// the turns of one whole period of the orbit: the path itself, and if
+	/// it `swaps`, then the path again around the swapped vertices, i.e.
+	/// its mirror image. only after both laps is the edge back where it
+	/// started, so turn limits and the corridor apply to the whole period.
+	/// its turning is at most twice MAX_TURNING, so it still fits an i32.
+	pub fn period(&self) -> TurnPath {
+		if !self.swaps() {
+			return self.clone();
+		}
+		let turns = self.turns.iter().cloned()
+			.chain(self.turns.iter().map(Turn::mirrored))
+			.collect();
+		TurnPath{turns}
+	}
+
+	/// the same periodic path started from its `k`th turn. continuing a
+	/// path that `swaps` past its end swaps the vertices, so rotating it by
+	/// len() gives its mirror image.
+	pub fn rotated(&self, k: usize) -> TurnPath {
+		let n = self.len();
+		let swaps = self.swaps();
+		if swaps && k % (2 * n) >= n {
+			return self.mirrored().rotated(k - n);
+		}
+		let k = k % n;
+		let turns = self.turns[k..].iter().cloned()
+			.chain(self.turns[..k].iter().map(|t| if swaps { t.mirrored() } else { *t }))
+			.collect();
+		TurnPath{turns}
+	}
+
+	/// the path traversed backwards: the same turns in reverse order.
+	/// degrees are measured relative to the direction of travel, so each
+	/// turn keeps its sign, which puts the apexes on the other sides.
+	pub fn reversed(&self) -> TurnPath {
+		let turns = self.turns.iter().rev()
+			.map(|t| Turn::new(t.degree, t.s.conjugate()))
+			.collect();
+		TurnPath{turns}
+	}
+
+	/// the path reflected by x -> 1 - x, which swaps B0 and B1 (and A0 and
+	/// A1). as with reversal the degrees keep their signs. it is feasible
+	/// at `mirror_apex(apex)` exactly when this path is feasible at `apex`.
+	pub fn mirrored(&self) -> TurnPath {
+		let turns = self.turns.iter().map(Turn::mirrored).collect();
+		TurnPath{turns}
+	}
+
+	/// every rotation, reversal and mirror image of this path, i.e. every
+	/// way of writing the periodic orbit it describes (with repeats when
+	/// the path is symmetric). the first 2 * len() are the rotations of the
+	/// path and then of its reversal.
+	pub fn symmetries(&self) -> Vec<TurnPath> {
+		let images = [self.clone(), self.reversed(), self.mirrored(), self.reversed().mirrored()];
+		images.iter()
+			.flat_map(|image| (0..image.len()).map(move |k| image.rotated(k)))
+			.collect()
+	}
+
+	/// the representative of this path's symmetry class: the image starting
+	/// around B1 (so it displays as plain degrees) whose degrees are
+	/// lexicographically least. equivalent paths have the same canonical
+	/// form.
+	pub fn canonical(&self) -> TurnPath {
+		least_from_b1(self.symmetries())
+	}
 
-}*/
\ No newline at end of file
+	/// like `canonical`, but among rotations and reversals only. these are
+	/// the ways of writing the same orbit of the same triangle, while a
+	/// mirror image is an orbit of the mirrored triangle, so paths with the
+	/// same `canonical_unmirrored` are feasible at the same apexes.
+	pub fn canonical_unmirrored(&self) -> TurnPath {
+		let mut images = self.symmetries();
+		images.truncate(2 * self.len());
+		least_from_b1(images)
+	}
+
+	/// whether this path isn't just a shorter one repeated, e.g. "1 -2 1 -2"
+	/// is "1 -2" twice. since an odd path continues around swapped
+	/// vertices, "2 2" is "2" twice too.
+	pub fn is_primitive(&self) -> bool {
+		let n = self.len();
+		(1..n)
+			.filter(|p| n.is_multiple_of(*p))
+			.all(|p| {
+				let swaps = swaps(&self.turns[..p]);
+				(p..n).any(|i| {
+					let prev = self.turns[i - p];
+					self.turns[i] != if swaps { prev.mirrored() } else { prev }
+				})
+			})
+	}
+
+	pub fn is_canonical(&self) -> bool {
+		*self == self.canonical()
+	}
+}
+
+// a sum of some of a path's degrees, which `TurnPath::new` keeps within
+// MAX_TURNING.
+fn sum<I: Iterator<Item = i64>>(mut degrees: I) -> i64 {
+	degrees.try_fold(0, i64::checked_add).expect("a path's turning is bounded")
+}
+
+// whether turning along `turns` leaves the edge pointing the other way.
+fn swaps(turns: &[Turn]) -> bool {
+	!turns.iter().filter(|t| t.s.base().is_some()).count().is_multiple_of(2)
+}
+
+// the image starting around B1 with the lexicographically least degrees,
+// and then vertices. every path that turns around both base vertices has
+// one among its rotations.
+fn least_from_b1(images: Vec<TurnPath>) -> TurnPath {
+	images.into_iter()
+		.min_by_key(|path| (
+			path.first() != Singularity::B1,
+			path.degrees(),
+			path.turns.iter().map(|t| t.s).collect::<Vec<Singularity>>()))
+		.unwrap()
+}
+
+/// whether `a` and `b` describe the same periodic orbit up to rotation,
+/// reversal and mirroring.
+pub fn equivalent(a: &TurnPath, b: &TurnPath) -> bool {
+	a.len() == b.len() && a.canonical() == b.canonical()
+}
+
+/// the apex (1 - x, y) of the mirror image of the triangle with apex
+/// (x, y), whose base vertices trade places.
+pub fn mirror_apex<K>(apex: &V2<K>) -> V2<K>
+where
+	K: One + Sub<Output = K> + Clone,
+{
+	V2(K::one() - apex.0.clone(), apex.1.clone())
+}
+
+impl fmt::Display for TurnPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let plain = self.first() == Singularity::B1
+			&& self.turns.iter().all(|t| t.s.base().is_some());
+		let words: Vec<String> = if plain {
+			self.turns.iter().map(|t| t.degree.to_string()).collect()
+		} else {
+			self.turns.iter().map(|t| t.to_string()).collect()
+		};
+		write!(f, "{}", words.join(" "))
+	}
+}
+
+fn parse_singularity(s: &str) -> Result<Singularity, String> {
+	match s {
+		"B0" | "b0" => Ok(Singularity::B0),
+		"B1" | "b1" => Ok(Singularity::B1),
+		"A0" | "a0" => Ok(Singularity::A0),
+		"A1" | "a1" => Ok(Singularity::A1),
+		_ => Err(format!("unknown vertex '{}' (expected B0, B1, A0 or A1)", s)),
+	}
+}
+
+fn parse_degree(s: &str) -> Result<i64, String> {
+	let degree = s.parse::<i64>().map_err(|_| format!("expected a turn degree, got '{}'", s))?;
+	if degree.unsigned_abs() > MAX_TURNING as u64 {
+		return Err(format!("turn degree {} is larger than {}", degree, MAX_TURNING));
+	}
+	Ok(degree)
+}
+
+impl FromStr for TurnPath {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<TurnPath, String> {
+		let words: Vec<&str> = s
+			.split(|c: char| c == ',' || c.is_whitespace())
+			.filter(|w| !w.is_empty())
+			.collect();
+		if words.iter().all(|w| !w.contains('^')) {
+			let degrees = words.iter()
+				.map(|w| parse_degree(w))
+				.collect::<Result<Vec<i64>, String>>()?;
+			return TurnPath::from_degrees(B1, &degrees);
+		}
+		let turns = words.iter()
+			.map(|w| {
+				let (s, degree) = w.split_once('^')
+					.ok_or_else(|| format!("expected a turn like B0^2, got '{}'", w))?;
+				Ok(Turn::new(parse_degree(degree)?, parse_singularity(s)?))
+			})
+			.collect::<Result<Vec<Turn>, String>>()?;
+		TurnPath::new(turns)
+	}
+}
+
+impl TryFrom<String> for TurnPath {
+	type Error = String;
+
+	fn try_from(s: String) -> Result<TurnPath, String> {
+		s.parse()
+	}
+}
+
+impl From<TurnPath> for String {
+	fn from(path: TurnPath) -> String {
+		path.to_string()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rug::Rational;
+
+	#[test]
+	fn test_parse_and_display() {
+		let path: TurnPath = "-2 2 2 -2".parse().unwrap();
+		assert_eq!(path.turns(), &[
+			Turn::new(-2, B1), Turn::new(2, B0), Turn::new(2, B1), Turn::new(-2, B0)]);
+		assert_eq!(path.to_string(), "-2 2 2 -2");
+		assert_eq!("B1^-2, B0^2 b1^2 B0^-2".parse::<TurnPath>().unwrap(), path);
+
+		let from_b0: TurnPath = "B0^3 B1^-1".parse().unwrap();
+		assert_eq!(from_b0.to_string(), "B0^3 B1^-1");
+		assert_eq!(from_b0.to_string().parse::<TurnPath>().unwrap(), from_b0);
+
+		assert!("".parse::<TurnPath>().is_err());
+		assert!("1 0 1".parse::<TurnPath>().is_err(), "zero degrees are invalid");
+		assert!("B0^1 B0^2".parse::<TurnPath>().is_err(), "turns must alternate");
+		assert!("B2^1".parse::<TurnPath>().is_err());
+		assert!("1 x".parse::<TurnPath>().is_err());
+		assert!("-9223372036854775808 1".parse::<TurnPath>().is_err(), "degrees must fit an i32");
+		assert!(TurnPath::from_degrees(B1, &[i64::MIN, 1]).is_err());
+		assert!(TurnPath::from_degrees(B1, &[MAX_TURNING, -1]).is_err());
+		let largest = TurnPath::from_degrees(B1, &[MAX_TURNING - 1, -1]).unwrap();
+		assert_eq!(largest.absolute_turning(), MAX_TURNING);
+
+		let apex: TurnPath = "B1^-1 a1^-1 B0^1 A0^1".parse().unwrap();
+		assert_eq!(apex.turns()[1], Turn::new(-1, Singularity::A1));
+		assert_eq!(apex.to_string(), "B1^-1 A1^-1 B0^1 A0^1");
+		assert_eq!(apex.first_base(), Some(B1));
+		assert!("B1^1 A0^1 B1^1".parse::<TurnPath>().is_err(), "base turns must alternate");
+		assert!("A0^1 B1^1 B0^1 A0^1".parse::<TurnPath>().is_err(), "A0 follows A0 on repeating");
+		assert!("A0^1".parse::<TurnPath>().is_err());
+		assert!("A0^1 B1^1 A1^1".parse::<TurnPath>().is_err(), "A0 becomes A1 on repeating");
+		assert!("A0^1 B1^1 A0^1".parse::<TurnPath>().is_ok());
+	}
+
+	#[test]
+	fn test_measures() {
+		let path: TurnPath = "-9 6 9 -6 3".parse().unwrap();
+		assert_eq!(path.len(), 5);
+		assert_eq!(path.total_turning(), 3);
+		assert_eq!(path.absolute_turning(), 33);
+		let sums = path.turning_by_singularity();
+		assert_eq!((sums[B0], sums[B1]), (0, 3));
+		assert_eq!(path.apex_turning(), 0);
+
+		let apex: TurnPath = "B1^-1 A1^-2 B0^1 A0^3".parse().unwrap();
+		assert_eq!(apex.turning_by_singularity()[B1], -1);
+		assert_eq!(apex.apex_turning(), 1);
+		assert_eq!(apex.total_turning(), 1);
+		assert!(!apex.swaps());
+	}
+
+	#[test]
+	fn test_serde() {
+		let path: TurnPath = "B0^3 B1^-1".parse().unwrap();
+		let json = serde_json::to_string(&path).unwrap();
+		assert_eq!(json, r#""B0^3 B1^-1""#);
+		assert_eq!(serde_json::from_str::<TurnPath>(&json).unwrap(), path);
+		assert!(serde_json::from_str::<TurnPath>(r#""1 0""#).is_err());
+	}
+
+	#[test]
+	fn test_symmetries() {
+		let path: TurnPath = "-3 3 -2 2".parse().unwrap();
+		assert_eq!(path.rotated(1).to_string(), "B0^3 B1^-2 B0^2 B1^-3");
+		assert_eq!(path.rotated(6), path.rotated(2));
+		assert_eq!(path.reversed().to_string(), "B0^2 B1^-2 B0^3 B1^-3");
+		assert_eq!(path.mirrored().to_string(), "B0^-3 B1^3 B0^-2 B1^2");
+		assert_eq!(path.mirrored().mirrored(), path);
+
+		// odd paths swap vertices each time around.
+		let odd: TurnPath = "1 2 3".parse().unwrap();
+		assert_eq!(odd.rotated(1).to_string(), "B0^2 B1^3 B0^1");
+		assert_eq!(odd.rotated(3).to_string(), "B0^1 B1^2 B0^3");
+
+		let canonical = path.canonical();
+		assert_eq!(canonical.to_string(), "-3 2 -2 3");
+		assert!(canonical.is_canonical());
+		assert!(!path.is_canonical());
+		for image in path.symmetries() {
+			assert_eq!(image.canonical(), canonical);
+			assert!(equivalent(&image, &path));
+		}
+		// the mirror image is a different orbit of the same triangle.
+		let unmirrored = path.canonical_unmirrored();
+		assert_eq!(path.reversed().rotated(1).canonical_unmirrored(), unmirrored);
+		assert_ne!(path.mirrored().canonical_unmirrored(), unmirrored);
+		assert_eq!(unmirrored.canonical(), canonical);
+		assert!(!equivalent(&path, &"-3 3 -2 1".parse().unwrap()));
+		assert!(!equivalent(&path, &"-3 3".parse().unwrap()));
+
+		assert!(path.is_primitive());
+		assert!(!"1 -2 1 -2".parse::<TurnPath>().unwrap().is_primitive());
+		assert!(!"2 2".parse::<TurnPath>().unwrap().is_primitive());
+		assert!("2 2 -2".parse::<TurnPath>().unwrap().is_primitive());
+
+		// reversal puts the apexes on the other sides, and odd paths swap
+		// them along with the base vertices.
+		let apex_path: TurnPath = "B1^1 A0^2 B0^-1 A1^1".parse().unwrap();
+		assert_eq!(apex_path.reversed().to_string(), "A0^1 B0^-1 A1^2 B1^1");
+		assert_eq!(apex_path.rotated(3).to_string(), "A1^1 B1^1 A0^2 B0^-1");
+		let odd_apex: TurnPath = "B1^1 A0^2".parse().unwrap();
+		assert!(odd_apex.swaps());
+		assert_eq!(odd_apex.rotated(1).to_string(), "A0^2 B0^1");
+		assert_eq!(odd_apex.rotated(2), odd_apex.mirrored());
+		assert!(!"B1^1 A0^2 B0^1 A1^2".parse::<TurnPath>().unwrap().is_primitive());
+		assert!("B1^1 A0^2 B0^1 A0^2".parse::<TurnPath>().unwrap().is_primitive());
+		assert_eq!(apex_path.canonical_unmirrored().first(), Singularity::B1);
+
+		let apex = V2(Rational::from((1, 4)), Rational::from((1, 3)));
+		assert_eq!(mirror_apex(&apex), V2(Rational::from((3, 4)), Rational::from((1, 3))));
+	}
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::billiards::{Params, ParamsTrait};
+use crate::billiards::orbit::{check_path, closing_turns, turns_close, PathCheck};
+use crate::billiards::singularity::{BaseSingularity::{B0, B1}, BaseValues};
+use crate::billiards::turn_path::TurnPath;
+
+// enumerating the periodic orbits of one triangle. every word is
+// generated once per symmetry class, as its canonical form (see
+// `TurnPath::canonical`), and then tested along with its mirror image:
+// reversing or rotating a word gives the same orbit of the same triangle,
+// but mirroring gives an orbit of the mirrored triangle, so either form
+// may be the one that fits.
+
+/// limits on the words to enumerate.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Budget {
+  /// the most turns a word may have.
+  pub max_len: usize,
+  /// the most absolute turning a word may have, if that is limited too.
+  pub max_turning: Option<u64>,
+}
+
+impl Budget {
+  pub fn length(max_len: usize) -> Budget {
+    Budget{max_len, max_turning: None}
+  }
+
+  /// a budget of absolute turning, which also bounds the length.
+  pub fn turning(max_turning: u64) -> Budget {
+    Budget{max_len: max_turning as usize, max_turning: Some(max_turning)}
+  }
+
+  /// whether every word within `other` is also within this budget.
+  pub fn includes(&self, other: &Budget) -> bool {
+    self.max_len >= other.max_len && match (self.max_turning, other.max_turning) {
+      (None, _) => true,
+      (Some(_), None) => false,
+      (Some(mine), Some(theirs)) => mine >= theirs,
+    }
+  }
+
+  /// whether `path` is within this budget.
+  pub fn allows(&self, path: &TurnPath) -> bool {
+    path.len() <= self.max_len &&
+      self.max_turning.is_none_or(|max| path.absolute_turning() as u64 <= max)
+  }
+}
+
+/// the primitive canonical words within a budget whose turns fit within
+/// `max_turns` as written or mirrored, in order of length and then of
+/// degrees. words are generated lazily, depth first.
+pub struct Words {
+  max_turns: BaseValues<u32>,
+  budget: Budget,
+  // the length of the words being generated.
+  len: usize,
+  // the degrees chosen so far, with one frame per position being chosen.
+  degrees: Vec<i64>,
+  stack: Vec<Frame>,
+}
+
+// the choices left for one position of a word.
+struct Frame {
+  next: i64,
+  max: i64,
+  // the degree limits at this position, as written and mirrored.
+  limits: (i64, i64),
+  // whether the prefix so far fits as written and mirrored.
+  fits: (bool, bool),
+  // the absolute turning of the prefix so far.
+  turning: u64,
+}
+
+impl Words {
+  pub fn new(max_turns: BaseValues<u32>, budget: Budget) -> Words {
+    Words{max_turns, budget, len: 0, degrees: vec![], stack: vec![]}
+  }
+
+  /// the words for a triangle's own turn limits.
+  pub fn for_params<K: ParamsTrait>(params: &Params<K>, budget: Budget) -> Words {
+    Words::new(
+      BaseValues(params.max_turn_around(B0), params.max_turn_around(B1)),
+      budget)
+  }
+
+  // the frame for the position after the current prefix.
+  fn frame(&self, turning: u64, fits: (bool, bool)) -> Frame {
+    // position i turns around B1 as written and B0 mirrored when i is
+    // even, and the other way around when it's odd.
+    let (s, mirror_s) = if self.degrees.len().is_multiple_of(2) { (B1, B0) } else { (B0, B1) };
+    let limits = (self.max_turns[s] as i64, self.max_turns[mirror_s] as i64);
+    // an odd word's second lap makes each turn around the other vertex
+    // (see `TurnPath::period`), so each must fit both limits.
+    let max = if !self.len.is_multiple_of(2) {
+      std::cmp::min(limits.0, limits.1)
+    } else {
+      std::cmp::max(
+        if fits.0 { limits.0 } else { 0 },
+        if fits.1 { limits.1 } else { 0 })
+    };
+    // the canonical form starts with its least degree.
+    let min = self.degrees.first().map_or(-max, |&first| std::cmp::max(first, -max));
+    Frame{next: min, max, limits, fits, turning}
+  }
+}
+
+impl Iterator for Words {
+  type Item = TurnPath;
+
+  fn next(&mut self) -> Option<TurnPath> {
+    loop {
+      let frame = match self.stack.last_mut() {
+        Some(frame) => frame,
+        None => {
+          if self.len >= self.budget.max_len {
+            return None;
+          }
+          self.len += 1;
+          let root = self.frame(0, (true, true));
+          self.stack.push(root);
+          continue;
+        }
+      };
+      if frame.next > frame.max {
+        self.stack.pop();
+        self.degrees.pop();
+        continue;
+      }
+      let degree = frame.next;
+      frame.next += 1;
+      if degree == 0 {
+        continue;
+      }
+      // every turn after this one adds at least 1.
+      let turning = frame.turning + degree.unsigned_abs();
+      let rest = (self.len - self.degrees.len() - 1) as u64;
+      if self.budget.max_turning.is_some_and(|max| turning + rest > max) {
+        continue;
+      }
+      let fits = (
+        frame.fits.0 && degree.abs() <= frame.limits.0,
+        frame.fits.1 && degree.abs() <= frame.limits.1);
+      self.degrees.push(degree);
+      if self.degrees.len() == self.len {
+        let path = TurnPath::from_degrees(B1, &self.degrees).unwrap();
+        self.degrees.pop();
+        if path.is_primitive() && path.is_canonical() {
+          return Some(path);
+        }
+        continue;
+      }
+      let frame = self.frame(turning, fits);
+      self.stack.push(frame);
+    }
+  }
+}
+
+/// the form of `path`'s symmetry class that is a periodic orbit of the
+/// triangle of `params`, if any: `path` itself or else its mirror image.
+pub fn find_orbit<K>(params: &mut Params<K>, path: &TurnPath) -> Option<(TurnPath, PathCheck<K>)>
+where
+    K: ParamsTrait
+{
+  // an odd word's orbit goes around each vertex in both roles, so its
+  // mirror image is one of its rotations.
+  let mirrored = path.mirrored();
+  let mut forms = vec![path.clone()];
+  if path.len().is_multiple_of(2) && !path.symmetries()[..2 * path.len()].contains(&mirrored) {
+    forms.push(mirrored);
+  }
+  forms.into_iter()
+    .map(|form| {
+      let check = check_path(params, &form);
+      (form, check)
+    })
+    .find(|(_, check)| check.is_feasible())
+}
+
+// whether `path` or its mirror image closes up, or might, remembering the
+// answer for each pair of totals in `closing`: most words don't close up,
+// and that depends only on their total turning around each vertex.
+fn might_close<K>(params: &mut Params<K>, closing: &mut HashMap<(i64, i64), bool>, path: &TurnPath) -> bool
+where
+    K: ParamsTrait
+{
+  let mut closes = |b0: i64, b1: i64| {
+    *closing.entry((b0, b1)).or_insert_with(|| turns_close(params, b0, b1) != Some(false))
+  };
+  // the mirror image swaps the totals.
+  let (b0, b1) = closing_turns(path);
+  closes(b0, b1) || closes(b1, b0)
+}
+
+/// every periodic orbit of the triangle of `params` within `budget`, one
+/// per symmetry class.
+pub fn orbits<'a, K>(params: &'a mut Params<K>, budget: Budget) -> impl Iterator<Item = (TurnPath, PathCheck<K>)> + 'a
+where
+    K: ParamsTrait
+{
+  let mut closing = HashMap::new();
+  Words::for_params(params, budget)
+    .filter_map(move |path| {
+      if might_close(params, &mut closing, &path) { find_orbit(params, &path) } else { None }
+    })
+}
+
+/// searches many triangles for an orbit each, sharing the candidate words
+/// between triangles with the same turn limits. candidates are generated
+/// only as far as some search has needed them.
+pub struct Search {
+  budget: Budget,
+  words: HashMap<(u32, u32), (Words, Vec<TurnPath>)>,
+}
+
+impl Search {
+  pub fn new(budget: Budget) -> Search {
+    Search{budget, words: HashMap::new()}
+  }
+
+  pub fn budget(&self) -> Budget {
+    self.budget
+  }
+
+  /// the shortest orbit of the triangle of `params` within the budget,
+  /// trying words in the order `Words` lists them.
+  pub fn find<K>(&mut self, params: &mut Params<K>) -> Option<(TurnPath, PathCheck<K>)>
+  where
+      K: ParamsTrait
+  {
+    self.find_while(params, || true)
+  }
+
+  /// like `find`, but gives up as soon as `keep_going` returns false,
+  /// which it is asked before each word.
+  pub fn find_while<K, F>(&mut self, params: &mut Params<K>, mut keep_going: F) -> Option<(TurnPath, PathCheck<K>)>
+  where
+      K: ParamsTrait,
+      F: FnMut() -> bool
+  {
+    // no turn can exceed the turning budget, so triangles whose limits
+    // are both beyond it share their words.
+    let budget = self.budget;
+    let cap = |limit: u32| budget.max_turning.map_or(limit, |max| std::cmp::min(limit as u64, max) as u32);
+    let key = (cap(params.max_turn_around(B0)), cap(params.max_turn_around(B1)));
+    let (words, generated) = self.words.entry(key)
+      .or_insert_with(|| (Words::new(BaseValues(key.0, key.1), budget), vec![]));
+    let mut closing = HashMap::new();
+    let mut i = 0;
+    while keep_going() {
+      if i == generated.len() {
+        generated.push(words.next()?);
+      }
+      let path = &generated[i];
+      i += 1;
+      if !might_close(params, &mut closing, path) {
+        continue;
+      }
+      if let Some(orbit) = find_orbit(params, path) {
+        return Some(orbit);
+      }
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+  use crate::vector::V2;
+
+  #[test]
+  fn test_words() {
+    // "-2 2" needs a double turn around both vertices, and so do "-2" and
+    // "2" on their second lap.
+    let words: Vec<String> = Words::new(BaseValues(1, 2), Budget::length(2))
+      .map(|path| path.to_string())
+      .collect();
+    assert_eq!(words, ["-1", "1", "-2 -1", "-2 1", "-1 1", "-1 2", "1 2"]);
+    for path in Words::new(BaseValues(3, 2), Budget::length(4)) {
+      assert!(path.is_canonical() && path.is_primitive(), "{}", path);
+    }
+    assert!(Words::new(BaseValues(3, 3), Budget::turning(4)).all(|path| path.absolute_turning() <= 4));
+
+    // odd words turn around both vertices at every position.
+    for path in Words::new(BaseValues(1, 4), Budget::length(5)) {
+      assert!(path.len() % 2 == 0 || path.iter().all(|t| t.degree.abs() <= 1), "{}", path);
+    }
+  }
+
+  #[test]
+  fn test_orbits() {
+    let mut params = Params::new(V2(Rational::from((1, 2)), Rational::from((3, 8))));
+    let found: Vec<String> = orbits(&mut params, Budget::length(4))
+      .map(|(path, _)| path.to_string())
+      .collect();
+    assert_eq!(found, ["-2 2", "-1 1", "-2 -2 2 2", "-2 1 -1 2"]);
+
+    // away from the axis of symmetry, an orbit may only fit as the mirror
+    // image of its canonical form.
+    let mut params = Params::new(V2(Rational::from((3, 5)), Rational::from((3, 10))));
+    let found: Vec<String> = orbits(&mut params, Budget::length(4))
+      .map(|(path, _)| path.to_string())
+      .collect();
+    assert_eq!(found, ["B0^-3 B1^-2 B0^3 B1^2"]);
+
+    let mut search = Search::new(Budget::length(4));
+    let (path, check) = search.find(&mut params).unwrap();
+    assert_eq!(path.to_string(), "B0^-3 B1^-2 B0^3 B1^2");
+    assert!(check.is_feasible());
+    assert!(Search::new(Budget::length(3)).find(&mut params).is_none());
+
+    // each odd orbit found survives both of its laps.
+    let mut params = Params::new(V2(Rational::from((5, 32)), Rational::from((9, 32))));
+    let found: Vec<_> = orbits(&mut params, Budget::length(5)).map(|(path, _)| path).collect();
+    for path in found {
+      assert_ne!(path.to_string(), "-4 -1 2 -1 4");
+      assert!(check_path(&mut params, &path.period()).is_feasible(), "{}", path);
+    }
+  }
+}
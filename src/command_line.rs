@@ -1,16 +1,20 @@
+mod path;
+mod pathset;
 mod pointset;
+mod tabulator;
+mod words;
 
 use std::env;
 use std::error::Error;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::Path;
 
 use chrono::prelude::*;
 use clap::{Arg, ArgMatches, App, SubCommand};
 
 use crate::repl;
 
-pub fn run(root_path: &PathBuf) {
+pub fn run(root_path: &Path) {
 	let cmd = command();
 	let matches = cmd.get_matches();
 
@@ -21,13 +25,19 @@ fn command<'a, 'b>() -> App<'a, 'b> {
 	App::new("billiards-rs")
 		.version("0.0.x")
 		.subcommand(pointset::subcommand())
+		.subcommand(pathset::subcommand())
+		.subcommand(words::subcommand())
+		.subcommand(path::subcommand())
 		.subcommand(SubCommand::with_name("repl")
 			.about("Opens an interactive interface"))
 }
 
-fn root_run(root_path: &PathBuf, matches: &ArgMatches) {
+fn root_run(root_path: &Path, matches: &ArgMatches) {
 	match matches.subcommand() {
 		("pointset", Some(sub_m)) => { pointset::run(root_path, sub_m) },
+		("pathset", Some(sub_m)) => { pathset::run(root_path, sub_m) },
+		("words", Some(sub_m)) => { words::run(sub_m) },
+		("path", Some(sub_m)) => { path::run(sub_m) },
 		("repl", _) => { repl::run(root_path) },
 		_ => { eprintln!("{}", matches.usage()); }
 	}
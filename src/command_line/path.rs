@@ -0,0 +1,194 @@
+use std::convert::TryFrom;
+
+use clap::{AppSettings, Arg, ArgMatches, App, SubCommand};
+use rug::Rational;
+
+use crate::algebra::SquaredNorm;
+use crate::billiards::Params;
+use crate::billiards::apex_box::{check_path_on_box, check_subdivided, ApexBox};
+use crate::billiards::orbit::{
+  check_path, closing_turns, corridor_sides, projection, translation, unfold, PathCheck};
+use crate::billiards::singularity::ApexSingularity::A0;
+use crate::billiards::singularity::BaseSingularity::{B0, B1};
+use crate::billiards::turn_path::TurnPath;
+use crate::command_line::words::{apex_arg, parse_apex};
+use crate::util::rational::parse_rational_pair;
+use crate::vector::V2;
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("path")
+    .about("Inspects a single turn word")
+    .subcommand(SubCommand::with_name("check")
+      .about("Checks whether a word is a periodic orbit of a triangle, and shows why")
+      .long_about("Unfolds the triangle with the given apex along a word and \
+        prints every position of the unfolded base edge, with the copies of \
+        the apex on either side, and then the corridor they leave for the \
+        orbit. Coordinates are printed exactly and then approximately. The \
+        margin is the corridor's width scaled by the length of the \
+        translation, and the word is an orbit when it's positive.")
+      // words like '-2 2' start with a hyphen.
+      .setting(AppSettings::AllowLeadingHyphen)
+      .arg(Arg::with_name("word")
+        .index(1)
+        .required(true)
+        .validator(|word| word.parse::<TurnPath>().map(|_| {}))
+        .help("The word to check, e.g. '-2 2 2 -2', 'B1^-2 B0^2' or 'B1^-1 A1^-1 B0^1 A0^1'")
+      )
+      .arg(apex_arg())
+    )
+    .subcommand(SubCommand::with_name("verify")
+      .about("Checks a word for every apex in a box at once")
+      .long_about("Unfolds the triangle along a word with interval coordinates \
+        standing for every apex in a box, and reports whether the word is \
+        certainly a periodic orbit throughout. Anything else is unknown: the \
+        word may fail somewhere in the box, or the intervals may have grown \
+        too wide, which happens quickly for large boxes. With --depth, unknown \
+        boxes are split into quarters and checked again.")
+      .setting(AppSettings::AllowLeadingHyphen)
+      .arg(Arg::with_name("word")
+        .index(1)
+        .required(true)
+        .validator(|word| word.parse::<TurnPath>().map(|_| {}))
+        .help("The word to check, e.g. '-2 2 2 -2'")
+      )
+      .arg(Arg::with_name("x")
+        .long("x")
+        .value_name("min,max")
+        .required(true)
+        .validator(|s| parse_rational_pair(&s).map(|_| {})
+          .ok_or_else(|| format!("expected a range like 0.49,0.51, got '{}'", s)))
+        .help("The range of apex x coordinates")
+      )
+      .arg(Arg::with_name("y")
+        .long("y")
+        .value_name("min,max")
+        .required(true)
+        .validator(|s| parse_rational_pair(&s).map(|_| {})
+          .ok_or_else(|| format!("expected a range like 3/8,0.38, got '{}'", s)))
+        .help("The range of apex y coordinates")
+      )
+      .arg(Arg::with_name("depth")
+        .long("depth")
+        .default_value("0")
+        .validator(|s| s.parse::<u32>().map(|_| {}).map_err(|e| e.to_string()))
+        .help("How many times to split boxes that come out unknown")
+      )
+    )
+}
+
+pub fn run(matches: &ArgMatches) {
+  match matches.subcommand() {
+    ("check", Some(sub_m)) => { run_check(sub_m) },
+    ("verify", Some(sub_m)) => { run_verify(sub_m) },
+    _ => { eprintln!("{}", matches.usage()); }
+  }
+}
+
+fn point(v: &V2<Rational>) -> String {
+  format!("({}, {}) ~ ({:.6}, {:.6})", v.0, v.1, v.0.to_f64(), v.1.to_f64())
+}
+
+fn run_check(matches: &ArgMatches) {
+  let path: TurnPath = matches.value_of("word").unwrap().parse().unwrap();
+  let apex = parse_apex(matches.value_of("apex").unwrap()).unwrap();
+  let mut params = Params::new(apex);
+  println!("word: {}", path);
+  println!("apex: {}", point(params.apex()));
+  println!("max turns: {} around B0, {} around B1, {} around either apex",
+    params.max_turn_around(B0), params.max_turn_around(B1), params.max_turn_around(A0));
+
+  let check = check_path(&mut params, &path);
+  match &check {
+    PathCheck::TurnTooLarge{turn, max_turn, ..} => {
+      // turning k times around a vertex rotates by k times twice its
+      // angle, and the last turn that fits stays short of a half turn.
+      let rotation = params.turn_vec(turn.s, i32::try_from(*max_turn + 1).unwrap());
+      println!("{}", check);
+      println!("  {} turns around {:?} rotate the edge by {}, at least a half turn",
+        max_turn + 1, turn.s, point(&rotation));
+      return;
+    },
+    PathCheck::Unclosed => {
+      let (b0, b1) = closing_turns(&path);
+      let rotation = params.turn_vec(B0, i32::try_from(b0).unwrap())
+        * params.turn_vec(B1, i32::try_from(b1).unwrap());
+      if path.swaps() {
+        println!("a word with an odd number of base turns closes up after going around twice");
+      }
+      if path.apex_turning() != 0 {
+        println!("a turn around an apex rotates as far as turning back once around each base vertex");
+      }
+      println!("turning {} times around B0 and {} times around B1 rotates by {}, \
+        not (1, 0)", b0, b1, point(&rotation));
+    },
+    PathCheck::Unknown{..} => {},
+    PathCheck::Corridor{..} => {},
+  }
+
+  let steps = unfold(&mut params, &path.period());
+  let offset = translation(&steps);
+  let across = |v: &V2<Rational>| projection(v, &offset).to_f64();
+  for (i, step) in steps.iter().enumerate() {
+    match step.turn {
+      None => println!("step {}: start", i),
+      Some(turn) => println!("step {}: {} (turning {})", i, turn,
+        if turn.degree > 0 { "left" } else { "right" }),
+    }
+    println!("  edge from {}", point(&step.from));
+    println!("         to {}", point(&step.to));
+    println!("  left apex  {}, across {:.6}", point(&step.left_apex), across(&step.left_apex));
+    println!("  right apex {}, across {:.6}", point(&step.right_apex), across(&step.right_apex));
+    if let (Some(left), Some(pivot)) = (step.pivot_is_left(), &step.pivot) {
+      println!("  pivot on the {}, across {:.6}",
+        if left { "left" } else { "right" }, across(pivot));
+    }
+  }
+  if check == PathCheck::Unclosed {
+    println!("{}", check);
+    return;
+  }
+
+  let (left_points, right_points) = corridor_sides(&steps);
+  let left = left_points.iter().map(|v| projection(v, &offset)).min().unwrap();
+  let right = right_points.iter().map(|v| projection(v, &offset)).max().unwrap();
+  let length = offset.squared_norm().to_f64().sqrt();
+  println!("translation: {}", point(&offset));
+  println!("left side: nearest point across {} ~ {:.6}", left, left.to_f64());
+  println!("right side: nearest point across {} ~ {:.6}", right, right.to_f64());
+  let margin = check.margin().unwrap();
+  println!("margin: {} ~ {:.6}, corridor width ~ {:.6}",
+    margin, margin.to_f64(), margin.to_f64() / length);
+  println!("{}", if check.is_feasible() { "feasible" } else { "infeasible" });
+}
+
+fn run_verify(matches: &ArgMatches) {
+  let path: TurnPath = matches.value_of("word").unwrap().parse().unwrap();
+  let x = parse_rational_pair(matches.value_of("x").unwrap()).unwrap();
+  let y = parse_rational_pair(matches.value_of("y").unwrap()).unwrap();
+  let depth: u32 = matches.value_of("depth").unwrap().parse().unwrap();
+  let apex_box = match ApexBox::new(x, y) {
+    Ok(apex_box) => apex_box,
+    Err(e) => {
+      eprintln!("{}", e);
+      return;
+    }
+  };
+  if depth == 0 {
+    let check = check_path_on_box(&path, &apex_box);
+    if check.is_feasible() {
+      println!("feasible for every apex in the box ({})", check);
+    } else {
+      println!("unknown ({})", check);
+    }
+    return;
+  }
+  let (feasible, unknown) = check_subdivided(&path, &apex_box, depth);
+  println!("feasible on {} boxes, unknown on {}", feasible.len(), unknown.len());
+  if apex_box.area() > 0 {
+    let area: Rational = feasible.iter().map(ApexBox::area).sum();
+    println!("feasible on {:.2}% of the area", 100.0 * (area / apex_box.area()).to_f64());
+  }
+  for b in &unknown {
+    println!("unknown: x in [{}, {}], y in [{}, {}]", b.x.0, b.x.1, b.y.0, b.y.1);
+  }
+}
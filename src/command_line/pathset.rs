@@ -0,0 +1,214 @@
+use std::io;
+use std::path::Path;
+
+use clap::{AppSettings, Arg, ArgMatches, App, SubCommand};
+
+use crate::billiards::turn_path::TurnPath;
+use crate::command_line::tabulator::Tabulator;
+use crate::data::path_set;
+use crate::data::path_set::PathEntry;
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("pathset")
+    .about("Manipulates named sets of turn words")
+    .subcommands(vec![
+      subcommand_create(),
+      subcommand_add(),
+      subcommand_list(),
+      subcommand_print(),
+      subcommand_delete(),
+      subcommand_import(),
+    ])
+}
+
+fn name_arg<'a, 'b>(help: &'static str) -> Arg<'a, 'b> {
+  Arg::with_name("name")
+    .index(1)
+    .required(true)
+    .help(help)
+}
+
+pub fn subcommand_create<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("create")
+    .about("Creates a new, empty path set")
+    .arg(name_arg("The name of the new path set"))
+    .arg(Arg::with_name("notes")
+      .long("notes")
+      .takes_value(true)
+      .help("A description of the set")
+    )
+}
+
+pub fn subcommand_add<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("add")
+    .about("Adds words to a path set")
+    // words like '-2 2' start with a hyphen.
+    .setting(AppSettings::AllowLeadingHyphen)
+    .long_about("Adds words to a path set, skipping any that are rotations \
+      or reversals of words already in it.")
+    .arg(name_arg("The path set to add to"))
+    .arg(Arg::with_name("words")
+      .index(2)
+      .required(true)
+      .multiple(true)
+      .allow_hyphen_values(true)
+      .validator(|word| word.parse::<TurnPath>().map(|_| {}))
+      .help("The words to add, each quoted, e.g. '-2 2 2 -2' 'B1^-2 B0^2'")
+    )
+    .arg(Arg::with_name("note")
+      .long("note")
+      .takes_value(true)
+      .help("A note to attach to each word")
+    )
+    .arg(Arg::with_name("source")
+      .long("source")
+      .takes_value(true)
+      .help("Where the words came from, e.g. a paper or a point set")
+    )
+}
+
+pub fn subcommand_list<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("list")
+    .about("Lists all path sets")
+}
+
+pub fn subcommand_print<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("print")
+    .about("Prints the words of a path set")
+    .long_about("Prints the words of a path set, one per line, followed by \
+      its note and its source separated by tabs. The output can be read back \
+      with `pathset import`.")
+    .arg(name_arg("The name of the path set to print"))
+}
+
+pub fn subcommand_delete<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("delete")
+    .about("Deletes a path set")
+    .arg(name_arg("The name of the path set to delete"))
+}
+
+pub fn subcommand_import<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("import")
+    .about("Adds the words listed in a file to a path set")
+    .long_about("Adds the words listed in a file to a path set, creating \
+      the set if it doesn't exist. The file lists one word per line, \
+      optionally followed by a tab and a note and then a tab and a source, as \
+      `pathset print` writes them. Blank lines and lines starting with # are \
+      skipped, and words without a source get the file and line as their \
+      source.")
+    .arg(name_arg("The path set to add to"))
+    .arg(Arg::with_name("file")
+      .index(2)
+      .required(true)
+      .help("The file to read")
+    )
+}
+
+pub fn run(root_path: &Path, matches: &ArgMatches) {
+  let manager = path_set::manager(root_path.join("data").join("path_set"));
+  match matches.subcommand() {
+    ("create", Some(sub_m)) => { run_create(&manager, sub_m) },
+    ("add", Some(sub_m)) => { run_add(&manager, sub_m) },
+    ("list", Some(_)) => { run_list(&manager) },
+    ("print", Some(sub_m)) => { run_print(&manager, sub_m) },
+    ("delete", Some(sub_m)) => { run_delete(&manager, sub_m) },
+    ("import", Some(sub_m)) => { run_import(&manager, sub_m) },
+    _ => { eprintln!("{}", matches.usage()); }
+  }
+}
+
+fn run_create(manager: &path_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let notes = matches.value_of("notes").map(String::from);
+  match manager.create(name, notes) {
+    Err(e) => { eprintln!("couldn't create path set: {}", e); },
+    Ok(_) => { eprintln!("created path set '{}'", name); },
+  }
+}
+
+fn report_added(name: &str, offered: usize, added: &[PathEntry]) {
+  eprintln!("added {} words to '{}'", added.len(), name);
+  if added.len() < offered {
+    eprintln!("skipped {} words already in the set", offered - added.len());
+  }
+}
+
+fn run_add(manager: &path_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let note = matches.value_of("note").map(String::from);
+  let source = matches.value_of("source").map(String::from);
+  let entries: Vec<PathEntry> = matches.values_of("words").unwrap()
+    .map(|word| PathEntry{
+      word: word.parse().unwrap(),
+      note: note.clone(),
+      source: source.clone(),
+    })
+    .collect();
+  let offered = entries.len();
+  match manager.add(name, entries) {
+    Err(e) => { eprintln!("couldn't add to path set '{}': {}", name, e); },
+    Ok(added) => { report_added(name, offered, &added); },
+  }
+}
+
+fn run_list(manager: &path_set::Manager) {
+  let mut path_sets = vec![];
+  match manager.list() {
+    Ok(results) => {
+      for (name, result) in results {
+        match result {
+          Ok(set) => path_sets.push(set),
+          Err(e) => eprintln!("warning: couldn't read path set '{}': {}", name, e),
+        }
+      }
+    },
+    Err(e) => {
+      eprintln!("couldn't list path sets: {}", e);
+      return;
+    }
+  };
+  let mut table = Tabulator::new(vec![
+    String::from("name"),
+    String::from("count"),
+    String::from("created"),
+    String::from("notes")]);
+  path_sets.sort_by_key(|set| set.name.to_lowercase());
+  for set in path_sets {
+    table.append(vec![
+      set.name,
+      set.paths.len().to_string(),
+      set.created.to_rfc2822(),
+      set.notes.unwrap_or_default()]);
+  }
+  table.display();
+}
+
+fn run_print(manager: &path_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let result = manager.load(name).and_then(|set| {
+    if let Some(notes) = &set.notes {
+      println!("# {}", notes);
+    }
+    path_set::write_entries(io::stdout().lock(), &set.paths)
+  });
+  if let Err(e) = result {
+    eprintln!("couldn't print path set '{}': {}", name, e);
+  }
+}
+
+fn run_delete(manager: &path_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  match manager.delete(name) {
+    Err(e) => { eprintln!("couldn't delete path set: {}", e); },
+    Ok(()) => { eprintln!("deleted path set '{}'", name); },
+  }
+}
+
+fn run_import(manager: &path_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let file = Path::new(matches.value_of("file").unwrap());
+  match manager.import(name, file) {
+    Err(e) => { eprintln!("couldn't import into path set '{}': {}", name, e); },
+    Ok(added) => { eprintln!("imported {} words into '{}'", added.len(), name); },
+  }
+}
@@ -1,15 +1,21 @@
+mod catalog;
+mod cover;
+mod exchange;
+mod ops;
 mod plot;
-mod tabulator;
+mod query;
 
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::Duration;
 use clap::{Arg, ArgMatches, App, SubCommand};
+use rand::Rng;
 
 use crate::data::point_set;
-use crate::data::point_set::PointSet;
+use crate::data::point_set::{Origin, PointSet, PointSetInfo};
 
-use self::tabulator::Tabulator;
+use crate::command_line::tabulator::Tabulator;
 
 pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("pointset")
@@ -17,10 +23,20 @@ pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
     .subcommands(vec![
       create::subcommand(),
       subcommand_list(),
+      subcommand_info(),
       subcommand_print(),
       plot::subcommand(),
+      query::subcommand(),
+      cover::subcommand(),
+      catalog::subcommand(),
       subcommand_delete(),
+      subcommand_restore(),
+      subcommand_purge(),
+      subcommand_verify(),
+      subcommand_dedupe(),
     ])
+    .subcommands(ops::subcommands())
+    .subcommands(exchange::subcommands())
 }
 
 mod create {
@@ -38,13 +54,28 @@ mod create {
         .short("c")
         .long("count")
         .takes_value(true)
-        .required(true)
         .validator(|count| {
           count.parse::<u32>()
             .map(|_| {})
             .map_err(|_| "expected integer".to_string())
         })
-        .help("The number of random points to generate")
+        .help("The number of points to generate (required except for lattice)")
+      )
+      .arg(Arg::with_name("region")
+        .long("region")
+        .takes_value(true)
+        .validator(|spec| spec.parse::<point_set::Region>().map(|_| {}))
+        .help("Only keep points in this region, e.g. \
+          'angle largest between 90 100', 'disc 0.5,0 0.25', \
+          'annulus 0.5,0 0.25 0.5' or 'polygon 0.5,0 1,0 0.5,0.5', \
+          combined with 'and'")
+      )
+      .arg(Arg::with_name("generator")
+        .long("generator")
+        .takes_value(true)
+        .possible_values(point_set::GENERATOR_NAMES)
+        .default_value("random")
+        .help("How to choose the points")
       )
       .arg(Arg::with_name("grid_density")
         .short("g")
@@ -52,12 +83,24 @@ mod create {
         .value_name("density")
         .takes_value(true)
         .validator(|density| {
-          density.parse::<u32>()
-            .map(|_| {})
-            .map_err(|_| "expected integer".to_string())
+          match density.parse::<u32>() {
+            Ok(d) if d >= 2 => Ok(()),
+            Ok(_) => Err("grid density must be at least 2".to_string()),
+            Err(_) => Err("expected integer".to_string()),
+          }
         })
         .help("The density of the generating grid, in log base 2")
       )
+      .arg(Arg::with_name("seed")
+        .long("seed")
+        .takes_value(true)
+        .validator(|seed| {
+          seed.parse::<u64>()
+            .map(|_| {})
+            .map_err(|_| "expected a nonnegative integer".to_string())
+        })
+        .help("Seed for the random and stratified generators (chosen at random if omitted)")
+      )
       .arg(Arg::with_name("overwrite")
         .short("o")
         .long("overwrite")
@@ -70,6 +113,20 @@ mod create {
 pub fn subcommand_list<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("list")
     .about("Lists all point sets")
+    .arg(Arg::with_name("trash")
+      .long("trash")
+      .help("List deleted point sets that can still be restored")
+    )
+}
+
+pub fn subcommand_info<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("info")
+    .about("Shows how a point set was created")
+    .arg(Arg::with_name("name")
+      .index(1)
+      .required(true)
+      .help("The name of the point set")
+    )
 }
 
 pub fn subcommand_print<'a, 'b>() -> App<'a, 'b> {
@@ -84,7 +141,7 @@ pub fn subcommand_print<'a, 'b>() -> App<'a, 'b> {
 
 pub fn subcommand_delete<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("delete")
-    .about("Deletes a point set")
+    .about("Moves a point set to the trash")
     .arg(Arg::with_name("name")
       .index(1)
       .required(true)
@@ -92,39 +149,171 @@ pub fn subcommand_delete<'a, 'b>() -> App<'a, 'b> {
     )
 }
 
-pub fn run(root_path: &PathBuf, matches: &ArgMatches) {
+pub fn subcommand_restore<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("restore")
+    .about("Restores the most recently deleted point set with a given name")
+    .arg(Arg::with_name("name")
+      .index(1)
+      .required(true)
+      .help("The name of the point set to restore")
+    )
+}
+
+pub fn subcommand_purge<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("purge")
+    .about("Permanently removes deleted point sets from the trash")
+    .arg(Arg::with_name("older_than")
+      .long("older-than")
+      .value_name("age")
+      .takes_value(true)
+      .validator(|age| parse_age(&age).map(|_| {}))
+      .help("Only purge sets deleted longer ago than this, e.g. 12h or 30d")
+    )
+}
+
+pub fn subcommand_verify<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("verify")
+    .about("Checks point sets against their stored hashes and counts")
+    .arg(Arg::with_name("name")
+      .index(1)
+      .required_unless("all")
+      .conflicts_with("all")
+      .help("The name of the point set to verify")
+    )
+    .arg(Arg::with_name("all")
+      .long("all")
+      .help("Verify every point set")
+    )
+}
+
+pub fn subcommand_dedupe<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("dedupe")
+    .about("Moves point sets with the same contents as an older set to the trash")
+    .arg(Arg::with_name("dry_run")
+      .long("dry-run")
+      .help("Only list the duplicates")
+    )
+}
+
+// parses an age like "90s", "15m", "12h" or "30d".
+fn parse_age(age: &str) -> Result<Duration, String> {
+  let err = || format!("expected a number followed by s, m, h or d, got '{}'", age);
+  if age.len() < 2 {
+    return Err(err());
+  }
+  let (count, unit) = age.split_at(age.len() - 1);
+  // an unsigned count, so that a negative age can't purge everything.
+  let count = count.parse::<u32>().map_err(|_| err())? as i64;
+  match unit {
+    "s" => Ok(Duration::seconds(count)),
+    "m" => Ok(Duration::minutes(count)),
+    "h" => Ok(Duration::hours(count)),
+    "d" => Ok(Duration::days(count)),
+    _ => Err(err()),
+  }
+}
+
+pub fn run(root_path: &Path, matches: &ArgMatches) {
   let data_path = root_path.join("data");
   let point_set_manager = point_set::manager(data_path.join("point_set"));
   match matches.subcommand() {
     ("create", Some(sub_m)) => { run_create(&point_set_manager, sub_m) },
     ("list", Some(sub_m)) => { run_list(&point_set_manager, sub_m) },
+    ("info", Some(sub_m)) => { run_info(&point_set_manager, sub_m) },
     ("print", Some(sub_m)) => { run_print(&point_set_manager, sub_m) },
     ("plot", Some(sub_m)) => { plot::run(&data_path, &point_set_manager, sub_m) },
+    ("query", Some(sub_m)) => { query::run(&point_set_manager, sub_m) },
+    ("cover", Some(sub_m)) => { cover::run(&data_path, &point_set_manager, sub_m) },
+    ("catalog", Some(sub_m)) => { catalog::run(&data_path, &point_set_manager, sub_m) },
     ("delete", Some(sub_m)) => { run_delete(&point_set_manager, sub_m) },
+    ("restore", Some(sub_m)) => { run_restore(&point_set_manager, sub_m) },
+    ("purge", Some(sub_m)) => { run_purge(&point_set_manager, sub_m) },
+    ("verify", Some(sub_m)) => { run_verify(&point_set_manager, sub_m) },
+    ("dedupe", Some(sub_m)) => { run_dedupe(&point_set_manager, sub_m) },
+    (command @ "copy", Some(sub_m)) |
+    (command @ "rename", Some(sub_m)) |
+    (command @ "merge", Some(sub_m)) |
+    (command @ "subtract", Some(sub_m)) |
+    (command @ "intersect", Some(sub_m)) |
+    (command @ "sample", Some(sub_m)) |
+    (command @ "filter", Some(sub_m)) => {
+      ops::run(&point_set_manager, command, sub_m)
+    },
+    (command @ "export", Some(sub_m)) |
+    (command @ "import", Some(sub_m)) => {
+      exchange::run(&point_set_manager, command, sub_m)
+    },
     _ => { eprintln!("{}", matches.usage()); }
   }
 }
 
 pub fn run_create(manager: &point_set::Manager, matches: &ArgMatches) {
   let name = matches.value_of("name").unwrap();
-  let count = matches.value_of("count").unwrap().parse::<u32>().unwrap();
+  let count = matches.value_of("count").map(|s| s.parse::<u32>().unwrap());
   let grid_density = matches.value_of("grid_density").map(|s| s.parse::<u32>().unwrap()).unwrap_or(32);
   let overwrite = matches.is_present("overwrite");
-  let point_generator = point_set::random_from_grid(grid_density, count);
-  if let Err(e) = manager.save(name, overwrite, point_generator) {
-    eprintln!("couldn't create point set: {}", e);
-  } else {
-    eprintln!("saved {} points as '{}'", count, name);
+  let generator_name = matches.value_of("generator").unwrap();
+  let region = matches.value_of("region")
+    .map(|spec| spec.parse::<point_set::Region>().unwrap())
+    .unwrap_or_default();
+  let generator = match point_set::Generator::new(generator_name, grid_density, count, &region) {
+    Ok(generator) => generator,
+    Err(e) => {
+      eprintln!("couldn't create point set: {}", e);
+      return;
+    }
+  };
+  let seed = matches.value_of("seed")
+    .map(|s| s.parse::<u64>().unwrap())
+    .unwrap_or_else(|| rand::thread_rng().gen());
+  if matches.is_present("seed") && !generator.is_randomized() {
+    eprintln!("warning: the {} generator is deterministic and ignores --seed",
+      generator_name);
+  }
+  let point_generator = generator.points(&region, seed);
+  let origin = Origin::Generated{
+    region: if region.is_everything() { None } else { Some(region.to_string()) },
+    seed: if generator.is_randomized() { Some(seed) } else { None },
+    generator,
+  };
+  match manager.save(name, overwrite, Some(origin), point_generator) {
+    Err(e) => { eprintln!("couldn't create point set: {}", e); },
+    Ok(info) => {
+      eprintln!("saved {} points as '{}'", info.count, name);
+      note_duplicates(manager, &info);
+      if count.is_some_and(|count| info.count < count as u64) {
+        eprintln!("warning: the generator gave up before finding {} points in the region",
+          count.unwrap());
+      }
+    },
   }
 }
 
 pub fn run_list(manager: &point_set::Manager, matches: &ArgMatches) {
-  let mut point_sets = manager.list().unwrap();
+  if matches.is_present("trash") {
+    run_list_trash(manager);
+    return;
+  }
+  let mut point_sets = vec![];
+  match manager.list() {
+    Ok(results) => {
+      for (name, result) in results {
+        match result {
+          Ok(info) => point_sets.push(info),
+          Err(e) => eprintln!("warning: couldn't read point set '{}': {}", name, e),
+        }
+      }
+    },
+    Err(e) => {
+      eprintln!("couldn't list point sets: {}", e);
+      return;
+    }
+  };
   let mut table = Tabulator::new(vec![
     String::from("name"),
     String::from("count"),
     String::from("created")]);
-  point_sets.sort_by(|a,b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+  point_sets.sort_by_key(|info| info.name.to_lowercase());
   for info in point_sets {
     let count = format!("{}", info.count);
     let created = info.created.to_rfc2822().to_string();
@@ -133,21 +322,217 @@ pub fn run_list(manager: &point_set::Manager, matches: &ArgMatches) {
   table.display();
 }
 
-pub fn run_print(manager: &point_set::Manager, matches: &ArgMatches) {
+pub fn run_info(manager: &point_set::Manager, matches: &ArgMatches) {
   let name = matches.value_of("name").unwrap();
-  let result = manager.load(name);
-  match result {
-    Err(e) => { eprintln!("couldn't load point set '{}': {}", name, e); },
-    Ok(point_set) => {
-      for p in point_set.points {
-        println!("{},{}", p.0.to_f64(), p.1.to_f64());
+  match manager.info(name) {
+    Err(e) => { eprintln!("couldn't read point set '{}': {}", name, e); },
+    Ok(info) => { print_info(&info); },
+  }
+}
+
+fn print_info(info: &PointSetInfo) {
+  println!("name: {}", info.name);
+  println!("count: {}", info.count);
+  println!("created: {}", info.created.to_rfc2822());
+  println!("format version: {}", info.format_version);
+  if let Some(hash) = &info.content_hash {
+    println!("content hash: {}", hash);
+  }
+  match &info.origin {
+    Some(Origin::Generated{generator, region, seed}) => {
+      // the generator's own metadata names it and lists its parameters.
+      let params = serde_json::to_value(generator).unwrap();
+      println!("generator: {}", params["generator"].as_str().unwrap_or("?"));
+      println!("grid density: {}", generator.grid_density());
+      if let Some(count) = params.get("count") {
+        println!("requested count: {}", count);
+      }
+      if let Some(region) = region {
+        println!("region: {}", region);
+      }
+      match seed {
+        Some(seed) => println!("seed: {}", seed),
+        None => println!("seed: (deterministic generator)"),
+      }
+    },
+    Some(Origin::Derived{operation, sources, region, seed}) => {
+      println!("derived by: {}", operation);
+      println!("sources: {}", sources.join(", "));
+      if let Some(region) = region {
+        println!("region: {}", region);
       }
+      if let Some(seed) = seed {
+        println!("seed: {}", seed);
+      }
+    },
+    Some(Origin::Imported{file, format}) => {
+      println!("imported from: {} ({})", file, format);
+    },
+    None => { println!("origin: unknown"); },
+  }
+  match &info.provenance {
+    Some(provenance) => {
+      println!("crate version: {}", provenance.crate_version);
+      let args: Vec<String> = provenance.command_line.iter()
+        .map(|arg| shell_quote(arg))
+        .collect();
+      println!("command line: {}", args.join(" "));
+    },
+    None => { println!("provenance: unknown"); },
+  }
+}
+
+// quotes an argument so that a printed command line can be pasted back into
+// a shell.
+fn shell_quote(arg: &str) -> String {
+  let plain = !arg.is_empty() && arg.chars().all(|c|
+    c.is_ascii_alphanumeric() || "-_./,=:+".contains(c));
+  if plain {
+    arg.to_string()
+  } else {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+  }
+}
+
+pub fn run_print(manager: &point_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let result = manager.stream(name).and_then(|points| {
+    for p in points {
+      let p = p?;
+      println!("{},{}", p.0.to_f64(), p.1.to_f64());
+    }
+    Ok(())
+  });
+  if let Err(e) = result {
+    eprintln!("couldn't load point set '{}': {}", name, e);
+  }
+}
+
+fn run_list_trash(manager: &point_set::Manager) {
+  let trashed = match manager.list_trash() {
+    Ok(trashed) => trashed,
+    Err(e) => {
+      eprintln!("couldn't list deleted point sets: {}", e);
+      return;
     }
+  };
+  let mut table = Tabulator::new(vec![
+    String::from("name"),
+    String::from("count"),
+    String::from("deleted")]);
+  for t in trashed {
+    let count = match &t.info {
+      Ok(info) => format!("{}", info.count),
+      Err(e) => {
+        eprintln!("warning: couldn't read deleted point set '{}': {}", t.name, e);
+        String::from("?")
+      },
+    };
+    table.append(vec![t.name, count, t.deleted.to_rfc2822()]);
   }
+  table.display();
 }
 
 pub fn run_delete(manager: &point_set::Manager, matches: &ArgMatches) {
   let name = matches.value_of("name").unwrap();
-  eprintln!("deleting point sets hasn't been implemented");
-  //eprintln!("deleting point set '{}'", name);
+  match manager.delete(name) {
+    Err(e) => { eprintln!("couldn't delete point set: {}", e); },
+    Ok(()) => {
+      eprintln!("moved point set '{}' to the trash (undo with 'pointset restore {}')",
+        name, name);
+    }
+  }
+}
+
+pub fn run_restore(manager: &point_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  match manager.restore(name) {
+    Err(e) => { eprintln!("couldn't restore point set: {}", e); },
+    Ok(info) => { eprintln!("restored {} points as '{}'", info.count, name); },
+  }
+}
+
+// points out other sets with the same contents as a newly saved one.
+fn note_duplicates(manager: &point_set::Manager, info: &PointSetInfo) {
+  let hash = match &info.content_hash {
+    Some(hash) => hash,
+    None => return,
+  };
+  if let Ok(matches) = manager.find_by_hash(hash) {
+    for other in matches.iter().filter(|other| other.name != info.name) {
+      eprintln!("note: '{}' has the same points as '{}'", info.name, other.name);
+    }
+  }
+}
+
+pub fn run_verify(manager: &point_set::Manager, matches: &ArgMatches) {
+  let results = match matches.value_of("name") {
+    Some(name) => vec![(name.to_string(), manager.verify(name))],
+    None => match manager.verify_all() {
+      Ok(results) => results,
+      Err(e) => {
+        eprintln!("couldn't list point sets: {}", e);
+        return;
+      }
+    },
+  };
+  let mut failures = 0;
+  for (name, result) in &results {
+    match result {
+      Ok(point_set::Verification::Matched) => { println!("{}: ok", name); },
+      Ok(point_set::Verification::Unhashed{hash}) => {
+        println!("{}: ok, but no stored hash to check (contents now hash to {})", name, hash);
+      },
+      Err(e) => {
+        println!("{}: FAILED: {}", name, e);
+        failures += 1;
+      },
+    }
+  }
+  if failures > 0 {
+    eprintln!("{} of {} point sets failed verification", failures, results.len());
+  }
+}
+
+pub fn run_dedupe(manager: &point_set::Manager, matches: &ArgMatches) {
+  if matches.is_present("dry_run") {
+    match manager.duplicates() {
+      Err(e) => { eprintln!("couldn't find duplicate point sets: {}", e); },
+      Ok(groups) => {
+        for group in &groups {
+          let names: Vec<&str> = group.iter().map(|info| info.name.as_str()).collect();
+          println!("{}", names.join(" = "));
+        }
+        eprintln!("found {} groups of identical point sets", groups.len());
+      },
+    }
+    return;
+  }
+  match manager.dedupe() {
+    Err(e) => { eprintln!("couldn't remove duplicate point sets: {}", e); },
+    Ok(removed) => {
+      for (info, kept) in &removed {
+        eprintln!("moved '{}' to the trash (same points as '{}')", info.name, kept);
+      }
+      eprintln!("removed {} duplicate point sets", removed.len());
+    },
+  }
+}
+
+pub fn run_purge(manager: &point_set::Manager, matches: &ArgMatches) {
+  let older_than = matches.value_of("older_than").map(|age| parse_age(age).unwrap());
+  match manager.purge(older_than) {
+    Err(e) => { eprintln!("couldn't purge deleted point sets: {}", e); },
+    Ok(purged) => {
+      for t in &purged {
+        match &t.info {
+          Ok(info) => eprintln!("purged '{}' ({} points, deleted {})",
+            t.name, info.count, t.deleted.to_rfc2822()),
+          Err(_) => eprintln!("purged unreadable '{}' (deleted {})",
+            t.name, t.deleted.to_rfc2822()),
+        }
+      }
+      eprintln!("purged {} point sets", purged.len());
+    }
+  }
 }
\ No newline at end of file
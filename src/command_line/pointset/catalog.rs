@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use crate::billiards::staged::StageStats;
+use crate::billiards::turn_path::TurnPath;
+use crate::data::{path_set, point_set, results};
+use crate::data::path_set::PathEntry;
+use crate::util::set_cover;
+
+// how long an exact search may take before settling for the greedy cover.
+const MAX_EXACT_STEPS: u64 = 1_000_000;
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("catalog")
+    .about("Chooses a few words that together cover a point set")
+    .long_about("Tests every word found by `pointset cover`, along with any \
+      given with --word, at every point of a set, and chooses a short list of \
+      words that between them are periodic orbits at every point any of them \
+      is. Words are printed in order of choice as word, then the number of \
+      points it adds to the words before it, then the number of points it \
+      covers on its own.")
+    .arg(Arg::with_name("name")
+      .index(1)
+      .required(true)
+      .help("The point set to cover")
+    )
+    .arg(Arg::with_name("word")
+      .long("word")
+      .value_name("word")
+      .multiple(true)
+      .number_of_values(1)
+      .allow_hyphen_values(true)
+      .validator(|word| word.parse::<TurnPath>().map(|_| {}))
+      .help("Another candidate word, e.g. '-2 2' or 'B0^-1 B1'")
+    )
+    .arg(Arg::with_name("save")
+      .long("save")
+      .value_name("pathset")
+      .help("Also add the chosen words to this path set, creating it if needed")
+    )
+    .arg(Arg::with_name("exact")
+      .long("exact")
+      .help("Find a smallest list rather than choosing greedily, if the \
+        instance is small enough")
+    )
+}
+
+pub fn run(data_path: &Path, manager: &point_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let mut words = match manager.cover_results(name) {
+    Ok(cover) => cover.words(),
+    Err(e) => {
+      eprintln!("couldn't read the results for '{}': {}", name, e);
+      return;
+    }
+  };
+  for word in matches.values_of("word").into_iter().flatten() {
+    let word: TurnPath = word.parse().unwrap();
+    if !words.contains(&word) {
+      words.push(word);
+    }
+  }
+  if words.is_empty() {
+    eprintln!("no words to choose from: run `pointset cover {}` or give --word", name);
+    return;
+  }
+
+  // checking every word at every point is slow, so the results are kept.
+  let results = results::manager(data_path.join("results"));
+  let mut stats = StageStats::default();
+  let feasible = match results.feasibility(manager, name, &words, &mut stats) {
+    Ok(feasible) => feasible,
+    Err(e) => {
+      eprintln!("couldn't load point set '{}': {}", name, e);
+      return;
+    }
+  };
+  if stats.total() > 0 {
+    eprintln!("made {}", stats);
+  }
+  let picks = if matches.is_present("exact") {
+    set_cover::exact(&feasible, MAX_EXACT_STEPS).unwrap_or_else(|| {
+      eprintln!("warning: too many words for an exact search, choosing greedily");
+      set_cover::greedy(&feasible)
+    })
+  } else {
+    set_cover::greedy(&feasible)
+  };
+  let mut covered = 0;
+  for pick in &picks {
+    covered += pick.gain;
+    println!("{}\t{}\t{}", words[pick.set], pick.gain, feasible[pick.set].len());
+  }
+  eprintln!("{} of {} words cover {} points", picks.len(), words.len(), covered);
+
+  if let Some(path_set_name) = matches.value_of("save") {
+    let entries = picks.iter().map(|pick| PathEntry{
+      word: words[pick.set].clone(),
+      note: Some(format!("covers {} points, {} more than the words before it",
+        feasible[pick.set].len(), pick.gain)),
+      source: Some(format!("pointset catalog {}", name)),
+    });
+    let path_sets = path_set::manager(data_path.join("path_set"));
+    let result = if path_sets.exists(path_set_name) {
+      Ok(())
+    } else {
+      path_sets.create(path_set_name, None).map(|_| {})
+    };
+    match result.and_then(|_| path_sets.add(path_set_name, entries)) {
+      Err(e) => { eprintln!("couldn't save to path set '{}': {}", path_set_name, e); },
+      Ok(added) => { eprintln!("added {} words to path set '{}'", added.len(), path_set_name); },
+    }
+  }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use crate::billiards::words::Budget;
+use crate::command_line::words::{budget, budget_args};
+use crate::data::{point_set, results};
+
+// thin triangles allow very large turns, so by default the search is
+// bounded by turning as well as by length.
+const DEFAULT_BUDGET: Budget = Budget{max_len: 6, max_turning: Some(20)};
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("cover")
+    .about("Searches for a periodic orbit at each point of a set")
+    .long_about("Searches each point of a set for a periodic orbit, trying \
+      shorter words first, and saves the word found for each point. The \
+      search can be interrupted with ctrl-c and resumed by running it again: \
+      points already covered are skipped, and points left uncovered are only \
+      searched again with a larger budget. A word already checked at every \
+      point of the set, e.g. by `pointset plot --filter`, covers the points \
+      where it's an orbit without a search, if it's within the budget. \
+      Uncovered points are printed as x,y when the search is done. Without \
+      --max-len or --max-turning, words have at most 6 turns, and without \
+      --max-turning at most 20 absolute turning.")
+    .arg(Arg::with_name("name")
+      .index(1)
+      .required(true)
+      .help("The point set to cover")
+    )
+    .args(&budget_args())
+    .arg(Arg::with_name("report")
+      .long("report")
+      .conflicts_with_all(&["max_len", "max_turning", "restart"])
+      .help("Print the stored results as x,y,word without searching, \
+        with - for points left uncovered")
+    )
+    .arg(Arg::with_name("restart")
+      .long("restart")
+      .help("Forget the stored results and search every point again")
+    )
+}
+
+pub fn run(data_path: &Path, manager: &point_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  if matches.is_present("report") {
+    run_report(manager, name);
+    return;
+  }
+  if matches.is_present("restart") {
+    if let Err(e) = manager.clear_cover(name) {
+      eprintln!("couldn't clear the results for '{}': {}", name, e);
+      return;
+    }
+  }
+  // a longer search still needs the default cap on turning.
+  let budget = match budget(matches) {
+    Some(budget) if !matches.is_present("max_turning") =>
+      Budget{max_turning: DEFAULT_BUDGET.max_turning, ..budget},
+    Some(budget) => budget,
+    None => DEFAULT_BUDGET,
+  };
+
+  // the first ctrl-c stops the search cleanly, and a second one kills it.
+  let stop = Arc::new(AtomicBool::new(false));
+  let handler_stop = Arc::clone(&stop);
+  let handler = ctrlc::set_handler(move || {
+    if handler_stop.swap(true, Ordering::SeqCst) {
+      std::process::exit(130);
+    }
+    eprintln!("\nstopping...");
+  });
+  if let Err(e) = handler {
+    eprintln!("warning: can't catch ctrl-c, so it can't be stopped cleanly: {}", e);
+  }
+
+  let total = match manager.cover_results(name) {
+    Ok(cover) => cover.count,
+    Err(e) => {
+      eprintln!("couldn't cover point set '{}': {}", name, e);
+      return;
+    }
+  };
+  let mut last_progress = Instant::now();
+  let mut found = 0;
+  let results = results::manager(data_path.join("results"));
+  let result = manager.cover(name, budget, &results, &stop, |_, entry| {
+    if entry.word.is_some() {
+      found += 1;
+    }
+    if last_progress.elapsed() >= PROGRESS_INTERVAL {
+      eprintln!("searched up to point {} of {}, found {} orbits", entry.point + 1, total, found);
+      last_progress = Instant::now();
+    }
+  });
+  let summary = match result {
+    Ok(summary) => summary,
+    Err(e) => {
+      eprintln!("couldn't cover point set '{}': {}", name, e);
+      return;
+    }
+  };
+  eprintln!("searched {} points and found {} orbits", summary.searched, summary.found);
+  if summary.interrupted {
+    eprintln!("interrupted; run the same command again to resume");
+    return;
+  }
+  print_uncovered(manager, name);
+}
+
+fn print_uncovered(manager: &point_set::Manager, name: &str) {
+  let cover = match manager.cover_results(name) {
+    Ok(cover) => cover,
+    Err(e) => {
+      eprintln!("couldn't read the results for '{}': {}", name, e);
+      return;
+    }
+  };
+  let points = match manager.stream(name) {
+    Ok(points) => points,
+    Err(e) => {
+      eprintln!("couldn't load point set '{}': {}", name, e);
+      return;
+    }
+  };
+  for (i, p) in points.enumerate() {
+    match p {
+      Ok(p) => {
+        if cover.entries.get(&(i as u64)).is_some_and(|e| e.word.is_none()) {
+          println!("{},{}", p.0, p.1);
+        }
+      },
+      Err(e) => {
+        eprintln!("couldn't load point set '{}': {}", name, e);
+        return;
+      }
+    }
+  }
+  eprintln!("{} of {} points covered, {} uncovered",
+    cover.covered(), cover.count, cover.uncovered().count());
+}
+
+fn run_report(manager: &point_set::Manager, name: &str) {
+  let result = manager.cover_results(name)
+    .and_then(|cover| manager.stream(name).map(|points| (cover, points)));
+  let (cover, points) = match result {
+    Ok(result) => result,
+    Err(e) => {
+      eprintln!("couldn't read the results for '{}': {}", name, e);
+      return;
+    }
+  };
+  for (i, p) in points.enumerate() {
+    let p = match p {
+      Ok(p) => p,
+      Err(e) => {
+        eprintln!("couldn't load point set '{}': {}", name, e);
+        return;
+      }
+    };
+    if let Some(entry) = cover.entries.get(&(i as u64)) {
+      match &entry.word {
+        Some(word) => println!("{},{},{}", p.0, p.1, word),
+        None => println!("{},{},-", p.0, p.1),
+      }
+    }
+  }
+  eprintln!("{} of {} points covered, {} uncovered, {} not searched",
+    cover.covered(), cover.count, cover.uncovered().count(), cover.unsearched());
+}
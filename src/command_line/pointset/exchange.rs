@@ -0,0 +1,125 @@
+use std::io;
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use crate::data;
+use crate::data::point_set;
+use crate::data::point_set::{ExchangeFormat, EXCHANGE_FORMAT_NAMES};
+
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("format")
+    .short("f")
+    .long("format")
+    .takes_value(true)
+    .possible_values(EXCHANGE_FORMAT_NAMES)
+    .help("The file format (defaults to the file's extension)")
+}
+
+pub fn subcommands<'a, 'b>() -> Vec<App<'a, 'b>> {
+  vec![
+    SubCommand::with_name("export")
+      .about("Writes a point set to a csv, json or npy file")
+      .long_about("Writes a point set to a file. csv and json keep every \
+        coordinate exact; npy stores float64 values.")
+      .arg(Arg::with_name("name")
+        .index(1)
+        .required(true)
+        .help("The point set to export")
+      )
+      .arg(Arg::with_name("file")
+        .index(2)
+        .required(true)
+        .help("The file to write, or - for standard output")
+      )
+      .arg(format_arg()),
+    SubCommand::with_name("import")
+      .about("Creates a point set from a csv, json or npy file")
+      .arg(Arg::with_name("name")
+        .index(1)
+        .required(true)
+        .help("The name of the new point set")
+      )
+      .arg(Arg::with_name("file")
+        .index(2)
+        .required(true)
+        .help("The file to read")
+      )
+      .arg(format_arg())
+      .arg(Arg::with_name("overwrite")
+        .short("o")
+        .long("overwrite")
+        .help("Overwrite the point set if it already exists")
+      ),
+  ]
+}
+
+// the explicit --format, or else the one named by the file's extension.
+fn format(matches: &ArgMatches, path: &Path) -> Result<ExchangeFormat, String> {
+  match matches.value_of("format") {
+    Some(name) => Ok(ExchangeFormat::from_name(name).unwrap()),
+    None => ExchangeFormat::from_path(path).ok_or_else(|| format!(
+      "can't tell the format of '{}' from its name, use --format", path.display())),
+  }
+}
+
+pub fn run(manager: &point_set::Manager, command: &str, matches: &ArgMatches) {
+  match command {
+    "export" => run_export(manager, matches),
+    "import" => run_import(manager, matches),
+    _ => unreachable!(),
+  }
+}
+
+fn run_export(manager: &point_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let file = matches.value_of("file").unwrap();
+  let to_stdout = file == "-";
+  let format = if to_stdout && !matches.is_present("format") {
+    Ok(ExchangeFormat::Csv)
+  } else {
+    format(matches, Path::new(file))
+  };
+  let format = match format {
+    Ok(format) => format,
+    Err(e) => {
+      eprintln!("couldn't export point set: {}", e);
+      return;
+    }
+  };
+  // a file is written beside its destination and only moved into place
+  // once complete, so a failed export leaves whatever was there.
+  let exported = if to_stdout {
+    manager.export(name, format, || Ok(io::stdout()))
+  } else {
+    let mut exported = None;
+    data::write_atomically(Path::new(file), |writer| {
+      exported = Some(manager.export(name, format, || Ok(writer))?);
+      Ok(())
+    }).map(|()| exported.unwrap())
+  };
+  match exported {
+    Err(e) => { eprintln!("couldn't export point set '{}': {}", name, e); },
+    Ok(info) => {
+      if !to_stdout {
+        eprintln!("wrote {} points to '{}' as {}", info.count, file, format.name());
+      }
+    },
+  }
+}
+
+fn run_import(manager: &point_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let path = Path::new(matches.value_of("file").unwrap());
+  let overwrite = matches.is_present("overwrite");
+  let result = format(matches, path)
+    .and_then(|format| manager.import(name, overwrite, path, format)
+      .map_err(|e| e.to_string()));
+  match result {
+    Err(e) => { eprintln!("couldn't import point set: {}", e); },
+    Ok(info) => {
+      eprintln!("saved {} points as '{}'", info.count, info.name);
+      super::note_duplicates(manager, &info);
+    },
+  }
+}
@@ -0,0 +1,163 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+use rand::Rng;
+
+use crate::data::point_set;
+use crate::data::Result;
+use crate::data::point_set::{PointSetInfo, Region};
+
+fn overwrite_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("overwrite")
+    .short("o")
+    .long("overwrite")
+    .help("Overwrite the output set if it already exists")
+}
+
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("output")
+    .index(1)
+    .required(true)
+    .help("The name of the new point set")
+}
+
+fn inputs_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+  Arg::with_name("inputs")
+    .index(2)
+    .required(true)
+    .multiple(true)
+    .help(help)
+}
+
+pub fn subcommands<'a, 'b>() -> Vec<App<'a, 'b>> {
+  vec![
+    SubCommand::with_name("copy")
+      .about("Copies a point set")
+      .arg(Arg::with_name("source")
+        .index(1)
+        .required(true)
+        .help("The point set to copy")
+      )
+      .arg(Arg::with_name("dest")
+        .index(2)
+        .required(true)
+        .help("The name of the copy")
+      )
+      .arg(overwrite_arg()),
+    SubCommand::with_name("rename")
+      .about("Renames a point set")
+      .arg(Arg::with_name("source")
+        .index(1)
+        .required(true)
+        .help("The point set to rename")
+      )
+      .arg(Arg::with_name("dest")
+        .index(2)
+        .required(true)
+        .help("The new name")
+      ),
+    SubCommand::with_name("merge")
+      .about("Creates a point set containing every point of the given sets")
+      .arg(output_arg())
+      .arg(inputs_arg("The point sets to merge"))
+      .arg(overwrite_arg()),
+    SubCommand::with_name("subtract")
+      .about("Creates a point set from the points of one set that aren't in the others")
+      .arg(output_arg())
+      .arg(inputs_arg("The point set to subtract from, followed by the sets to remove"))
+      .arg(overwrite_arg()),
+    SubCommand::with_name("intersect")
+      .about("Creates a point set containing the points common to all the given sets")
+      .arg(output_arg())
+      .arg(inputs_arg("The point sets to intersect"))
+      .arg(overwrite_arg()),
+    SubCommand::with_name("sample")
+      .about("Creates a point set from a random subset of another")
+      .arg(output_arg())
+      .arg(Arg::with_name("source")
+        .index(2)
+        .required(true)
+        .help("The point set to sample from")
+      )
+      .arg(Arg::with_name("count")
+        .short("c")
+        .long("count")
+        .takes_value(true)
+        .required(true)
+        .validator(|count| {
+          count.parse::<usize>()
+            .map(|_| {})
+            .map_err(|_| "expected integer".to_string())
+        })
+        .help("The number of points to keep")
+      )
+      .arg(Arg::with_name("seed")
+        .long("seed")
+        .takes_value(true)
+        .validator(|seed| {
+          seed.parse::<u64>()
+            .map(|_| {})
+            .map_err(|_| "expected a nonnegative integer".to_string())
+        })
+        .help("Seed for choosing the points (chosen at random if omitted)")
+      )
+      .arg(overwrite_arg()),
+    SubCommand::with_name("filter")
+      .about("Creates a point set from the points of another that lie in a region")
+      .arg(output_arg())
+      .arg(Arg::with_name("source")
+        .index(2)
+        .required(true)
+        .help("The point set to filter")
+      )
+      .arg(Arg::with_name("region")
+        .long("region")
+        .takes_value(true)
+        .required(true)
+        .validator(|spec| spec.parse::<Region>().map(|_| {}))
+        .help("The region to keep, in the same form as for 'pointset create'")
+      )
+      .arg(overwrite_arg()),
+  ]
+}
+
+pub fn run(manager: &point_set::Manager, command: &str, matches: &ArgMatches) {
+  let overwrite = matches.is_present("overwrite");
+  let inputs: Vec<&str> = matches.values_of("inputs")
+    .map(|values| values.collect())
+    .unwrap_or_default();
+  let result: Result<PointSetInfo> = match command {
+    "copy" => manager.copy(
+      matches.value_of("source").unwrap(),
+      matches.value_of("dest").unwrap(),
+      overwrite),
+    "rename" => manager.rename(
+      matches.value_of("source").unwrap(),
+      matches.value_of("dest").unwrap()),
+    "merge" => manager.merge(
+      matches.value_of("output").unwrap(), &inputs, overwrite),
+    "subtract" => manager.subtract(
+      matches.value_of("output").unwrap(), inputs[0], &inputs[1..], overwrite),
+    "intersect" => manager.intersect(
+      matches.value_of("output").unwrap(), &inputs, overwrite),
+    "sample" => manager.sample(
+      matches.value_of("output").unwrap(),
+      matches.value_of("source").unwrap(),
+      matches.value_of("count").unwrap().parse::<usize>().unwrap(),
+      overwrite,
+      matches.value_of("seed")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap_or_else(|| rand::thread_rng().gen())),
+    "filter" => manager.filter(
+      matches.value_of("output").unwrap(),
+      matches.value_of("source").unwrap(),
+      &matches.value_of("region").unwrap().parse::<Region>().unwrap(),
+      overwrite),
+    _ => unreachable!(),
+  };
+  match result {
+    Err(e) => { eprintln!("couldn't {} point sets: {}", command, e); },
+    Ok(info) => {
+      eprintln!("saved {} points as '{}'", info.count, info.name);
+      super::note_duplicates(manager, &info);
+    },
+  }
+}
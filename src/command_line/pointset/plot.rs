@@ -1,19 +1,17 @@
-use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufWriter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 use clap::{Arg, ArgMatches, App, SubCommand};
 use rug::Rational;
 
-use crate::algebra::{Zero, One};
-use crate::billiards::Params;
-use crate::billiards::base_edge::BaseEdge;
-use crate::data::point_set;
-use crate::billiards::singularity::{BaseOrientation, BaseValues};
-use crate::data::point_set::{Point, PointSet};
-use crate::vector::V2;
+use crate::billiards::orbit::CHECK_VERSION;
+use crate::billiards::staged::StageStats;
+use crate::data;
+use crate::data::{point_set, results};
+use crate::billiards::turn_path::TurnPath;
+use crate::data::point_set::{Origin, Point, PointSet, Region};
+use crate::data::point_set::region::Constraint;
 
 type PointIter = Box<dyn Iterator<Item = Point>>;
 
@@ -28,68 +26,61 @@ pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
     .arg(Arg::with_name("filter")
       .long("filter")
       .takes_value(false)
+      .help("Only plot points where the --word trajectory is feasible")
+    )
+    .arg(Arg::with_name("word")
+      .long("word")
+      .takes_value(true)
+      .requires("filter")
+      .allow_hyphen_values(true)
+      .validator(|word| word.parse::<TurnPath>().map(|_| {}))
+      .help("The turn path to filter by, e.g. '-2 2 2 -2' or 'B1^-2 B0^2' (default -2 2 2 -2)")
     )
     
 }
 
-fn test_path_on_point(p: &Point) -> bool {
-  let apex: V2<Rational> = V2::from(p.to_owned());
-  let mut params = Params::new(apex);
-
-  //let turns: Vec<i32> = vec![-9, 6, 9, -6];
-  //let turns: Vec<i32> = vec![-6, 3, 6, -3];
-  let turns: Vec<i32> = vec![-2, 2, 2, -2];
-  let mut edge = BaseEdge::new(
-    &mut params,
-    BaseValues(
-      V2(Rational::zero(), Rational::zero()),
-      V2(Rational::one(), Rational::zero()),
-    ),
-    BaseOrientation::Forward,
-  );
-  let mut left_points = vec![edge.left_apex()];
-  let mut right_points = vec![edge.right_apex()];
-  for turn in turns {
-    if (turn.abs() as u32) > edge.params.max_turn_around(edge.to()) {
-      return false;
-    }
-    edge.step(turn);
-    left_points.push(edge.left_apex());
-    right_points.push(edge.right_apex());
-    if turn > 0 {
-      left_points.push(edge.from_coords());
-    } else {
-      right_points.push(edge.from_coords());
-    }
-  }
-  let offset = left_points.last().unwrap().clone() - left_points.first().unwrap();
-  let normal = V2(-offset.1, offset.0);
-  let dot_normal = |v: &V2<Rational>| v.dot(&normal);
-  let left_offsets = left_points.iter().map(dot_normal);
-  let right_offsets = right_points.iter().map(dot_normal);
-  left_offsets.min().unwrap() > right_offsets.max().unwrap()
-
-  //p.0.to_owned() * &p.0 - &p.1 < 0
-  /*let left = apex.clone() * apex.clone() / left_norm;
-  let right = right_edge.clone() * right_edge.clone() / right_norm;
-  left.0 > 0 && right.0 > 0*/
-  //true
-}
+const DEFAULT_WORD: &str = "-2 2 2 -2";
 
-pub fn run(data_path: &PathBuf, manager: &point_set::Manager, matches: &ArgMatches) {
+pub fn run(data_path: &Path, manager: &point_set::Manager, matches: &ArgMatches) {
   let name = matches.value_of("name").unwrap();
   //plot::run();
-  match manager.load(name) {
+  match manager.stream(name) {
     Err(e) => { eprintln!("couldn't load point set '{}': {}", name, e); },
-    Ok(point_set) => {
-      let mut points_iter = point_set.points.iter();
-      let result = if matches.is_present("filter") {
-        _do_plot(data_path, name,
-          &mut points_iter.filter(|p| {
-            test_path_on_point(&p)
-          }))
+    Ok(mut points_iter) => {
+      let region = match &points_iter.info.origin {
+        Some(Origin::Generated{region: Some(spec), ..}) => spec.parse::<Region>().ok(),
+        _ => None,
+      };
+      let filter = matches.is_present("filter");
+      let word = matches.value_of("word").unwrap_or(DEFAULT_WORD);
+      let path = word.parse::<TurnPath>().unwrap();
+      // the plotted points depend only on the set's contents and the
+      // filter, so they're cached under the set's content hash.
+      let cache_key = points_iter.info.content_hash.as_ref().map(|hash| {
+        let hex = hash.rsplit(':').next().unwrap();
+        if filter {
+          let word_key = path.canonical_unmirrored().to_string().replace(' ', "_").replace('^', "");
+          format!("{}-filtered-v{}-{}", hex, CHECK_VERSION, word_key)
+        } else {
+          hex.to_string()
+        }
+      });
+      let result = if filter {
+        let results = results::manager(data_path.join("results"));
+        let mut stats = StageStats::default();
+        match results.results(manager, name, &[path], &mut stats) {
+          Ok(mut results) => {
+            if stats.total() > 0 {
+              eprintln!("made {}", stats);
+            }
+            let feasible = results.pop().unwrap();
+            _do_plot(data_path, name, region.as_ref(), cache_key,
+              &mut points_iter.zip(feasible).filter(|(_, r)| r.feasible).map(|(p, _)| p))
+          },
+          Err(e) => Err(format!("couldn't check '{}': {}", word, e)),
+        }
       } else {
-        _do_plot(data_path, name, &mut points_iter)
+        _do_plot(data_path, name, region.as_ref(), cache_key, &mut points_iter)
       };
       if let Err(e) = result {
         eprintln!("error plotting point set '{}': {}", name, e);
@@ -98,23 +89,33 @@ pub fn run(data_path: &PathBuf, manager: &point_set::Manager, matches: &ArgMatch
   }
 }
 
-fn _do_plot<'a, I: Iterator<Item=&'a Point>>(
-  data_path: &PathBuf, name: &str, points_iter: &mut I,
+fn _do_plot<I: Iterator<Item=data::Result<Point>>>(
+  data_path: &Path, name: &str, region: Option<&Region>, cache_key: Option<String>,
+  points_iter: &mut I,
 ) -> Result<(), String> {
   let plot_dir_path = data_path.join("plots");
-  std::fs::create_dir_all(&plot_dir_path).map_err(|e| format!("{}", e).to_string())?;
+  let cache_dir_path = plot_dir_path.join("cache");
+  std::fs::create_dir_all(&cache_dir_path).map_err(|e| format!("{}", e).to_string())?;
 
-  let points_path = plot_dir_path.join(format!("{}.csv", name));
-  save_points_as_csv(points_iter, &points_path)
-    .map_err(|e| format!("{}", e).to_string())?;
+  let points_path = match &cache_key {
+    Some(key) => cache_dir_path.join(format!("{}.csv", key)),
+    None => plot_dir_path.join(format!("{}.csv", name)),
+  };
+  if cache_key.is_some() && points_path.is_file() {
+    eprintln!("using cached points from {}", points_path.display());
+  } else {
+    save_points_as_csv(points_iter, &points_path)
+      .map_err(|e| format!("{}", e).to_string())?;
+  }
 
-  let png_path = points_path.with_extension("png");
+  let png_path = plot_dir_path.join(format!("{}.png", name));
   let spec = GnuplotSpec{
     name,
     points_path: &points_path,
     png_path: &png_path,
     line_weight: 2.0,
     point_size: 1.4,
+    region,
   };
   let gnuplot_input = spec.render();
   let mut child = Command::new("gnuplot")
@@ -149,9 +150,40 @@ struct GnuplotSpec<'a> {
   png_path: &'a PathBuf,
   line_weight: f32,
   point_size: f32,
+  // outlines of the region the points were generated in, if any. angle
+  // constraints aren't drawn.
+  region: Option<&'a Region>,
 }
 
 impl GnuplotSpec<'_> {
+  fn render_region(&self) -> String {
+    let style = "front fillstyle empty border lc rgb '#cc3333' lw 2";
+    let mut objects = Vec::new();
+    let circle = |center: &Point, radius: &Rational| format!(
+      "circle at {},{} size {} {}",
+      center.0.to_f64(), center.1.to_f64(), radius.to_f64(), style);
+    for constraint in self.region.map(|r| r.0.iter()).into_iter().flatten() {
+      match constraint {
+        Constraint::Disc{center, radius} => objects.push(circle(center, radius)),
+        Constraint::Annulus{center, inner, outer} => {
+          objects.push(circle(center, inner));
+          objects.push(circle(center, outer));
+        },
+        Constraint::Polygon(vertices) => {
+          let corners: Vec<String> = vertices.iter().chain(vertices.first())
+            .map(|v| format!("{},{}", v.0.to_f64(), v.1.to_f64()))
+            .collect();
+          objects.push(format!("polygon from {} {}", corners.join(" to "), style));
+        },
+        Constraint::Angle{..} => {},
+      }
+    }
+    // object 1 is the half-disc of obtuse apexes.
+    objects.iter().enumerate()
+      .map(|(i, object)| format!("set object {} {}\n", i + 2, object))
+      .collect()
+  }
+
   fn render(&self) -> String {
     format!(r#"
 set terminal png large size 1600,1000 font "Verdana,16"
@@ -173,30 +205,35 @@ set timestamp
 set style line 1 lc rgb '#666699' lw {} pt 6 ps {}
 
 set object 1 circle at 0.5,0 size 0.5 behind
-plot \
+{}plot \
   "{}" with points ls 1 title "{}"
 quit
 "#,
-      format!("{}", self.png_path.to_str().unwrap()),
-      format!("{}", self.line_weight),
-      format!("{}", self.point_size),
-      format!("{}", self.points_path.to_str().unwrap()),
+      self.png_path.to_str().unwrap(),
+      self.line_weight,
+      self.point_size,
+      self.render_region(),
+      self.points_path.to_str().unwrap(),
       self.name
     ).to_string()
   }
   }
 
-pub fn save_points_as_csv<'a, I: Iterator<Item=&'a Point>>(
-  points_iter: &mut I, file_path: &PathBuf,
-) -> Result<(), std::io::Error> {
+/// saves the points as csv, only moving the file into place once they're
+/// all written so a failure never leaves a truncated file to be reused as
+/// a cache.
+pub fn save_points_as_csv<I: Iterator<Item=data::Result<Point>>>(
+  points_iter: &mut I, file_path: &Path,
+) -> data::Result<()> {
   let mut count = 0;
-  let file = File::create(file_path)?;
-  let mut writer = BufWriter::new(file);
-  for p in points_iter {
-    write!(writer, "{},{}\n", p.0.to_f64(), p.1.to_f64())?;
-    count += 1;
-  }
-  writer.flush()?;
+  data::write_atomically(file_path, |writer| {
+    for p in points_iter {
+      let p = p?;
+      writeln!(writer, "{},{}", p.0.to_f64(), p.1.to_f64())?;
+      count += 1;
+    }
+    Ok(())
+  })?;
   eprintln!("saved {} points", count);
   Ok(())
 }
\ No newline at end of file
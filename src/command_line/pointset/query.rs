@@ -0,0 +1,136 @@
+use clap::{Arg, ArgGroup, ArgMatches, App, SubCommand};
+use rug::Rational;
+
+use crate::data::point_set;
+use crate::data::point_set::{Point, Region};
+use crate::util::rational::{parse_rational, parse_rational_pair};
+
+fn validate_point(s: String) -> Result<(), String> {
+  parse_rational_pair(&s)
+    .map(|_| {})
+    .ok_or_else(|| format!("expected a point x,y, got '{}'", s))
+}
+
+// the center and radius given to --within.
+fn parse_within(center: &str, radius: &str) -> Option<(Point, Rational)> {
+  let center = parse_rational_pair(center)?;
+  let radius = parse_rational(radius).filter(|r| *r >= 0)?;
+  Some((center, radius))
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("query")
+    .about("Finds the points of a set in a box, disc or region, or nearest a point")
+    .long_about("Finds points of a set using its spatial index, which is built \
+      and saved on first use. Coordinates may be integers, fractions (7/16) or \
+      decimals, and matching points are printed exactly as x,y.")
+    .arg(Arg::with_name("name")
+      .index(1)
+      .required(true)
+      .help("The point set to search")
+    )
+    .arg(Arg::with_name("box")
+      .long("box")
+      .value_names(&["min", "max"])
+      .number_of_values(2)
+      .validator(validate_point)
+      .help("Points with min.x <= x <= max.x and min.y <= y <= max.y")
+    )
+    .arg(Arg::with_name("nearest")
+      .long("nearest")
+      .value_name("point")
+      .validator(validate_point)
+      .help("The points nearest to this one, with their distances")
+    )
+    .arg(Arg::with_name("count")
+      .short("c")
+      .long("count")
+      .takes_value(true)
+      .requires("nearest")
+      .validator(|count| {
+        count.parse::<usize>()
+          .map(|_| {})
+          .map_err(|_| "expected integer".to_string())
+      })
+      .help("How many nearest points to find (default 1)")
+    )
+    .arg(Arg::with_name("within")
+      .long("within")
+      .value_names(&["center", "radius"])
+      .number_of_values(2)
+      .validator(|s| {
+        // clap validates the center and the radius alike, so accept either
+        // here and check their order once both are known.
+        if parse_rational_pair(&s).is_some() || parse_rational(&s).is_some() {
+          Ok(())
+        } else {
+          Err(format!("expected a point x,y or a radius, got '{}'", s))
+        }
+      })
+      .help("Points at most radius away from center")
+    )
+    .arg(Arg::with_name("region")
+      .long("region")
+      .takes_value(true)
+      .validator(|spec| spec.parse::<Region>().map(|_| {}))
+      .help("Points in a region, in the same form as for 'pointset create' \
+        (e.g. 'annulus 0.5,0 0.49 0.5' for points near the boundary)")
+    )
+    .group(ArgGroup::with_name("query")
+      .args(&["box", "nearest", "within", "region"])
+      .required(true)
+    )
+}
+
+fn print_point(p: &Point) {
+  println!("{},{}", p.0, p.1);
+}
+
+pub fn run(manager: &point_set::Manager, matches: &ArgMatches) {
+  let name = matches.value_of("name").unwrap();
+  let within = match matches.values_of("within") {
+    Some(mut values) => {
+      match parse_within(values.next().unwrap(), values.next().unwrap()) {
+        Some(within) => Some(within),
+        None => {
+          eprintln!("--within expects a point x,y and a nonnegative radius");
+          return;
+        }
+      }
+    },
+    None => None,
+  };
+  let tree = match manager.index(name) {
+    Ok(tree) => tree,
+    Err(e) => {
+      eprintln!("couldn't index point set '{}': {}", name, e);
+      return;
+    }
+  };
+
+  let mut found = if let Some(mut values) = matches.values_of("box") {
+    let min = parse_rational_pair(values.next().unwrap()).unwrap();
+    let max = parse_rational_pair(values.next().unwrap()).unwrap();
+    tree.in_box(&min, &max)
+  } else if let Some(target) = matches.value_of("nearest") {
+    let target = parse_rational_pair(target).unwrap();
+    let count = matches.value_of("count").map_or(1, |c| c.parse::<usize>().unwrap());
+    let nearest = tree.nearest(&target, count);
+    for (p, squared_distance) in &nearest {
+      let distance = squared_distance.to_f64().sqrt();
+      println!("{},{},{}", p.0, p.1, distance);
+    }
+    eprintln!("found {} of {} points", nearest.len(), tree.len());
+    return;
+  } else if let Some((center, radius)) = within {
+    tree.within(&center, &radius)
+  } else {
+    let region = matches.value_of("region").unwrap().parse::<Region>().unwrap();
+    tree.in_region(&region)
+  };
+  found.sort();
+  for p in &found {
+    print_point(p);
+  }
+  eprintln!("found {} of {} points", found.len(), tree.len());
+}
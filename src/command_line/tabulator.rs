@@ -10,7 +10,7 @@ pub struct Tabulator {
 impl Tabulator {
   pub fn new(header: Vec<String>) -> Tabulator {
     let column_widths = header.iter().map(|s| s.len()).collect();
-    return Tabulator{header, column_widths, rows: Vec::new()}
+    Tabulator{header, column_widths, rows: Vec::new()}
   }
 
   pub fn append(&mut self, row: Vec<String>) {
@@ -28,25 +28,25 @@ impl Tabulator {
   pub fn display(&self) {
     let text_len: usize = self.column_widths.iter().sum();
     let margin_len = 3 * self.column_widths.len() - 1;
-    let top = std::iter::repeat("_").take(text_len + margin_len).collect::<String>();
+    let top = "_".repeat(text_len + margin_len);
     println!("+{}+", top);
     self.display_row(&self.header);
     self.display_blank_row();
     for row in &self.rows {
       self.display_row(row);
     }
-    let bottom = std::iter::repeat("-").take(text_len + margin_len).collect::<String>();
+    let bottom = "-".repeat(text_len + margin_len);
     println!("+{}+", bottom);
   }
 
-  fn display_row(&self, row: &Vec<String>) {
+  fn display_row(&self, row: &[String]) {
     let mut result = "|".to_string();
     for (i, s) in row.iter().enumerate() {
       result.push_str(&format!(" {} ", s.cyan()));
       let padding_len = self.column_widths[i] - s.len();
-      let padding = std::iter::repeat(" ").take(padding_len).collect::<String>();
+      let padding = " ".repeat(padding_len);
       result.push_str(&padding);
-      result.push_str("|");
+      result.push('|');
     }
     println!("{}", result);
   }
@@ -55,9 +55,9 @@ impl Tabulator {
     let mut result = "|".to_string();
     for width in &self.column_widths {
       let padding_len = width + 2;
-      let padding = std::iter::repeat("-").take(padding_len).collect::<String>();
+      let padding = "-".repeat(padding_len);
       result.push_str(&padding);
-      result.push_str("|");
+      result.push('|');
     }
     println!("{}", result);
   }
@@ -0,0 +1,94 @@
+use clap::{Arg, ArgGroup, ArgMatches, App, SubCommand};
+use rug::Rational;
+
+use crate::billiards::Params;
+use crate::billiards::words::{Budget, orbits};
+use crate::util::rational::parse_rational_pair;
+use crate::vector::V2;
+
+/// parses the apex of a triangle over the base (0,0) -> (1,0).
+pub fn parse_apex(s: &str) -> Option<V2<Rational>> {
+  parse_rational_pair(s)
+    .filter(|(_, y)| *y > 0)
+    .map(V2::from)
+}
+
+/// the required --apex option, read with `parse_apex`.
+pub fn apex_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("apex")
+    .long("apex")
+    .value_name("x,y")
+    .required(true)
+    .allow_hyphen_values(true)
+    .validator(|s| parse_apex(&s)
+      .map(|_| {})
+      .ok_or_else(|| format!("expected an apex x,y with y > 0, got '{}'", s)))
+    .help("The triangle's apex, e.g. 0.4,0.3 or 2/5,3/10")
+}
+
+/// the --max-len and --max-turning options, which give a `Budget`.
+pub fn budget_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  vec![
+    Arg::with_name("max_len")
+      .short("n")
+      .long("max-len")
+      .value_name("N")
+      .validator(|n| {
+        n.parse::<usize>()
+          .map(|_| {})
+          .map_err(|_| "expected integer".to_string())
+      })
+      .help("The most turns a word may have"),
+    Arg::with_name("max_turning")
+      .short("t")
+      .long("max-turning")
+      .value_name("T")
+      .validator(|t| {
+        t.parse::<u64>()
+          .map(|_| {})
+          .map_err(|_| "expected integer".to_string())
+      })
+      .help("The most absolute turning (the sum of the turns' absolute \
+        degrees) a word may have"),
+  ]
+}
+
+/// the budget given by `budget_args`, if any.
+pub fn budget(matches: &ArgMatches) -> Option<Budget> {
+  let max_len = matches.value_of("max_len").map(|n| n.parse::<usize>().unwrap());
+  let max_turning = matches.value_of("max_turning").map(|t| t.parse::<u64>().unwrap());
+  match (max_len, max_turning) {
+    (Some(max_len), max_turning) => Some(Budget{max_len, max_turning}),
+    (None, Some(max_turning)) => Some(Budget::turning(max_turning)),
+    (None, None) => None,
+  }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("words")
+    .about("Lists the periodic orbits of a triangle up to a given length")
+    .long_about("Lists every turn word within the given budget that is a \
+      periodic orbit of the triangle with base (0,0) -> (1,0) and the given \
+      apex, with its corridor margin. Each orbit is listed once, although it \
+      can also be written as any rotation or reversal of its word.")
+    .arg(apex_arg())
+    .args(&budget_args())
+    .group(ArgGroup::with_name("budget")
+      .args(&["max_len", "max_turning"])
+      .multiple(true)
+      .required(true)
+    )
+}
+
+pub fn run(matches: &ArgMatches) {
+  let apex = parse_apex(matches.value_of("apex").unwrap()).unwrap();
+  let budget = budget(matches).unwrap();
+
+  let mut params = Params::new(apex);
+  let mut found = 0;
+  for (orbit, check) in orbits(&mut params, budget) {
+    found += 1;
+    println!("{}\t{}", orbit, check.margin().unwrap().to_f64());
+  }
+  eprintln!("found {} periodic orbits", found);
+}
@@ -1,14 +1,28 @@
+pub mod path_set;
 pub mod point_set;
+pub mod results;
 
 use std::error::Error;
 use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
 #[derive(Debug)]
 pub enum DataError {
   IOError(std::io::Error),
   JSONError(serde_json::Error),
   OsStringError(std::ffi::OsString),
-  Unimplemented(String)
+  Unimplemented(String),
+  // the named entry exists but its contents can't be read back faithfully
+  // (bad header, unknown format version, truncated or malformed records).
+  Corrupt(String),
+  NotFound(String),
+  AlreadyExists(String),
+  InvalidName(String),
+  // the arguments to an operation don't make sense together, e.g. no sets
+  // to intersect.
+  InvalidArgument(String),
 }
 
 impl fmt::Display for DataError {
@@ -18,7 +32,12 @@ impl fmt::Display for DataError {
       DataError::IOError(e) => write!(f, "{}", e),
       DataError::JSONError(e) => write!(f, "{}", e),
       DataError::OsStringError(e) => e.clone().into_string().expect("invalid unicode").fmt(f),
-      DataError::Unimplemented(s) => write!(f, "Unimplemented: {}", s)
+      DataError::Unimplemented(s) => write!(f, "Unimplemented: {}", s),
+      DataError::Corrupt(s) => write!(f, "corrupt data: {}", s),
+      DataError::NotFound(s) => write!(f, "'{}' doesn't exist", s),
+      DataError::AlreadyExists(s) => write!(f, "'{}' already exists", s),
+      DataError::InvalidName(s) => write!(f, "invalid name '{}'", s),
+      DataError::InvalidArgument(s) => write!(f, "{}", s),
     }
   }
 }
@@ -43,4 +62,60 @@ impl From<std::ffi::OsString> for DataError {
 
 pub type Result<T> = std::result::Result<T, DataError>;
 
-//use crate::geometry::*;
\ No newline at end of file
+// names are used as file names, and those beginning with '.' are reserved
+// for the managers' own use.
+fn validate_name(name: &str) -> Result<()> {
+  let valid = !name.is_empty() &&
+    !name.starts_with('.') &&
+    !name.contains(|c: char| c == '/' || c == '\\' || c.is_whitespace());
+  if valid {
+    Ok(())
+  } else {
+    Err(DataError::InvalidName(name.to_string()))
+  }
+}
+
+/// writes the file at `path` with `write`, first to a scratch file beside
+/// it that's only moved into place once complete, so a failed or
+/// interrupted write never leaves a partial file behind.
+pub fn write_atomically<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>
+{
+  let dir = path.parent().unwrap_or_else(|| Path::new("."));
+  let file_name = path.file_name()
+    .ok_or_else(|| DataError::InvalidName(path.display().to_string()))?;
+  fs::create_dir_all(dir)?;
+  let tmp_path = dir.join(format!(".tmp-{}", file_name.to_string_lossy()));
+  let mut writer = BufWriter::new(File::create(&tmp_path)?);
+  let written = write(&mut writer).and_then(|()| Ok(writer.flush()?));
+  drop(writer);
+  if let Err(e) = written {
+    let _ = fs::remove_file(&tmp_path);
+    return Err(e);
+  }
+  fs::rename(&tmp_path, path)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_write_atomically() {
+    let dir = std::env::temp_dir().join(format!("billiards-test-atomic-{}", std::process::id()));
+    let path = dir.join("file");
+    write_atomically(&path, |w| Ok(w.write_all(b"complete")?)).unwrap();
+    let failed = write_atomically(&path, |w| {
+      w.write_all(b"partial")?;
+      Err(DataError::Corrupt("failed".to_string()))
+    });
+    assert!(failed.is_err());
+    assert_eq!(fs::read(&path).unwrap(), b"complete");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "the scratch file should be removed");
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
+
+//use crate::geometry::*;
@@ -0,0 +1,278 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::billiards::turn_path::TurnPath;
+use crate::data::{validate_name, write_atomically, DataError, Result};
+
+/// a named collection of turn words, e.g. a catalog of the orbits that
+/// cover some region.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PathSet {
+  pub name: String,
+  pub created: DateTime<Utc>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub notes: Option<String>,
+  pub paths: Vec<PathEntry>,
+}
+
+/// one word of a path set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PathEntry {
+  pub word: TurnPath,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub note: Option<String>,
+  // where the word came from, e.g. a paper or the file it was imported
+  // from.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub source: Option<String>,
+}
+
+impl PathEntry {
+  pub fn new(word: TurnPath) -> PathEntry {
+    PathEntry{word, note: None, source: None}
+  }
+}
+
+/// path sets live under a single root directory, one file per set:
+///
+///   <root>/<name>.json    the `PathSet`
+///
+/// path sets are small, so each is read and written whole.
+pub struct Manager {
+  root: PathBuf,
+}
+
+pub fn manager(root: PathBuf) -> Manager {
+  Manager{root}
+}
+
+const EXTENSION: &str = "json";
+
+impl Manager {
+  fn set_path(&self, name: &str) -> PathBuf {
+    self.root.join(format!("{}.{}", name, EXTENSION))
+  }
+
+  pub fn exists(&self, name: &str) -> bool {
+    self.set_path(name).is_file()
+  }
+
+  fn write(&self, set: &PathSet) -> Result<()> {
+    write_atomically(&self.set_path(&set.name), |writer| {
+      Ok(serde_json::to_writer_pretty(writer, set)?)
+    })
+  }
+
+  /// saves a new, empty set called `name`.
+  pub fn create(&self, name: &str, notes: Option<String>) -> Result<PathSet> {
+    validate_name(name)?;
+    if self.exists(name) {
+      return Err(DataError::AlreadyExists(name.to_string()));
+    }
+    let set = PathSet{name: name.to_string(), created: Utc::now(), notes, paths: vec![]};
+    self.write(&set)?;
+    Ok(set)
+  }
+
+  pub fn load(&self, name: &str) -> Result<PathSet> {
+    validate_name(name)?;
+    if !self.exists(name) {
+      return Err(DataError::NotFound(name.to_string()));
+    }
+    let reader = BufReader::new(File::open(self.set_path(name))?);
+    serde_json::from_reader(reader)
+      .map_err(|e| DataError::Corrupt(format!("{}: {}", name, e)))
+  }
+
+  /// adds `entries` to set `name`, skipping words that are rotations or
+  /// reversals of words already in it, and returns the entries added.
+  pub fn add<I>(&self, name: &str, entries: I) -> Result<Vec<PathEntry>>
+  where
+      I: IntoIterator<Item = PathEntry>
+  {
+    let mut set = self.load(name)?;
+    let mut known: Vec<TurnPath> = set.paths.iter()
+      .map(|entry| entry.word.canonical_unmirrored())
+      .collect();
+    let mut added = vec![];
+    for entry in entries {
+      let canonical = entry.word.canonical_unmirrored();
+      if known.contains(&canonical) {
+        continue;
+      }
+      known.push(canonical);
+      added.push(entry.clone());
+      set.paths.push(entry);
+    }
+    self.write(&set)?;
+    Ok(added)
+  }
+
+  /// every set by name, loaded or with the reason it can't be, so one
+  /// damaged set doesn't hide the others.
+  pub fn list(&self) -> Result<Vec<(String, Result<PathSet>)>> {
+    let mut names = vec![];
+    if !self.root.is_dir() {
+      return Ok(vec![]);
+    }
+    for entry in fs::read_dir(&self.root)? {
+      let path = entry?.path();
+      let name = match path.file_stem().and_then(|s| s.to_str()) {
+        Some(name) if !name.starts_with('.') => name.to_string(),
+        _ => continue,
+      };
+      if path.extension().is_some_and(|ext| ext == EXTENSION) {
+        names.push(name);
+      }
+    }
+    names.sort();
+    Ok(names.into_iter()
+      .map(|name| {
+        let set = self.load(&name);
+        (name, set)
+      })
+      .collect())
+  }
+
+  pub fn delete(&self, name: &str) -> Result<()> {
+    validate_name(name)?;
+    if !self.exists(name) {
+      return Err(DataError::NotFound(name.to_string()));
+    }
+    fs::remove_file(self.set_path(name))?;
+    Ok(())
+  }
+
+  /// adds the words listed in `file` to set `name`, creating it if needed,
+  /// and returns the entries added. see `read_entries` for the format.
+  pub fn import(&self, name: &str, file: &Path) -> Result<Vec<PathEntry>> {
+    let entries = read_entries(BufReader::new(File::open(file)?), &file.display().to_string())?;
+    if !self.exists(name) {
+      self.create(name, None)?;
+    }
+    self.add(name, entries)
+  }
+}
+
+/// reads a word list: one word per line, optionally followed by a tab and
+/// a note and then by a tab and a source, which is how `pathset print`
+/// writes them. blank lines and lines starting with '#' are skipped.
+/// entries without a source are given `file_name:line` as their source.
+pub fn read_entries(reader: impl BufRead, file_name: &str) -> Result<Vec<PathEntry>> {
+  let mut entries = vec![];
+  for (i, line) in reader.lines().enumerate() {
+    let line = line?;
+    if line.trim().is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let mut fields = line.split('\t');
+    let word = fields.next().unwrap().trim().parse::<TurnPath>()
+      .map_err(|e| DataError::Corrupt(format!("{}:{}: {}", file_name, i + 1, e)))?;
+    let mut field = || fields.next().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+    let note = field();
+    let source = field().or_else(|| Some(format!("{}:{}", file_name, i + 1)));
+    entries.push(PathEntry{word, note, source});
+  }
+  Ok(entries)
+}
+
+/// writes `entries` in the format `read_entries` reads.
+pub fn write_entries(mut writer: impl Write, entries: &[PathEntry]) -> Result<()> {
+  for entry in entries {
+    let note = entry.note.as_deref().unwrap_or("");
+    match &entry.source {
+      Some(source) => writeln!(writer, "{}\t{}\t{}", entry.word, note, source)?,
+      None if !note.is_empty() => writeln!(writer, "{}\t{}", entry.word, note)?,
+      None => writeln!(writer, "{}", entry.word)?,
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn test_manager(label: &str) -> Manager {
+    let root = std::env::temp_dir().join(
+      format!("billiards-test-pathset-{}-{}", label, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    manager(root)
+  }
+
+  fn entry(word: &str) -> PathEntry {
+    PathEntry::new(word.parse().unwrap())
+  }
+
+  #[test]
+  fn test_path_sets() {
+    let manager = test_manager("basic");
+    assert!(manager.list().unwrap().is_empty());
+    manager.create("orbits", Some("short orbits".to_string())).unwrap();
+    assert!(matches!(manager.create("orbits", None), Err(DataError::AlreadyExists(_))));
+    assert!(matches!(manager.create(".hidden", None), Err(DataError::InvalidName(_))));
+    assert!(matches!(manager.load("other"), Err(DataError::NotFound(_))));
+
+    // rotations and reversals of known words are skipped, while mirror
+    // images are different orbits.
+    let added = manager.add("orbits", vec![
+      entry("-2 2 2 -2"), entry("2 -2 -2 2"), entry("-1 1"), entry("B0^1 B1^-1"),
+    ]).unwrap();
+    assert_eq!(added, vec![entry("-2 2 2 -2"), entry("-1 1")]);
+    let added = manager.add("orbits", vec![
+      entry("B0^-3 B1^-2 B0^3 B1^2"), entry("-3 -2 3 2"),
+    ]).unwrap();
+    assert_eq!(added.len(), 2);
+
+    let set = manager.load("orbits").unwrap();
+    assert_eq!(set.notes.as_deref(), Some("short orbits"));
+    assert_eq!(set.paths.len(), 4);
+    assert_eq!(manager.list().unwrap().len(), 1);
+
+    // a damaged set is listed with its error alongside the others.
+    fs::write(manager.set_path("broken"), "{").unwrap();
+    let listed = manager.list().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(matches!(listed[0], (ref name, Err(DataError::Corrupt(_))) if name == "broken"));
+    assert!(matches!(listed[1], (ref name, Ok(_)) if name == "orbits"));
+    manager.delete("broken").unwrap();
+
+    manager.delete("orbits").unwrap();
+    assert!(!manager.exists("orbits"));
+    assert!(matches!(manager.delete("orbits"), Err(DataError::NotFound(_))));
+  }
+
+  #[test]
+  fn test_entries() {
+    let text = "# a catalog\n-2 2 2 -2\tthe square\n\n-1 1\t\tpaper, fig. 3\nB0^-1 B1^2\n";
+    let entries = read_entries(text.as_bytes(), "cat.txt").unwrap();
+    assert_eq!(entries, vec![
+      PathEntry{
+        word: "-2 2 2 -2".parse().unwrap(),
+        note: Some("the square".to_string()),
+        source: Some("cat.txt:2".to_string()),
+      },
+      PathEntry{
+        word: "-1 1".parse().unwrap(),
+        note: None,
+        source: Some("paper, fig. 3".to_string()),
+      },
+      PathEntry{
+        word: "B0^-1 B1^2".parse().unwrap(),
+        note: None,
+        source: Some("cat.txt:5".to_string()),
+      },
+    ]);
+
+    // what's written reads back the same.
+    let mut written = vec![];
+    write_entries(&mut written, &entries).unwrap();
+    assert_eq!(read_entries(&written[..], "other.txt").unwrap(), entries);
+
+    assert!(read_entries("1 x\n".as_bytes(), "bad.txt").is_err());
+  }
+}
@@ -0,0 +1,495 @@
+mod cover;
+mod exchange;
+mod format;
+mod generate;
+mod hash;
+mod index;
+mod integrity;
+mod ops;
+pub mod region;
+mod trash;
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use rug::Rational;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{validate_name, write_atomically, DataError, Result};
+
+pub use self::cover::{Cover, CoverEntry, CoverSummary};
+pub use self::exchange::{ExchangeFormat, EXCHANGE_FORMAT_NAMES};
+pub use self::generate::{random_from_grid, Generator, SeededRng, GENERATOR_NAMES};
+pub use self::hash::content_hash;
+pub use self::index::KdTree;
+pub use self::integrity::Verification;
+pub use self::region::Region;
+pub use self::trash::TrashedPointSet;
+
+/// an apex point, as exact (x, y) coordinates.
+pub type Point = (Rational, Rational);
+
+/// metadata stored alongside each point set, cheap to load without reading
+/// the points themselves.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PointSetInfo {
+  pub name: String,
+  pub count: u64,
+  pub created: DateTime<Utc>,
+  // the version of the points file format this set was written with.
+  pub format_version: u32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub origin: Option<Origin>,
+  // missing for sets saved before provenance was recorded.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub provenance: Option<Provenance>,
+  // the hash of the set's points (see `hash.rs`), which identifies its
+  // contents independently of its name. missing for sets saved before
+  // hashes were recorded.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub content_hash: Option<String>,
+}
+
+/// the program that wrote a point set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Provenance {
+  pub crate_version: String,
+  // the full command line, starting with the program name.
+  pub command_line: Vec<String>,
+}
+
+impl Provenance {
+  /// the provenance of sets written by this process.
+  pub fn current() -> Provenance {
+    Provenance{
+      crate_version: env!("CARGO_PKG_VERSION").to_string(),
+      command_line: std::env::args().collect(),
+    }
+  }
+}
+
+/// how a point set was produced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Origin {
+  // produced by one of the point generators, keeping only the points in
+  // `region` (written as a region spec) if one was given. `seed` is set
+  // for randomized generators.
+  Generated {
+    #[serde(flatten)]
+    generator: Generator,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+  },
+  // computed from other sets by `operation` (e.g. "merge"), with `sources`
+  // naming the input sets as they were called at the time. `region` is the
+  // region spec for operations that select points by region, and `seed` is
+  // set for randomized operations.
+  Derived {
+    operation: String,
+    sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+  },
+  // read from `file` (an absolute path at the time) in one of the exchange
+  // formats.
+  Imported { file: String, format: String },
+}
+
+impl Origin {
+  pub fn derived(operation: &str, sources: &[&str]) -> Origin {
+    Origin::Derived{
+      operation: operation.to_string(),
+      sources: sources.iter().map(|s| s.to_string()).collect(),
+      region: None,
+      seed: None,
+    }
+  }
+}
+
+pub struct PointSet {
+  pub info: PointSetInfo,
+  pub points: Vec<Point>,
+}
+
+/// point sets live under a single root directory, one subdirectory per set:
+///
+///   <root>/<name>/info.json    the set's `PointSetInfo`
+///   <root>/<name>/points       header line followed by the points
+///   <root>/<name>/index*       the set's spatial index, if it's been built
+///
+/// names beginning with '.' are reserved for the manager's own use.
+pub struct Manager {
+  root: PathBuf,
+}
+
+pub fn manager(root: PathBuf) -> Manager {
+  Manager{root}
+}
+
+const INFO_FILE: &str = "info.json";
+const POINTS_FILE: &str = "points";
+
+fn write_info(path: &Path, info: &PointSetInfo) -> Result<()> {
+  write_atomically(&path.join(INFO_FILE), |writer| Ok(serde_json::to_writer_pretty(writer, info)?))
+}
+
+impl Manager {
+  fn set_path(&self, name: &str) -> PathBuf {
+    self.root.join(name)
+  }
+
+  pub fn exists(&self, name: &str) -> bool {
+    self.set_path(name).join(INFO_FILE).is_file()
+  }
+
+  /// writes the given points as a new set called `name`, recording this
+  /// process's `Provenance` in its metadata. the set is written
+  /// to a scratch directory first and only moved into place once complete,
+  /// so an interrupted save never leaves a partial set behind. a set
+  /// replaced with `overwrite` goes to the trash rather than being removed.
+  pub fn save<I>(
+      &self, name: &str, overwrite: bool, origin: Option<Origin>, points: I,
+  ) -> Result<PointSetInfo>
+  where
+      I: IntoIterator<Item = Point>
+  {
+    self.try_save(name, overwrite, origin, points.into_iter().map(Ok))
+  }
+
+  /// like `save`, but for points that may fail to arrive, as when they're
+  /// streamed from another set. on the first error nothing is saved.
+  pub fn try_save<I>(
+      &self, name: &str, overwrite: bool, origin: Option<Origin>, points: I,
+  ) -> Result<PointSetInfo>
+  where
+      I: IntoIterator<Item = Result<Point>>
+  {
+    validate_name(name)?;
+    let path = self.set_path(name);
+    if !overwrite && path.exists() {
+      return Err(DataError::AlreadyExists(name.to_string()));
+    }
+    fs::create_dir_all(&self.root)?;
+    let tmp_path = self.root.join(format!(".tmp-{}", name));
+    if tmp_path.exists() {
+      fs::remove_dir_all(&tmp_path)?;
+    }
+    fs::create_dir(&tmp_path)?;
+
+    // whatever fails, the scratch directory goes.
+    let saved = self.write_set(name, &tmp_path, origin, points).and_then(|info| {
+      if self.exists(name) {
+        self.delete(name)?;
+      }
+      fs::rename(&tmp_path, &path)?;
+      Ok(info)
+    });
+    if saved.is_err() {
+      let _ = fs::remove_dir_all(&tmp_path);
+    }
+    saved
+  }
+
+  // writes the points and info of set `name` into the directory `path`.
+  fn write_set<I>(
+      &self, name: &str, path: &Path, origin: Option<Origin>, points: I,
+  ) -> Result<PointSetInfo>
+  where
+      I: IntoIterator<Item = Result<Point>>
+  {
+    let mut writer = BufWriter::new(File::create(path.join(POINTS_FILE))?);
+    let mut hasher = hash::ContentHasher::new();
+    let points = points.into_iter().inspect(|p| {
+      if let Ok(p) = p {
+        hasher.update(p);
+      }
+    });
+    let count = format::write_points(&mut writer, points)?;
+    drop(writer);
+    let info = PointSetInfo{
+      name: name.to_string(),
+      count,
+      created: Utc::now(),
+      format_version: format::VERSION,
+      origin,
+      provenance: Some(Provenance::current()),
+      content_hash: Some(hasher.finish()),
+    };
+    write_info(path, &info)?;
+    Ok(info)
+  }
+
+  pub fn info(&self, name: &str) -> Result<PointSetInfo> {
+    validate_name(name)?;
+    if !self.exists(name) {
+      return Err(DataError::NotFound(name.to_string()));
+    }
+    self.read_info(name, &self.set_path(name))
+  }
+
+  // reads the info file from a set directory, which may be in the trash.
+  fn read_info(&self, name: &str, path: &Path) -> Result<PointSetInfo> {
+    let info_path = path.join(INFO_FILE);
+    let reader = BufReader::new(File::open(info_path)?);
+    let info: PointSetInfo = serde_json::from_reader(reader)
+      .map_err(|e| DataError::Corrupt(format!("{}: {}", name, e)))?;
+    if !format::is_supported(info.format_version) {
+      return Err(DataError::Corrupt(format!(
+        "{}: unsupported format version {}", name, info.format_version)));
+    }
+    Ok(info)
+  }
+
+  // the names of all set directories, without reading their info.
+  fn names(&self) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    if !self.root.is_dir() {
+      return Ok(names);
+    }
+    for entry in fs::read_dir(&self.root)? {
+      let entry = entry?;
+      let name = entry.file_name().into_string()?;
+      if name.starts_with('.') || !entry.path().is_dir() {
+        continue;
+      }
+      names.push(name);
+    }
+    Ok(names)
+  }
+
+  /// every set by name, with its info or the reason it can't be read, so
+  /// one damaged set doesn't hide the others.
+  pub fn list(&self) -> Result<Vec<(String, Result<PointSetInfo>)>> {
+    let mut names = self.names()?;
+    names.sort();
+    Ok(names.into_iter()
+      .map(|name| {
+        let info = self.info(&name);
+        (name, info)
+      })
+      .collect())
+  }
+
+  /// reads a whole set into memory. prefer `stream` for sets that may be
+  /// large.
+  pub fn load(&self, name: &str) -> Result<PointSet> {
+    let stream = self.stream(name)?;
+    let info = stream.info.clone();
+    let points = stream.collect::<Result<Vec<Point>>>()?;
+    Ok(PointSet{info, points})
+  }
+
+  /// the hash of set `name`'s points, computing it if it wasn't recorded.
+  pub fn content_hash(&self, name: &str) -> Result<String> {
+    let mut stream = self.stream(name)?;
+    if let Some(hash) = stream.info.content_hash.clone() {
+      return Ok(hash);
+    }
+    let mut hasher = hash::ContentHasher::new();
+    for p in &mut stream {
+      hasher.update(&p?);
+    }
+    Ok(hasher.finish())
+  }
+
+  /// opens a set for reading its points one at a time.
+  pub fn stream(&self, name: &str) -> Result<PointStream> {
+    let info = self.info(name)?;
+    let points_path = self.set_path(name).join(POINTS_FILE);
+    if !points_path.is_file() {
+      return Err(DataError::Corrupt(format!("{}: missing points file", name)));
+    }
+    let reader = format::PointReader::new(BufReader::new(File::open(points_path)?))
+      .map_err(|e| with_set_name(name, e))?;
+    Ok(PointStream{info, reader, read: 0, done: false})
+  }
+}
+
+fn with_set_name(name: &str, e: DataError) -> DataError {
+  match e {
+    DataError::Corrupt(s) => DataError::Corrupt(format!("{}: {}", name, s)),
+    e => e,
+  }
+}
+
+/// the points of a stored set, read from disk as they're needed. after the
+/// last point it checks that the set held as many points as its info says;
+/// like any error, a mismatch is the final item.
+pub struct PointStream {
+  pub info: PointSetInfo,
+  reader: format::PointReader<BufReader<File>>,
+  read: u64,
+  done: bool,
+}
+
+impl PointStream {
+  /// the format version declared by the points file itself.
+  pub fn format_version(&self) -> u32 {
+    self.reader.version()
+  }
+}
+
+impl Iterator for PointStream {
+  type Item = Result<Point>;
+
+  fn next(&mut self) -> Option<Result<Point>> {
+    if self.done {
+      return None;
+    }
+    match self.reader.next() {
+      Some(Ok(p)) => {
+        self.read += 1;
+        Some(Ok(p))
+      },
+      Some(Err(e)) => {
+        self.done = true;
+        Some(Err(with_set_name(&self.info.name, e)))
+      },
+      None => {
+        self.done = true;
+        if self.read == self.info.count {
+          None
+        } else {
+          Some(Err(DataError::Corrupt(format!("{}: expected {} points, found {}",
+            self.info.name, self.info.count, self.read))))
+        }
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  pub fn test_manager(label: &str) -> Manager {
+    let root = std::env::temp_dir().join(
+      format!("billiards-test-{}-{}", label, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    manager(root)
+  }
+
+  pub fn sample_points() -> Vec<Point> {
+    vec![
+      (Rational::from((1, 2)), Rational::from((1, 4))),
+      (Rational::from((3, 8)), Rational::from((-5, 16))),
+      (Rational::from(1), Rational::from(0)),
+    ]
+  }
+
+  #[test]
+  fn test_save_and_load() {
+    let manager = test_manager("save-load");
+    let info = manager.save("test", false, None, sample_points()).unwrap();
+    assert_eq!(info.count, 3);
+
+    let point_set = manager.load("test").unwrap();
+    assert_eq!(point_set.points, sample_points(), "points should round-trip exactly");
+    assert_eq!(point_set.info.name, "test");
+
+    let names: Vec<String> = manager.list().unwrap().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["test".to_string()]);
+
+    match manager.save("test", false, None, sample_points()) {
+      Err(DataError::AlreadyExists(_)) => {},
+      _ => panic!("saving over an existing set without overwrite should fail"),
+    }
+    manager.save("test", true, None, vec![]).expect("overwrite should succeed");
+    assert_eq!(manager.load("test").unwrap().points.len(), 0);
+    assert_eq!(manager.list_trash().unwrap().len(), 1, "overwritten set should be trashed");
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+
+  #[test]
+  fn test_errors() {
+    let manager = test_manager("errors");
+    match manager.load("missing") {
+      Err(DataError::NotFound(_)) => {},
+      _ => panic!("loading a missing set should fail with NotFound"),
+    }
+    match manager.save("../escape", false, None, vec![]) {
+      Err(DataError::InvalidName(_)) => {},
+      _ => panic!("names containing '/' should be rejected"),
+    }
+
+    manager.save("truncated", false, None, sample_points()).unwrap();
+    let points_path = manager.set_path("truncated").join(POINTS_FILE);
+    let contents = fs::read(&points_path).unwrap();
+    fs::write(&points_path, &contents[..contents.len() - 3]).unwrap();
+    match manager.load("truncated") {
+      Err(DataError::Corrupt(_)) => {},
+      _ => panic!("a truncated set should be reported as corrupt"),
+    }
+
+    fs::write(&points_path, "not a point set\n").unwrap();
+    match manager.load("truncated") {
+      Err(DataError::Corrupt(_)) => {},
+      _ => panic!("a bad header should be reported as corrupt"),
+    }
+
+    // a set with unreadable info doesn't stop the others being listed.
+    manager.save("good", false, None, sample_points()).unwrap();
+    fs::write(manager.set_path("truncated").join(INFO_FILE), "{").unwrap();
+    let listed = manager.list().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(matches!(listed[0], (ref name, Ok(_)) if name == "good"));
+    assert!(matches!(listed[1], (ref name, Err(DataError::Corrupt(_))) if name == "truncated"));
+
+    // a save that fails after writing its points leaves no scratch behind.
+    fs::write(manager.set_path("blocked"), "not a set").unwrap();
+    assert!(manager.save("blocked", true, None, sample_points()).is_err());
+    assert!(!manager.root.join(".tmp-blocked").exists());
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+
+  #[test]
+  fn test_stream() {
+    let manager = test_manager("stream");
+    let points: Vec<Point> = random_from_grid(20, 10000).collect();
+    manager.save("big", false, None, points.clone()).unwrap();
+    let streamed: Vec<Point> = manager.stream("big").unwrap()
+      .collect::<Result<_>>().unwrap();
+    assert_eq!(streamed, points);
+
+    // a failing stream saves nothing.
+    let failing = manager.stream("big").unwrap().take(5)
+      .chain(std::iter::once(Err(DataError::Corrupt("test".to_string()))));
+    assert!(manager.try_save("partial", false, None, failing).is_err());
+    assert!(!manager.exists("partial"));
+    assert_eq!(manager.list().unwrap().len(), 1);
+
+    // sets written in the original text format still load.
+    let path = manager.set_path("old");
+    fs::create_dir_all(&path).unwrap();
+    fs::write(path.join(POINTS_FILE), "billiards-point-set v1\n1/2 1/4\n3/8 -5/16\n1 0\n").unwrap();
+    fs::write(path.join(INFO_FILE), r#"{"name": "old", "count": 3,
+      "created": "2020-01-01T00:00:00Z", "format_version": 1}"#).unwrap();
+    assert_eq!(manager.load("old").unwrap().points, sample_points());
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+
+  #[test]
+  fn test_random_from_grid() {
+    let points: Vec<Point> = random_from_grid(10, 100).collect();
+    assert_eq!(points.len(), 100);
+    let half = Rational::from((1, 2));
+    let quarter = Rational::from((1, 4));
+    for (x, y) in points {
+      assert!(y > 0, "apex should be above the base");
+      assert!(*x.denom() <= 1024, "apex should lie on the grid");
+      let dx = x - &half;
+      let norm = Rational::from(dx.square_ref()) + y.square();
+      assert!(norm < quarter, "apex should be inside the half-disc");
+    }
+  }
+}
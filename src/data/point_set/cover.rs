@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::billiards::Params;
+use crate::billiards::turn_path::TurnPath;
+use crate::billiards::words::{Budget, Search};
+use crate::data::{results, write_atomically, DataError, Result};
+use crate::data::point_set::{Manager, Point};
+use crate::vector::V2;
+
+// covering a set means finding a periodic orbit for each of its points.
+// the results are stored next to the set as two files:
+//
+//   <root>/<name>/cover.json    a `CoverInfo` naming the hash of the
+//                               points the results belong to
+//   <root>/<name>/cover         one json `CoverEntry` per line, appended
+//                               as each point is searched
+//
+// since every entry is written as soon as it's found, a search that is
+// interrupted only loses its work on the current point, and the next
+// search picks up where it stopped. a point that a search couldn't cover
+// is searched again only under a larger budget. results whose hash
+// doesn't match the set's are discarded.
+//
+// before searching a point, the words with stored results for the set
+// (see `results`) are tried: the first one within the budget that is a
+// periodic orbit at the point covers it without a search, although a
+// search might have found a shorter one.
+
+const COVER_FILE: &str = "cover";
+const COVER_INFO_FILE: &str = "cover.json";
+const COVER_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct CoverInfo {
+  version: u32,
+  source_hash: String,
+}
+
+/// the outcome of searching one point for an orbit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoverEntry {
+  // the point's position in its set.
+  pub point: u64,
+  // the orbit found, or none if there was none within the budget.
+  pub word: Option<TurnPath>,
+  #[serde(flatten)]
+  pub budget: Budget,
+}
+
+impl CoverEntry {
+  /// whether this entry settles `budget`: it found an orbit, or searched
+  /// at least every word `budget` allows.
+  fn settles(&self, budget: &Budget) -> bool {
+    self.word.is_some() || self.budget.includes(budget)
+  }
+}
+
+/// the stored results of covering a set, by point.
+pub struct Cover {
+  pub count: u64,
+  pub entries: BTreeMap<u64, CoverEntry>,
+}
+
+impl Cover {
+  pub fn covered(&self) -> usize {
+    self.entries.values().filter(|e| e.word.is_some()).count()
+  }
+
+  /// the points searched without finding an orbit.
+  pub fn uncovered(&self) -> impl Iterator<Item = &CoverEntry> {
+    self.entries.values().filter(|e| e.word.is_none())
+  }
+
+  /// the number of points not searched at all.
+  pub fn unsearched(&self) -> u64 {
+    self.count - self.entries.len() as u64
+  }
+
+  /// the distinct words found, shortest first.
+  pub fn words(&self) -> Vec<TurnPath> {
+    let words: BTreeSet<(usize, &TurnPath)> = self.entries.values()
+      .filter_map(|e| e.word.as_ref())
+      .map(|word| (word.len(), word))
+      .collect();
+    words.into_iter().map(|(_, word)| word.clone()).collect()
+  }
+}
+
+/// what became of a call to `Manager::cover`.
+pub struct CoverSummary {
+  // the points searched by this call.
+  pub searched: u64,
+  // the points among them that were covered.
+  pub found: u64,
+  // whether the search stopped before reaching every point.
+  pub interrupted: bool,
+}
+
+fn read_entries(reader: impl BufRead) -> Result<BTreeMap<u64, CoverEntry>> {
+  let mut entries = BTreeMap::new();
+  for line in reader.lines() {
+    let line = line?;
+    // a search killed mid-write can leave a partial last line.
+    let entry: CoverEntry = match serde_json::from_str(&line) {
+      Ok(entry) => entry,
+      Err(_) => continue,
+    };
+    // later entries supersede earlier ones for the same point.
+    entries.insert(entry.point, entry);
+  }
+  Ok(entries)
+}
+
+impl Manager {
+  /// the cover results stored for set `name`, if they're for its current
+  /// points.
+  pub fn cover_results(&self, name: &str) -> Result<Cover> {
+    let info = self.info(name)?;
+    let path = self.set_path(name);
+    let cover_info: Option<CoverInfo> = File::open(path.join(COVER_INFO_FILE)).ok()
+      .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok());
+    let fresh = match cover_info {
+      Some(cover_info) => cover_info.version == COVER_VERSION &&
+        cover_info.source_hash == self.content_hash(name)?,
+      None => false,
+    };
+    let entries = match File::open(path.join(COVER_FILE)) {
+      Ok(f) if fresh => read_entries(BufReader::new(f))?,
+      _ => BTreeMap::new(),
+    };
+    Ok(Cover{count: info.count, entries})
+  }
+
+  /// searches each point of set `name` that earlier searches haven't
+  /// settled for an orbit within `budget`, unless `results` already holds
+  /// one, saving each result as it's found. `progress` sees every new
+  /// entry, and the search stops early once `stop` is set.
+  pub fn cover<F>(
+      &self, name: &str, budget: Budget, results: &results::Manager, stop: &AtomicBool, mut progress: F,
+  ) -> Result<CoverSummary>
+  where
+      F: FnMut(&Point, &CoverEntry)
+  {
+    let cover = self.cover_results(name)?;
+    let path = self.set_path(name);
+
+    // start the file afresh with one entry per point, which also drops any
+    // results for other contents.
+    write_atomically(&path.join(COVER_FILE), |writer| {
+      for entry in cover.entries.values() {
+        serde_json::to_writer(&mut *writer, entry)?;
+        writeln!(writer)?;
+      }
+      Ok(())
+    })?;
+    let cover_info = CoverInfo{version: COVER_VERSION, source_hash: self.content_hash(name)?};
+    write_atomically(&path.join(COVER_INFO_FILE), |writer| {
+      Ok(serde_json::to_writer_pretty(writer, &cover_info)?)
+    })?;
+
+    let known: Vec<_> = results.stored(self, name)?.into_iter()
+      .filter(|(word, _)| budget.allows(word))
+      .collect();
+    let mut file = OpenOptions::new().append(true).open(path.join(COVER_FILE))?;
+    let mut search = Search::new(budget);
+    let mut summary = CoverSummary{searched: 0, found: 0, interrupted: false};
+    for (i, p) in self.stream(name)?.enumerate() {
+      let p = p?;
+      let i = i as u64;
+      if cover.entries.get(&i).is_some_and(|e| e.settles(&budget)) {
+        continue;
+      }
+      if stop.load(Ordering::SeqCst) {
+        summary.interrupted = true;
+        break;
+      }
+      let stored = known.iter()
+        .find(|(_, results)| results[i as usize].feasible)
+        .map(|(word, _)| word.clone());
+      let word = stored.or_else(|| {
+        let mut params = Params::new(V2::from(&p));
+        search.find_while(&mut params, || !stop.load(Ordering::SeqCst))
+          .map(|(word, _)| word)
+      });
+      // a search cut short settles nothing.
+      if word.is_none() && stop.load(Ordering::SeqCst) {
+        summary.interrupted = true;
+        break;
+      }
+      let entry = CoverEntry{point: i, word, budget};
+      // each entry goes out in a single write, so an interruption can't
+      // split it from its newline.
+      let mut line = serde_json::to_string(&entry).map_err(DataError::from)?;
+      line.push('\n');
+      file.write_all(line.as_bytes())?;
+      summary.searched += 1;
+      if entry.word.is_some() {
+        summary.found += 1;
+      }
+      progress(&p, &entry);
+    }
+    Ok(summary)
+  }
+
+  /// forgets the cover results of set `name`.
+  pub fn clear_cover(&self, name: &str) -> Result<()> {
+    let path = self.set_path(name);
+    for file in &[COVER_FILE, COVER_INFO_FILE] {
+      if path.join(file).exists() {
+        fs::remove_file(path.join(file))?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+  use crate::billiards::staged::StageStats;
+  use crate::data::point_set::content_hash;
+  use crate::data::point_set::tests::test_manager;
+
+  fn test_results(name: &str) -> results::Manager {
+    let results = results::manager(std::env::temp_dir().join(
+      format!("billiards-test-cover-results-{}-{}", name, std::process::id())));
+    results.clear().unwrap();
+    results
+  }
+
+  #[test]
+  fn test_cover() {
+    let manager = test_manager("cover");
+    let q = |x: i32, y: i32| (Rational::from((x, 32)), Rational::from((y, 32)));
+    // the first two have orbits of length 2 and 4, the last has none that
+    // short.
+    let points = vec![q(16, 12), q(19, 10), q(10, 10)];
+    manager.save("s", false, None, points.clone()).unwrap();
+    let results = test_results("cover");
+
+    // a stop that is already set searches nothing.
+    let stopped = AtomicBool::new(true);
+    let summary = manager.cover("s", Budget::length(2), &results, &stopped, |_, _| {}).unwrap();
+    assert!(summary.interrupted);
+    assert_eq!(summary.searched, 0);
+
+    let running = AtomicBool::new(false);
+    let mut seen = vec![];
+    let summary = manager.cover("s", Budget::length(2), &results, &running, |p, e| {
+      seen.push((p.clone(), e.word.is_some()));
+    }).unwrap();
+    assert!(!summary.interrupted);
+    assert_eq!((summary.searched, summary.found), (3, 1));
+    assert_eq!(seen, vec![(q(16, 12), true), (q(19, 10), false), (q(10, 10), false)]);
+
+    let cover = manager.cover_results("s").unwrap();
+    assert_eq!(cover.covered(), 1);
+    assert_eq!(cover.unsearched(), 0);
+    assert_eq!(cover.entries[&0].word.as_ref().unwrap().to_string(), "-2 2");
+
+    // the same budget settles everything, while a larger one only
+    // searches the uncovered points again.
+    let summary = manager.cover("s", Budget::length(2), &results, &running, |_, _| {}).unwrap();
+    assert_eq!(summary.searched, 0);
+    let summary = manager.cover("s", Budget::length(4), &results, &running, |_, _| {}).unwrap();
+    assert_eq!((summary.searched, summary.found), (2, 1));
+    let cover = manager.cover_results("s").unwrap();
+    assert_eq!(cover.covered(), 2);
+    assert_eq!(cover.uncovered().map(|e| e.point).collect::<Vec<_>>(), vec![2]);
+    assert_eq!(cover.words().iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+      vec!["-2 2", "B0^-3 B1^-2 B0^3 B1^2"]);
+
+    // a partial line from an interrupted write is ignored.
+    let mut file = OpenOptions::new().append(true)
+      .open(manager.set_path("s").join(COVER_FILE)).unwrap();
+    file.write_all(b"{\"point\":2,\"wo").unwrap();
+    assert_eq!(manager.cover_results("s").unwrap().entries.len(), 3);
+
+    // results for other contents are discarded.
+    let stale = CoverInfo{version: COVER_VERSION, source_hash: content_hash(&points[..2])};
+    let info_file = File::create(manager.set_path("s").join(COVER_INFO_FILE)).unwrap();
+    serde_json::to_writer(info_file, &stale).unwrap();
+    let cover = manager.cover_results("s").unwrap();
+    assert!(cover.entries.is_empty());
+    assert_eq!(cover.unsearched(), 3);
+  }
+
+  #[test]
+  fn test_cover_stored() {
+    let manager = test_manager("cover-stored");
+    let q = |x: i32, y: i32| (Rational::from((x, 32)), Rational::from((y, 32)));
+    manager.save("s", false, None, vec![q(16, 12), q(19, 10)]).unwrap();
+    let results = test_results("stored");
+    let words: Vec<TurnPath> = vec!["-2 2 2 -2".parse().unwrap()];
+    results.results(&manager, "s", &words, &mut StageStats::default()).unwrap();
+
+    // a stored word beyond the budget is ignored, and one within it covers
+    // the first point although the search would find "-2 2" there.
+    let running = AtomicBool::new(false);
+    manager.cover("s", Budget::length(2), &results, &running, |_, _| {}).unwrap();
+    assert_eq!(manager.cover_results("s").unwrap().entries[&0].word.as_ref().unwrap().to_string(), "-2 2");
+    manager.clear_cover("s").unwrap();
+    let summary = manager.cover("s", Budget::length(4), &results, &running, |_, _| {}).unwrap();
+    assert_eq!((summary.searched, summary.found), (2, 2));
+    let cover = manager.cover_results("s").unwrap();
+    assert_eq!(cover.entries[&0].word, Some(words[0].canonical_unmirrored()));
+    assert_eq!(cover.entries[&1].word.as_ref().unwrap().to_string(), "B0^-3 B1^-2 B0^3 B1^2");
+    results.clear().unwrap();
+  }
+}
@@ -0,0 +1,400 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use rug::{Integer, Rational};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{DataError, Result};
+use crate::data::point_set::{Manager, Origin, Point, PointSetInfo};
+use crate::util::rational::parse_rational;
+
+// formats for moving point sets to and from other tools. csv and json keep
+// every coordinate exact; npy holds float64 values, so exporting to it
+// rounds each coordinate to the nearest double, and importing from it
+// gives the exact rational value of each double.
+//
+//   csv    a header line "x,y", then one "p/q,p/q" line per point. on
+//          import, integers and finite decimals are accepted as well, and
+//          blank lines and lines starting with '#' are skipped.
+//   json   {"points": [{"x": {"num": "p", "den": "q"}, "y": ...}, ...]},
+//          with numerators and denominators written as decimal strings so
+//          that no json reader truncates them.
+//   npy    a NumPy array of shape (count, 2) and dtype '<f8'.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExchangeFormat {
+  Csv,
+  Json,
+  Npy,
+}
+
+pub const EXCHANGE_FORMAT_NAMES: &[&str] = &["csv", "json", "npy"];
+
+impl ExchangeFormat {
+  pub fn from_name(name: &str) -> Option<ExchangeFormat> {
+    match name {
+      "csv" => Some(ExchangeFormat::Csv),
+      "json" => Some(ExchangeFormat::Json),
+      "npy" => Some(ExchangeFormat::Npy),
+      _ => None,
+    }
+  }
+
+  /// the format named by a file's extension.
+  pub fn from_path(path: &Path) -> Option<ExchangeFormat> {
+    path.extension()
+      .and_then(|ext| ext.to_str())
+      .and_then(|ext| ExchangeFormat::from_name(&ext.to_lowercase()))
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      ExchangeFormat::Csv => "csv",
+      ExchangeFormat::Json => "json",
+      ExchangeFormat::Npy => "npy",
+    }
+  }
+
+  pub fn write<W: Write>(&self, writer: &mut W, points: &[Point]) -> Result<()> {
+    match self {
+      ExchangeFormat::Csv => write_csv(writer, points),
+      ExchangeFormat::Json => write_json(writer, points),
+      ExchangeFormat::Npy => write_npy(writer, points),
+    }
+  }
+
+  pub fn read<R: BufRead>(&self, reader: &mut R) -> Result<Vec<Point>> {
+    match self {
+      ExchangeFormat::Csv => read_csv(reader),
+      ExchangeFormat::Json => read_json(reader),
+      ExchangeFormat::Npy => read_npy(reader),
+    }
+  }
+}
+
+pub fn write_csv<W: Write>(writer: &mut W, points: &[Point]) -> Result<()> {
+  writeln!(writer, "x,y")?;
+  for (x, y) in points {
+    writeln!(writer, "{},{}", x, y)?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+pub fn read_csv<R: BufRead>(reader: &mut R) -> Result<Vec<Point>> {
+  Ok(read_csv_lines(reader)?.into_iter().map(|(_, p)| p).collect())
+}
+
+// the points of a csv file with the line each came from, counting from 1.
+fn read_csv_lines<R: BufRead>(reader: &mut R) -> Result<Vec<(usize, Point)>> {
+  let mut points = Vec::new();
+  for (index, line) in reader.lines().enumerate() {
+    let line = line?;
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') ||
+        (index == 0 && line.eq_ignore_ascii_case("x,y")) {
+      continue;
+    }
+    let malformed = || DataError::Corrupt(
+      format!("line {}: malformed point '{}'", index + 1, line));
+    let mut fields = line.split(',');
+    let x = fields.next().and_then(parse_rational).ok_or_else(malformed)?;
+    let y = fields.next().and_then(parse_rational).ok_or_else(malformed)?;
+    if fields.next().is_some() {
+      return Err(malformed());
+    }
+    points.push((index + 1, (x, y)));
+  }
+  Ok(points)
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonRational {
+  num: String,
+  den: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonPoint {
+  x: JsonRational,
+  y: JsonRational,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonPoints {
+  points: Vec<JsonPoint>,
+}
+
+impl From<&Rational> for JsonRational {
+  fn from(r: &Rational) -> JsonRational {
+    JsonRational{num: r.numer().to_string(), den: r.denom().to_string()}
+  }
+}
+
+impl JsonRational {
+  fn to_rational(&self) -> Result<Rational> {
+    let malformed = || DataError::Corrupt(
+      format!("malformed rational {}/{}", self.num, self.den));
+    let num = self.num.parse::<Integer>().map_err(|_| malformed())?;
+    let den = self.den.parse::<Integer>().map_err(|_| malformed())?;
+    if den == 0 {
+      return Err(malformed());
+    }
+    Ok(Rational::from((num, den)))
+  }
+}
+
+pub fn write_json<W: Write>(writer: &mut W, points: &[Point]) -> Result<()> {
+  let points = JsonPoints{
+    points: points.iter()
+      .map(|(x, y)| JsonPoint{x: x.into(), y: y.into()})
+      .collect(),
+  };
+  serde_json::to_writer(&mut *writer, &points)?;
+  writeln!(writer)?;
+  writer.flush()?;
+  Ok(())
+}
+
+pub fn read_json<R: BufRead>(reader: &mut R) -> Result<Vec<Point>> {
+  let points: JsonPoints = serde_json::from_reader(reader)?;
+  points.points.iter()
+    .map(|p| Ok((p.x.to_rational()?, p.y.to_rational()?)))
+    .collect()
+}
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+pub fn write_npy<W: Write>(writer: &mut W, points: &[Point]) -> Result<()> {
+  let mut header = format!(
+    "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, 2), }}", points.len());
+  // the magic string, version and header length take 10 bytes, and the
+  // whole preamble must be padded to a multiple of 64 ending in a newline.
+  let padding = 63 - (10 + header.len()) % 64;
+  header.push_str(&" ".repeat(padding));
+  header.push('\n');
+  writer.write_all(NPY_MAGIC)?;
+  writer.write_all(&[1, 0])?;
+  writer.write_all(&(header.len() as u16).to_le_bytes())?;
+  writer.write_all(header.as_bytes())?;
+  for (x, y) in points {
+    writer.write_all(&x.to_f64().to_le_bytes())?;
+    writer.write_all(&y.to_f64().to_le_bytes())?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+// the value of `key` in a npy header dictionary, e.g. "'<f8'" for 'descr'.
+fn npy_header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+  let start = header.find(&format!("'{}':", key))? + key.len() + 3;
+  let rest = header[start..].trim_start();
+  let end = if rest.starts_with('(') {
+    rest.find(')')? + 1
+  } else {
+    rest.find([',', '}'])?
+  };
+  Some(rest[..end].trim())
+}
+
+pub fn read_npy<R: Read>(reader: &mut R) -> Result<Vec<Point>> {
+  let corrupt = |s: &str| DataError::Corrupt(format!("npy: {}", s));
+  let mut magic = [0u8; 8];
+  reader.read_exact(&mut magic)?;
+  if &magic[..6] != NPY_MAGIC {
+    return Err(corrupt("not a NumPy array file"));
+  }
+  let header_len = match magic[6] {
+    1 => {
+      let mut len = [0u8; 2];
+      reader.read_exact(&mut len)?;
+      u16::from_le_bytes(len) as usize
+    },
+    2 | 3 => {
+      let mut len = [0u8; 4];
+      reader.read_exact(&mut len)?;
+      u32::from_le_bytes(len) as usize
+    },
+    v => return Err(corrupt(&format!("unsupported version {}", v))),
+  };
+  let mut header = vec![0u8; header_len];
+  reader.read_exact(&mut header)?;
+  let header = String::from_utf8(header).map_err(|_| corrupt("header isn't text"))?;
+
+  match npy_header_value(&header, "descr") {
+    Some("'<f8'") | Some("'float64'") => {},
+    descr => return Err(corrupt(&format!(
+      "expected little-endian float64 values, found {}", descr.unwrap_or("none")))),
+  }
+  if npy_header_value(&header, "fortran_order") != Some("False") {
+    return Err(corrupt("fortran-ordered arrays aren't supported"));
+  }
+  let shape = npy_header_value(&header, "shape").unwrap_or("");
+  let dims: Vec<&str> = shape.trim_start_matches('(').trim_end_matches(')')
+    .split(',')
+    .map(|d| d.trim())
+    .filter(|d| !d.is_empty())
+    .collect();
+  let count = match dims.as_slice() {
+    [count, "2"] => count.parse::<usize>().map_err(|_| corrupt("malformed shape"))?,
+    _ => return Err(corrupt(&format!("expected an array of shape (count, 2), found {}", shape))),
+  };
+
+  // the count comes from the file, so it's only trusted as far as the
+  // values are actually there.
+  let mut points = Vec::with_capacity(count.min(1 << 16));
+  let mut value = [0u8; 8];
+  let mut next_coord = || -> Result<Rational> {
+    reader.read_exact(&mut value).map_err(|e| match e.kind() {
+      std::io::ErrorKind::UnexpectedEof => corrupt(&format!("fewer than the {} points in its shape", count)),
+      _ => DataError::from(e),
+    })?;
+    let f = f64::from_le_bytes(value);
+    Rational::from_f64(f).ok_or_else(|| corrupt(&format!("non-finite value {}", f)))
+  };
+  for _ in 0..count {
+    let x = next_coord()?;
+    let y = next_coord()?;
+    points.push((x, y));
+  }
+  Ok(points)
+}
+
+impl Manager {
+  /// writes the points of set `name` in the given format to the writer
+  /// `open` returns. `open` is only called once the set has been read, so
+  /// a missing or damaged set never truncates the destination.
+  pub fn export<W, F>(&self, name: &str, format: ExchangeFormat, open: F) -> Result<PointSetInfo>
+  where
+      W: Write,
+      F: FnOnce() -> Result<W>
+  {
+    let point_set = self.load(name)?;
+    format.write(&mut open()?, &point_set.points)?;
+    Ok(point_set.info)
+  }
+
+  /// creates set `name` from the points in the file at `path`, which must
+  /// all be apexes above the base.
+  pub fn import(
+      &self, name: &str, overwrite: bool, path: &Path, format: ExchangeFormat,
+  ) -> Result<PointSetInfo> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let in_file = |e| match e {
+      DataError::Corrupt(s) => DataError::Corrupt(format!("{}: {}", path.display(), s)),
+      e => e,
+    };
+    // each point with where it is in the file, for reporting bad ones.
+    let located: Vec<(String, Point)> = match format {
+      ExchangeFormat::Csv => read_csv_lines(&mut reader).map_err(in_file)?.into_iter()
+        .map(|(line, p)| (format!("line {}", line), p))
+        .collect(),
+      _ => format.read(&mut reader).map_err(in_file)?.into_iter().enumerate()
+        .map(|(i, p)| (format!("point {}", i + 1), p))
+        .collect(),
+    };
+    if let Some((at, (x, y))) = located.iter().find(|(_, (_, y))| *y <= 0) {
+      return Err(in_file(DataError::Corrupt(
+        format!("{}: ({}, {}) isn't an apex above the base", at, x, y))));
+    }
+    let points = located.into_iter().map(|(_, p)| p);
+    let file = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let origin = Origin::Imported{
+      file: file.display().to_string(),
+      format: format.name().to_string(),
+    };
+    self.save(name, overwrite, Some(origin), points)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::io::Cursor;
+  use crate::data::point_set::tests::{sample_points, test_manager};
+
+  fn round_trip(format: ExchangeFormat, points: &[Point]) -> Vec<Point> {
+    let mut buffer = Vec::new();
+    format.write(&mut buffer, points).unwrap();
+    format.read(&mut Cursor::new(buffer)).unwrap()
+  }
+
+  #[test]
+  fn test_exact_formats() {
+    // a coordinate far beyond float or 64-bit precision.
+    let huge = Rational::from((Integer::from(Integer::u_pow_u(3, 200)) + 1u32, Integer::from(1) << 400));
+    let mut points = sample_points();
+    points.push((huge.clone(), -huge));
+    assert_eq!(round_trip(ExchangeFormat::Csv, &points), points);
+    assert_eq!(round_trip(ExchangeFormat::Json, &points), points);
+
+    let csv = "x,y\n# a comment\n0.25,1/3\n\n-2,7\n";
+    assert_eq!(read_csv(&mut Cursor::new(csv)).unwrap(), vec![
+      (Rational::from((1, 4)), Rational::from((1, 3))),
+      (Rational::from(-2), Rational::from(7)),
+    ]);
+    assert!(read_csv(&mut Cursor::new("1/2,1/3,1/4\n")).is_err());
+  }
+
+  #[test]
+  fn test_npy() {
+    let points = sample_points();
+    let mut buffer = Vec::new();
+    write_npy(&mut buffer, &points).unwrap();
+    let header_len = u16::from_le_bytes([buffer[8], buffer[9]]) as usize;
+    assert_eq!((10 + header_len) % 64, 0, "npy data should be 64-byte aligned");
+    assert_eq!(buffer.len(), 10 + header_len + 16 * points.len());
+    // the sample points are dyadic, so they survive the trip through f64.
+    assert_eq!(read_npy(&mut Cursor::new(buffer)).unwrap(), points);
+
+    let third = vec![(Rational::from((1, 3)), Rational::from(0))];
+    let back = round_trip(ExchangeFormat::Npy, &third);
+    assert_eq!(back[0].0, Rational::from_f64(1.0 / 3.0).unwrap());
+
+    // a shape claiming more points than there are is corrupt, however big.
+    let mut buffer = Vec::new();
+    write_npy(&mut buffer, &points).unwrap();
+    let huge = buffer.windows(4).position(|w| w == b"(3, ").unwrap() + 1;
+    buffer.splice(huge..huge + 1, b"18446744073709551615".iter().cloned());
+    assert!(matches!(read_npy(&mut Cursor::new(buffer)), Err(DataError::Corrupt(_))));
+  }
+
+  #[test]
+  fn test_import() {
+    let manager = test_manager("exchange");
+    let apexes: Vec<Point> = vec![
+      (Rational::from((1, 2)), Rational::from((1, 4))),
+      (Rational::from((3, 8)), Rational::from((5, 16))),
+      (Rational::from(1), Rational::from(2)),
+    ];
+    manager.save("original", false, None, apexes.clone()).unwrap();
+    let path = std::env::temp_dir().join(
+      format!("billiards-test-exchange-{}.json", std::process::id()));
+    let format = ExchangeFormat::from_path(&path).unwrap();
+    manager.export("original", format, || Ok(File::create(&path)?)).unwrap();
+    let info = manager.import("copy", false, &path, format).unwrap();
+    assert_eq!(info.count, 3);
+    assert_eq!(manager.load("copy").unwrap().points, apexes);
+    match info.origin {
+      Some(Origin::Imported{format, ..}) => assert_eq!(format, "json"),
+      _ => panic!("imported sets should record their origin"),
+    }
+
+    // exporting a missing set leaves the file alone.
+    assert!(matches!(manager.export("missing", format, || Ok(File::create(&path)?)),
+      Err(DataError::NotFound(_))));
+    assert_eq!(manager.import("again", false, &path, format).unwrap().count, 3);
+
+    // points on or below the base aren't apexes.
+    fs::write(&path, "x,y\n1/2,1/3\n# comment\n1,0\n").unwrap();
+    match manager.import("flat", false, &path, ExchangeFormat::Csv) {
+      Err(DataError::Corrupt(s)) => assert!(s.contains("line 4"), "{}", s),
+      _ => panic!("points on the base should be rejected"),
+    }
+    assert!(!manager.exists("flat"));
+    fs::remove_file(&path).unwrap();
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+}
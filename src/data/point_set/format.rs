@@ -0,0 +1,398 @@
+use std::io::{BufRead, Write};
+
+use rug::{Integer, Rational};
+
+use crate::data::{DataError, Result};
+use crate::data::point_set::Point;
+
+// every points file starts with a single header line of the form
+// "<MAGIC> v<version>\n". what follows depends on the version:
+//
+//   v1  one point per line as two exact rationals ("p/q" or "p")
+//       separated by a space.
+//   v2  binary chunks of up to CHUNK_POINTS points each, ended by an empty
+//       chunk. a chunk is a varint point count and a varint kind, then
+//         kind 0 (dyadic): a varint exponent d, then for each point the
+//           zigzag varint numerators of x and y over the shared 2^d.
+//         kind 1 (general): for each point the zigzag varint numerator
+//           and varint denominator of x, then of y.
+//       varints are little-endian base 128 and unbounded, so numerators
+//       of any size fit.
+//
+// sets are written as v2. the generators only produce dyadic points, which
+// then cost a few bytes per coordinate; other points (e.g. imported ones),
+// and dyadic ones past MAX_DYADIC_EXPONENT, fall back to general chunks. v1 sets can still be read.
+pub const MAGIC: &str = "billiards-point-set";
+pub const VERSION: u32 = 2;
+pub const TEXT_VERSION: u32 = 1;
+
+const CHUNK_POINTS: usize = 4096;
+const CHUNK_DYADIC: u64 = 0;
+const CHUNK_GENERAL: u64 = 1;
+// the largest exponent a dyadic chunk may have. the exponent is read from
+// the file, and each bit of it doubles the size of the shared denominator.
+const MAX_DYADIC_EXPONENT: u64 = 64 * 1024;
+
+pub fn header() -> String {
+  format!("{} v{}", MAGIC, VERSION)
+}
+
+pub fn is_supported(version: u32) -> bool {
+  version == VERSION || version == TEXT_VERSION
+}
+
+fn write_varint<W: Write>(writer: &mut W, n: &Integer) -> Result<()> {
+  if let Some(mut n) = n.to_u64() {
+    while n >= 0x80 {
+      writer.write_all(&[(n as u8) | 0x80])?;
+      n >>= 7;
+    }
+    writer.write_all(&[n as u8])?;
+    return Ok(());
+  }
+  let mut n = n.clone();
+  while n >= 0x80 {
+    writer.write_all(&[n.to_u8_wrapping() | 0x80])?;
+    n >>= 7;
+  }
+  writer.write_all(&[n.to_u8_wrapping()])?;
+  Ok(())
+}
+
+// zigzag encoding interleaves signs so that small magnitudes stay short:
+// 0, -1, 1, -2, ... become 0, 1, 2, 3, ...
+fn write_signed_varint<W: Write>(writer: &mut W, n: &Integer) -> Result<()> {
+  let zigzag = if *n >= 0 {
+    Integer::from(n << 1)
+  } else {
+    Integer::from(n << 1).abs() - 1u32
+  };
+  write_varint(writer, &zigzag)
+}
+
+fn read_varint<R: BufRead>(reader: &mut R) -> Result<Integer> {
+  let mut small: u64 = 0;
+  let mut shift = 0;
+  let mut byte = [0u8];
+  loop {
+    reader.read_exact(&mut byte).map_err(|_| truncated())?;
+    let bits = (byte[0] & 0x7f) as u64;
+    if shift < 63 {
+      small |= bits << shift;
+    } else {
+      return read_large_varint(reader, Integer::from(small), bits, shift, byte[0]);
+    }
+    shift += 7;
+    if byte[0] & 0x80 == 0 {
+      return Ok(Integer::from(small));
+    }
+  }
+}
+
+// continues reading a varint that didn't fit in 64 bits.
+fn read_large_varint<R: BufRead>(
+    reader: &mut R, mut n: Integer, mut bits: u64, mut shift: u32, mut byte: u8,
+) -> Result<Integer> {
+  loop {
+    n += Integer::from(bits) << shift;
+    if byte & 0x80 == 0 {
+      return Ok(n);
+    }
+    shift += 7;
+    let mut next = [0u8];
+    reader.read_exact(&mut next).map_err(|_| truncated())?;
+    byte = next[0];
+    bits = (byte & 0x7f) as u64;
+  }
+}
+
+fn read_signed_varint<R: BufRead>(reader: &mut R) -> Result<Integer> {
+  let zigzag = read_varint(reader)?;
+  let odd = zigzag.is_odd();
+  let magnitude: Integer = if odd { zigzag + 1u32 } else { zigzag };
+  let n: Integer = magnitude >> 1;
+  Ok(if odd { -n } else { n })
+}
+
+fn read_u64_varint<R: BufRead>(reader: &mut R) -> Result<u64> {
+  read_varint(reader)?.to_u64()
+    .ok_or_else(|| DataError::Corrupt("chunk field out of range".to_string()))
+}
+
+fn truncated() -> DataError {
+  DataError::Corrupt("points file is truncated".to_string())
+}
+
+// the exponent d if r's denominator is 2^d.
+fn dyadic_exponent(r: &Rational) -> Option<u32> {
+  let denom = r.denom();
+  if denom.is_power_of_two() {
+    Some(denom.significant_bits() - 1)
+  } else {
+    None
+  }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, points: &[Point]) -> Result<()> {
+  write_varint(writer, &Integer::from(points.len()))?;
+  let exponent = points.iter()
+    .flat_map(|(x, y)| std::iter::once(x).chain(std::iter::once(y)))
+    .map(dyadic_exponent)
+    .try_fold(0, |max, d| d.map(|d| max.max(d)))
+    .filter(|&d| u64::from(d) <= MAX_DYADIC_EXPONENT);
+  match exponent {
+    Some(exponent) => {
+      write_varint(writer, &Integer::from(CHUNK_DYADIC))?;
+      write_varint(writer, &Integer::from(exponent))?;
+      for (x, y) in points {
+        for r in &[x, y] {
+          let scale = exponent - dyadic_exponent(r).unwrap();
+          write_signed_varint(writer, &Integer::from(r.numer() << scale))?;
+        }
+      }
+    },
+    None => {
+      write_varint(writer, &Integer::from(CHUNK_GENERAL))?;
+      for (x, y) in points {
+        for r in &[x, y] {
+          write_signed_varint(writer, r.numer())?;
+          write_varint(writer, r.denom())?;
+        }
+      }
+    },
+  }
+  Ok(())
+}
+
+/// writes the header and all points, returning the number of points
+/// written. stops at the first error from `points`.
+pub fn write_points<W, I>(writer: &mut W, points: I) -> Result<u64>
+where
+    W: Write,
+    I: IntoIterator<Item = Result<Point>>
+{
+  writeln!(writer, "{}", header())?;
+  let mut count: u64 = 0;
+  let mut chunk = Vec::with_capacity(CHUNK_POINTS);
+  for p in points {
+    chunk.push(p?);
+    count += 1;
+    if chunk.len() == CHUNK_POINTS {
+      write_chunk(writer, &chunk)?;
+      chunk.clear();
+    }
+  }
+  if !chunk.is_empty() {
+    write_chunk(writer, &chunk)?;
+  }
+  write_varint(writer, &Integer::new())?;
+  writer.flush()?;
+  Ok(count)
+}
+
+/// checks the header line and returns the format version it declares.
+pub fn read_header<R: BufRead>(reader: &mut R) -> Result<u32> {
+  let mut line = Vec::new();
+  reader.read_until(b'\n', &mut line)?;
+  let line = String::from_utf8_lossy(&line);
+  let mut words = line.trim_end().split(' ');
+  if words.next() != Some(MAGIC) {
+    return Err(DataError::Corrupt("missing point set header".to_string()));
+  }
+  let version = words.next()
+    .and_then(|v| v.strip_prefix('v'))
+    .and_then(|v| v.parse::<u32>().ok())
+    .ok_or_else(|| DataError::Corrupt(
+      format!("malformed point set header '{}'", line.trim_end())))?;
+  if !is_supported(version) {
+    return Err(DataError::Corrupt(
+      format!("unsupported point set format version {}", version)));
+  }
+  Ok(version)
+}
+
+pub fn parse_point(line: &str) -> Result<Point> {
+  let malformed = || DataError::Corrupt(format!("malformed point '{}'", line));
+  let mut coords = line.split(' ');
+  let x = coords.next().ok_or_else(malformed)?;
+  let y = coords.next().ok_or_else(malformed)?;
+  if coords.next().is_some() {
+    return Err(malformed());
+  }
+  let x = x.parse::<Rational>().map_err(|_| malformed())?;
+  let y = y.parse::<Rational>().map_err(|_| malformed())?;
+  Ok((x, y))
+}
+
+enum Chunk {
+  Dyadic(Integer),
+  General,
+}
+
+/// reads the points of a points file one at a time, in either format
+/// version, so that sets needn't fit in memory. the iterator ends after
+/// the first error.
+pub struct PointReader<R> {
+  reader: R,
+  version: u32,
+  // the current v2 chunk and how many of its points are left.
+  chunk: Chunk,
+  remaining: u64,
+  done: bool,
+}
+
+impl<R: BufRead> PointReader<R> {
+  /// reads the header and prepares to read the points after it.
+  pub fn new(mut reader: R) -> Result<PointReader<R>> {
+    let version = read_header(&mut reader)?;
+    Ok(PointReader{reader, version, chunk: Chunk::General, remaining: 0, done: false})
+  }
+
+  pub fn version(&self) -> u32 {
+    self.version
+  }
+
+  fn next_text_point(&mut self) -> Result<Option<Point>> {
+    let mut line = String::new();
+    loop {
+      line.clear();
+      if self.reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+      }
+      let line = line.trim_end_matches(['\n', '\r']);
+      if !line.is_empty() {
+        return parse_point(line).map(Some);
+      }
+    }
+  }
+
+  fn read_coordinate(&mut self) -> Result<Rational> {
+    let numer = read_signed_varint(&mut self.reader)?;
+    let denom = match &self.chunk {
+      Chunk::Dyadic(denom) => denom.clone(),
+      Chunk::General => read_varint(&mut self.reader)?,
+    };
+    if denom == 0 {
+      return Err(DataError::Corrupt("zero denominator".to_string()));
+    }
+    Ok(Rational::from((numer, denom)))
+  }
+
+  fn next_binary_point(&mut self) -> Result<Option<Point>> {
+    if self.remaining == 0 {
+      self.remaining = read_u64_varint(&mut self.reader)?;
+      if self.remaining == 0 {
+        if !self.reader.fill_buf()?.is_empty() {
+          return Err(DataError::Corrupt("unexpected data after the last point".to_string()));
+        }
+        return Ok(None);
+      }
+      self.chunk = match read_u64_varint(&mut self.reader)? {
+        CHUNK_DYADIC => {
+          let exponent = read_u64_varint(&mut self.reader)?;
+          if exponent > MAX_DYADIC_EXPONENT {
+            return Err(DataError::Corrupt(format!("dyadic exponent {} is too large", exponent)));
+          }
+          Chunk::Dyadic(Integer::from(1) << exponent as u32)
+        },
+        CHUNK_GENERAL => Chunk::General,
+        kind => return Err(DataError::Corrupt(format!("unknown chunk kind {}", kind))),
+      };
+    }
+    self.remaining -= 1;
+    let x = self.read_coordinate()?;
+    let y = self.read_coordinate()?;
+    Ok(Some((x, y)))
+  }
+}
+
+impl<R: BufRead> Iterator for PointReader<R> {
+  type Item = Result<Point>;
+
+  fn next(&mut self) -> Option<Result<Point>> {
+    if self.done {
+      return None;
+    }
+    let result = if self.version == TEXT_VERSION {
+      self.next_text_point()
+    } else {
+      self.next_binary_point()
+    };
+    match result {
+      Ok(Some(p)) => Some(Ok(p)),
+      Ok(None) => { self.done = true; None },
+      Err(e) => { self.done = true; Some(Err(e)) },
+    }
+  }
+}
+
+/// reads a complete points file, header included.
+pub fn read_points<R: BufRead>(reader: R) -> Result<Vec<Point>> {
+  PointReader::new(reader)?.collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  fn round_trip(points: &[Point]) -> (Vec<Point>, usize) {
+    let mut buffer = Vec::new();
+    write_points(&mut buffer, points.iter().cloned().map(Ok)).unwrap();
+    let size = buffer.len();
+    (read_points(Cursor::new(buffer)).unwrap(), size)
+  }
+
+  #[test]
+  fn test_varints() {
+    let values = [
+      Integer::new(), Integer::from(-1), Integer::from(63), Integer::from(-64),
+      Integer::from(u64::MAX), Integer::from(i64::MIN),
+      Integer::from(1) << 200, Integer::from(-3) << 150,
+    ];
+    let mut buffer = Vec::new();
+    for n in &values {
+      write_signed_varint(&mut buffer, n).unwrap();
+    }
+    let mut reader = Cursor::new(buffer);
+    for n in &values {
+      assert_eq!(read_signed_varint(&mut reader).unwrap(), *n);
+    }
+  }
+
+  #[test]
+  fn test_binary_format() {
+    // dyadic points with a range of denominators share one exponent.
+    let dyadic: Vec<Point> = (1..10000)
+      .map(|i| (Rational::from((i, 1 << 20)), Rational::from((-i, 1 << (i % 21)))))
+      .collect();
+    let (read, size) = round_trip(&dyadic);
+    assert_eq!(read, dyadic);
+    assert!(size < 8 * dyadic.len(), "dyadic points should take a few bytes each");
+
+    let mixed = vec![
+      (Rational::from((1, 3)), Rational::from((1, 2))),
+      (Rational::from(-7), Rational::from((Integer::from(1) << 100, 3))),
+    ];
+    assert_eq!(round_trip(&mixed).0, mixed);
+    let fine = vec![(Rational::from((1, Integer::from(1) << 100_000)), Rational::from(1))];
+    assert_eq!(round_trip(&fine).0, fine);
+    assert!(round_trip(&[]).0.is_empty());
+
+    // a huge exponent is corrupt rather than a huge denominator.
+    let mut buffer = format!("{}\n", header()).into_bytes();
+    for n in &[1, CHUNK_DYADIC, 1 << 40] {
+      write_varint(&mut buffer, &Integer::from(*n)).unwrap();
+    }
+    match read_points(Cursor::new(buffer)) {
+      Err(DataError::Corrupt(s)) => assert!(s.contains("exponent"), "{}", s),
+      _ => panic!("a huge exponent should be corrupt"),
+    }
+
+    let text = format!("{} v1\n1/2 1/4\n\n-3 5/7\n", MAGIC);
+    assert_eq!(read_points(Cursor::new(text)).unwrap(), vec![
+      (Rational::from((1, 2)), Rational::from((1, 4))),
+      (Rational::from(-3), Rational::from((5, 7))),
+    ]);
+  }
+}
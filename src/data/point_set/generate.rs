@@ -0,0 +1,535 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rug::{Integer, Rational};
+use rug::integer::Order;
+use serde::{Deserialize, Serialize};
+
+use crate::data::point_set::Point;
+use crate::data::point_set::region::Region;
+
+// every generator produces apexes of obtuse triangles, i.e. points strictly
+// inside the upper half of the disc centered at (1/2, 0) with radius 1/2,
+// and every point lies on the grid with spacing 2^-grid_density so that its
+// coordinates are exact dyadic rationals. generators can further be limited
+// to a `Region`, in which case candidates are drawn from the part of the
+// half-disc's bounding box that the region's box covers, and those outside
+// the region are rejected.
+
+// the most consecutive rejected candidates a generator tolerates before
+// giving up, short of its count. this only happens when the region fills
+// little of its bounding box, or holds (almost) no grid points.
+const MAX_CONSECUTIVE_MISSES: u32 = 1 << 20;
+
+// stratified cells are numbered by u64 and tested for meeting the
+// half-disc in i128, which this density keeps within range.
+const MAX_CELL_DENSITY: u32 = 60;
+
+// how many times stratified sampling refines its cells when too few of them
+// turn out to hold points of the region, before giving up short of its
+// count. each refinement quadruples the cells.
+const MAX_REFINEMENTS: u32 = 3;
+
+/// a point generator together with all of its parameters, as recorded in
+/// the metadata of the sets it creates.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "generator", rename_all = "snake_case")]
+pub enum Generator {
+  // independent uniform samples from the grid.
+  Random { grid_density: u32, count: u32 },
+  // the two-dimensional Sobol sequence, skipping its initial point (0, 0).
+  Sobol { grid_density: u32, count: u32 },
+  // the Halton sequence in bases 2 and 3, rounded down onto the grid.
+  Halton { grid_density: u32, count: u32 },
+  // one uniform sample in each of `count` randomly chosen square cells of
+  // side 2^-cell_density that meet the half-disc.
+  Stratified { grid_density: u32, count: u32 },
+  // every grid point in the half-disc.
+  Lattice { grid_density: u32 },
+}
+
+pub const GENERATOR_NAMES: &[&str] = &["random", "sobol", "halton", "stratified", "lattice"];
+
+// lattice enumeration yields roughly (pi/8) * 4^grid_density points, so it
+// is refused past the density where that passes 10^8: about 2.6 * 10^7
+// points at 13, and 1.05 * 10^8 at 14.
+pub const MAX_LATTICE_DENSITY: u32 = 13;
+
+/// the generator used by the randomized point generators. sets record the
+/// seed it was started from, so with the same crate version (and thus the
+/// same rand release) a set can be regenerated exactly.
+pub type SeededRng = StdRng;
+
+impl Generator {
+  /// builds the named generator for points in `region`. `count` is
+  /// required by every generator except "lattice", which doesn't accept
+  /// one.
+  pub fn new(
+      name: &str, grid_density: u32, count: Option<u32>, region: &Region,
+  ) -> Result<Generator, String> {
+    let count = || count.ok_or_else(|| format!("the {} generator needs a point count", name));
+    let generator = match name {
+      "random" => Generator::Random{grid_density, count: count()?},
+      "sobol" => Generator::Sobol{grid_density, count: count()?},
+      "halton" => Generator::Halton{grid_density, count: count()?},
+      "stratified" => Generator::Stratified{grid_density, count: count()?},
+      "lattice" => {
+        if count().is_ok() {
+          return Err("the lattice generator enumerates every grid point \
+            and doesn't take a count".to_string());
+        }
+        if grid_density > MAX_LATTICE_DENSITY {
+          return Err(format!(
+            "lattice enumeration is limited to grid density {} or less",
+            MAX_LATTICE_DENSITY));
+        }
+        Generator::Lattice{grid_density}
+      },
+      _ => return Err(format!("unknown generator '{}'", name)),
+    };
+    if grid_density < 2 {
+      return Err("grid density must be at least 2".to_string());
+    }
+    if let Generator::Stratified{grid_density, count} = generator {
+      if stratified_cell_density(count, region, grid_density).is_none() {
+        return Err(format!(
+          "can't divide the region into {} strata at grid density {}", count, grid_density));
+      }
+    }
+    Ok(generator)
+  }
+
+  pub fn grid_density(&self) -> u32 {
+    match *self {
+      Generator::Random{grid_density, ..} |
+      Generator::Sobol{grid_density, ..} |
+      Generator::Halton{grid_density, ..} |
+      Generator::Stratified{grid_density, ..} |
+      Generator::Lattice{grid_density} => grid_density,
+    }
+  }
+
+  /// whether the generator's output depends on its seed.
+  pub fn is_randomized(&self) -> bool {
+    match self {
+      Generator::Random{..} | Generator::Stratified{..} => true,
+      Generator::Sobol{..} | Generator::Halton{..} | Generator::Lattice{..} => false,
+    }
+  }
+
+  /// the generated points that lie in `region`. generators with a count
+  /// produce fewer points only if they give up, when the region holds too
+  /// few grid points or fills too little of its bounding box. randomized generators give the same points whenever
+  /// they're given the same seed; the others ignore it.
+  pub fn points(&self, region: &Region, seed: u64) -> Box<dyn Iterator<Item = Point>> {
+    let region = region.clone();
+    let rng = SeededRng::seed_from_u64(seed);
+    match *self {
+      Generator::Random{grid_density, count} =>
+        Box::new(random_in_region(grid_density, count, region, rng)),
+      Generator::Sobol{grid_density, count} =>
+        Box::new(sobol(grid_density, count, region)),
+      Generator::Halton{grid_density, count} =>
+        Box::new(halton(grid_density, count, region)),
+      Generator::Stratified{grid_density, count} =>
+        Box::new(stratified(grid_density, count, region, rng)),
+      Generator::Lattice{grid_density} =>
+        Box::new(lattice(grid_density).filter(move |p| region.contains(p))),
+    }
+  }
+}
+
+/// a uniformly random integer in [0, 2^bits).
+pub fn random_bits<R: Rng>(rng: &mut R, bits: u32) -> Integer {
+  let words: Vec<u32> = (0..bits.div_ceil(32)).map(|_| rng.gen()).collect();
+  Integer::from_digits(&words, Order::Lsf).keep_bits(bits)
+}
+
+// a uniformly random integer in [0, n), for n > 0. when n is a power of two
+// this is `random_bits` exactly.
+fn random_below<R: Rng>(rng: &mut R, n: &Integer) -> Integer {
+  let bits = Integer::from(n - 1u32).significant_bits();
+  loop {
+    let r = random_bits(rng, bits);
+    if r < *n {
+      return r;
+    }
+  }
+}
+
+// the grid points that may lie in both the half-disc and a region, as
+// [x, x + width) x [y, y + height) in units of 2^-density. the same
+// ranges, read as cells [a, a+1] x [b, b+1], are the cells meeting the box.
+struct GridBox {
+  x: Integer,
+  width: Integer,
+  y: Integer,
+  height: Integer,
+}
+
+// the multiples k of 2^-density in [0, limit) whose cells [k, k+1] meet
+// [lo, hi], as the first and the count.
+fn grid_span(lo: &Rational, hi: &Rational, density: u32, limit: Integer) -> (Integer, Integer) {
+  let floor = |r: &Rational| Rational::from(r << density).floor().into_numer_denom().0;
+  let start = floor(lo).max(Integer::new());
+  let end = (floor(hi) + 1u32).min(limit);
+  let count = Integer::from(&end - &start).max(Integer::new());
+  (start, count)
+}
+
+// the box of the half-disc, narrowed to `region`'s bounding box, or `None`
+// if that's empty.
+fn grid_box(region: &Region, density: u32) -> Option<GridBox> {
+  let columns = Integer::from(1) << density;
+  // the half-disc lies below y = 1/2.
+  let rows = Integer::from(1) << (density - 1);
+  let (x, width, y, height) = match region.bounding_box() {
+    None => (Integer::new(), columns, Integer::new(), rows),
+    Some((min, max)) => {
+      let (x, width) = grid_span(&min.0, &max.0, density, columns);
+      let (y, height) = grid_span(&min.1, &max.1, density, rows);
+      (x, width, y, height)
+    },
+  };
+  if width == 0 || height == 0 {
+    return None;
+  }
+  Some(GridBox{x, width, y, height})
+}
+
+/// whether the apex (x / 2^d, y / 2^d) lies strictly inside the upper half
+/// of the disc centered at (1/2, 0) with radius 1/2, i.e. whether it gives
+/// an obtuse triangle over the unit base.
+pub fn grid_point_is_obtuse(x: &Integer, y: &Integer, grid_density: u32) -> bool {
+  if *y <= 0 {
+    return false;
+  }
+  let n = Integer::from(1) << grid_density;
+  let dx = Integer::from(x * 2) - &n;
+  let dy = Integer::from(y * 2);
+  Integer::from(dx.square_ref()) + dy.square() < n.square()
+}
+
+pub fn grid_point(x: Integer, y: Integer, grid_density: u32) -> Point {
+  let denom = Integer::from(1) << grid_density;
+  (Rational::from((x, denom.clone())), Rational::from((y, denom)))
+}
+
+// the first `count` candidates that are obtuse apexes inside `region`.
+fn accepted_points<I>(
+    grid_density: u32, count: u32, region: Region, candidates: I,
+) -> impl Iterator<Item = Point>
+where
+    I: Iterator<Item = (Integer, Integer)>
+{
+  candidates
+    .scan(0, move |misses, (x, y)| {
+      if grid_point_is_obtuse(&x, &y, grid_density) {
+        let p = grid_point(x, y, grid_density);
+        if region.contains(&p) {
+          *misses = 0;
+          return Some(Some(p));
+        }
+      }
+      *misses += 1;
+      if *misses > MAX_CONSECUTIVE_MISSES { None } else { Some(None) }
+    })
+    .flatten()
+    .take(count as usize)
+}
+
+/// `count` random apexes of obtuse triangles, chosen uniformly from the
+/// points of the grid with spacing 2^-grid_density.
+pub fn random_from_grid(grid_density: u32, count: u32) -> impl Iterator<Item = Point> {
+  random_in_region(grid_density, count, Region::default(), rand::thread_rng())
+}
+
+/// `count` apexes chosen uniformly from the grid points of the half-disc
+/// that lie in `region`.
+pub fn random_in_region<R: Rng>(
+    grid_density: u32, count: u32, region: Region, mut rng: R,
+) -> impl Iterator<Item = Point> {
+  let bounds = grid_box(&region, grid_density);
+  let candidates = std::iter::from_fn(move || {
+    let b = bounds.as_ref()?;
+    let x = random_below(&mut rng, &b.width) + &b.x;
+    let y = random_below(&mut rng, &b.height) + &b.y;
+    Some((x, y))
+  });
+  accepted_points(grid_density, count, region, candidates)
+}
+
+// floor(u / 2^64 * width), the binary fraction u / 2^64 in [0, 1) scaled
+// to [0, width).
+fn fraction_to_grid(u: u64, width: &Integer) -> Integer {
+  (Integer::from(u) * width) >> 64
+}
+
+// direction numbers for the second Sobol coordinate, from the primitive
+// polynomial x + 1: m_1 = 1, m_k = 2 m_{k-1} xor m_{k-1}.
+fn sobol_directions() -> ([u64; 64], [u64; 64]) {
+  let mut first = [0u64; 64];
+  let mut second = [0u64; 64];
+  let mut m: u64 = 1;
+  for k in 0..64 {
+    first[k] = 1 << (63 - k);
+    second[k] = m << (63 - k);
+    m ^= m << 1;
+  }
+  (first, second)
+}
+
+/// the first `count` points of the 2d Sobol sequence (in gray code order)
+/// that fall inside the half-disc and `region`. the sequence is scaled to
+/// the part of the half-disc's bounding box that may meet `region`.
+pub fn sobol(grid_density: u32, count: u32, region: Region) -> impl Iterator<Item = Point> {
+  let (first, second) = sobol_directions();
+  let mut state = (0u64, 0u64);
+  let bounds = grid_box(&region, grid_density);
+  let candidates = (1u64..).map_while(move |i| {
+    let b = bounds.as_ref()?;
+    let bit = i.trailing_zeros() as usize;
+    state = (state.0 ^ first[bit], state.1 ^ second[bit]);
+    Some((fraction_to_grid(state.0, &b.width) + &b.x,
+      fraction_to_grid(state.1, &b.height) + &b.y))
+  });
+  accepted_points(grid_density, count, region, candidates)
+}
+
+// floor(width * phi_b(i)), where phi_b is the radical inverse in base b.
+fn radical_inverse_to_grid(mut i: u64, base: u64, width: &Integer) -> Integer {
+  let mut numer = Integer::new();
+  let mut denom = Integer::from(1);
+  while i > 0 {
+    numer = numer * base + i % base;
+    denom *= base;
+    i /= base;
+  }
+  numer * width / denom
+}
+
+/// the first `count` points of the Halton sequence in bases (2, 3) that fall
+/// inside the half-disc and `region`, rounded down onto the grid and scaled
+/// as for `sobol`.
+pub fn halton(grid_density: u32, count: u32, region: Region) -> impl Iterator<Item = Point> {
+  let bounds = grid_box(&region, grid_density);
+  let candidates = (1u64..).map_while(move |i| {
+    let b = bounds.as_ref()?;
+    Some((radical_inverse_to_grid(i, 2, &b.width) + &b.x,
+      radical_inverse_to_grid(i, 3, &b.height) + &b.y))
+  });
+  accepted_points(grid_density, count, region, candidates)
+}
+
+// whether the closed cell [a, a+1] x [b, b+1], in units of 2^-cell_density,
+// meets the open half-disc.
+fn cell_meets_half_disc(a: u64, b: u64, cell_density: u32) -> bool {
+  let center = 1i128 << (cell_density - 1);
+  let (a, b) = (a as i128, b as i128);
+  let nearest_x = center.max(a).min(a + 1);
+  let dx = nearest_x - center;
+  dx * dx + b * b < center * center
+}
+
+// the cells of side 2^-cell_density that meet the half-disc and may meet
+// `region`, as (column, row) pairs. only the cells meeting the region's
+// bounding box are considered, so a small region needn't visit them all.
+fn stratified_cells(cell_density: u32, region: &Region) -> impl Iterator<Item = (u64, u64)> + '_ {
+  let columns = 1u64 << cell_density;
+  let corner = move |a: u64, b: u64| (
+    Rational::from((a, columns)), Rational::from((b, columns)));
+  let (a_range, b_range) = match grid_box(region, cell_density) {
+    Some(b) => {
+      let range = |start: Integer, count: Integer| {
+        let start = start.to_u64().unwrap();
+        start..start + count.to_u64().unwrap()
+      };
+      (range(b.x, b.width), range(b.y, b.height))
+    },
+    None => (0..0, 0..0),
+  };
+  a_range
+    .flat_map(move |a| b_range.clone().map(move |b| (a, b)))
+    .filter(move |&(a, b)| cell_meets_half_disc(a, b, cell_density))
+    .filter(move |&(a, b)| region.is_everything() ||
+      region.may_meet_box(&corner(a, b), &corner(a + 1, b + 1)))
+}
+
+// the coarsest cell size with at least `count` cells meeting the half-disc
+// (and possibly `region`), or `None` if no cell size up to `max_density`
+// (or MAX_CELL_DENSITY) has that many.
+fn stratified_cell_density(count: u32, region: &Region, max_density: u32) -> Option<u32> {
+  (1..=max_density.min(MAX_CELL_DENSITY))
+    .find(|&d| stratified_cells(d, region).take(count as usize).count() as u64 >= count as u64)
+}
+
+// one point from each cell, in random cell order. cells that only graze the
+// half-disc or the region are abandoned after a few misses.
+fn stratified_at<R: Rng>(
+    grid_density: u32, cell_density: u32, region: &Region, rng: &mut R,
+) -> Vec<Point> {
+  const ATTEMPTS_PER_CELL: usize = 64;
+  let mut cells: Vec<(u64, u64)> = stratified_cells(cell_density, region).collect();
+  cells.shuffle(rng);
+  let jitter_bits = grid_density - cell_density;
+  cells.into_iter()
+    .filter_map(|(a, b)| {
+      let base_x = Integer::from(a) << jitter_bits;
+      let base_y = Integer::from(b) << jitter_bits;
+      (0..ATTEMPTS_PER_CELL).find_map(|_| {
+        let x = random_bits(rng, jitter_bits) + &base_x;
+        let y = random_bits(rng, jitter_bits) + &base_y;
+        if !grid_point_is_obtuse(&x, &y, grid_density) {
+          return None;
+        }
+        Some(grid_point(x, y, grid_density)).filter(|p| region.contains(p))
+      })
+    })
+    .collect()
+}
+
+/// jittered stratified sampling: the half-disc's bounding box is divided
+/// into the coarsest square cells that give at least `count` cells meeting
+/// the half-disc, and one uniform grid point inside the half-disc is drawn
+/// from each of `count` of those cells. when cells outside `region` leave
+/// too few points, the cells are refined up to MAX_REFINEMENTS times, and
+/// if there still aren't enough it gives up with the points it has.
+pub fn stratified<R: Rng>(
+    grid_density: u32, count: u32, region: Region, mut rng: R,
+) -> impl Iterator<Item = Point> {
+  let mut points = Vec::new();
+  if let Some(start) = stratified_cell_density(count, &region, grid_density) {
+    let finest = grid_density.min(MAX_CELL_DENSITY).min(start + MAX_REFINEMENTS);
+    for cell_density in start..=finest {
+      points = stratified_at(grid_density, cell_density, &region, &mut rng);
+      if points.len() >= count as usize {
+        break;
+      }
+    }
+  }
+  points.into_iter().take(count as usize)
+}
+
+/// every point of the grid strictly inside the half-disc, by column.
+pub fn lattice(grid_density: u32) -> impl Iterator<Item = Point> {
+  let n = 1u64 << grid_density;
+  (1..n).flat_map(move |x| {
+    // (2x - n)^2 + (2y)^2 < n^2, so y^2 <= floor((n^2 - (2x - n)^2 - 1) / 4).
+    let n = Integer::from(n);
+    let dx = Integer::from(x * 2) - &n;
+    let bound: Integer = (n.square() - dx.square() - 1u32) / 4u32;
+    let max_y = bound.sqrt().to_u64().unwrap();
+    (1..=max_y).map(move |y| grid_point(Integer::from(x), Integer::from(y), grid_density))
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_valid(points: &[Point], grid_density: u32) {
+    let half = Rational::from((1, 2));
+    let quarter = Rational::from((1, 4));
+    let denom = Integer::from(1) << grid_density;
+    for (x, y) in points {
+      assert!(*y > 0, "apex should be above the base");
+      assert!(denom.is_divisible(x.denom()) && denom.is_divisible(y.denom()),
+        "apex should lie on the grid");
+      let dx = Rational::from(x - &half);
+      let norm = dx.square() + Rational::from(y.square_ref());
+      assert!(norm < quarter, "apex should be inside the half-disc");
+    }
+  }
+
+  #[test]
+  fn test_generators() {
+    for name in GENERATOR_NAMES {
+      let count = if *name == "lattice" { None } else { Some(200) };
+      let generator = Generator::new(name, 10, count, &Region::default()).unwrap();
+      let points: Vec<Point> = generator.points(&Region::default(), 1).collect();
+      if let Some(count) = count {
+        assert_eq!(points.len(), count as usize, "{} should give exactly {} points", name, count);
+      }
+      assert_valid(&points, 10);
+    }
+  }
+
+  #[test]
+  fn test_region() {
+    let region: Region = "angle a0 between 100 110 and disc 0.5,0.2 0.2".parse().unwrap();
+    for name in GENERATOR_NAMES {
+      let count = if *name == "lattice" { None } else { Some(50) };
+      let generator = Generator::new(name, 8, count, &region).unwrap();
+      let points: Vec<Point> = generator.points(&region, 1).collect();
+      if let Some(count) = count {
+        assert_eq!(points.len(), count as usize, "{} should fill its count in the region", name);
+      }
+      assert!(!points.is_empty());
+      assert_valid(&points, 8);
+      assert!(points.iter().all(|p| region.contains(p)), "{} should stay in the region", name);
+    }
+
+    // a small region is searched within its bounding box, however fine
+    // the grid, rather than across the whole half-disc.
+    let small: Region = "disc 0.5,0.3 0.001".parse().unwrap();
+    for name in &["random", "sobol", "halton", "stratified"] {
+      let generator = Generator::new(name, 64, Some(200), &small).unwrap();
+      let points: Vec<Point> = generator.points(&small, 1).collect();
+      assert_eq!(points.len(), 200, "{} should fill its count in a small region", name);
+      assert!(points.iter().all(|p| small.contains(p)));
+    }
+    let tiny: Region = "disc 0.5,0.3 0.0001".parse().unwrap();
+    assert!(Generator::new("stratified", 12, Some(100), &tiny).is_err());
+
+    // a region without any grid points ends generation early.
+    let empty: Region = "disc 513/1024,257/1024 1/4096".parse().unwrap();
+    assert_eq!(Generator::new("sobol", 8, Some(5), &empty).unwrap().points(&empty, 1).count(), 0);
+  }
+
+  #[test]
+  fn test_lattice() {
+    // brute force count of the grid points strictly inside the half-disc.
+    let density = 5;
+    let n = 1i64 << density;
+    let expected = (0..n).flat_map(|x| (1..n).map(move |y| (x, y)))
+      .filter(|(x, y)| (2 * x - n).pow(2) + (2 * y).pow(2) < n * n)
+      .count();
+    assert_eq!(lattice(density).count(), expected);
+  }
+
+  #[test]
+  fn test_sobol() {
+    // the first points of the 2d Sobol sequence are (1/2, 1/2), (3/4, 1/4),
+    // (1/4, 3/4), with the second coordinate halved to fit the half-disc.
+    let points: Vec<Point> = sobol(4, 2, Region::default()).collect();
+    assert_eq!(points[0], (Rational::from((1, 2)), Rational::from((1, 4))));
+    assert_eq!(points[1], (Rational::from((3, 4)), Rational::from((1, 8))));
+  }
+
+  #[test]
+  fn test_seed() {
+    for name in &["random", "stratified"] {
+      let generator = Generator::new(name, 16, Some(20), &Region::default()).unwrap();
+      let region = Region::default();
+      let first: Vec<Point> = generator.points(&region, 7).collect();
+      let second: Vec<Point> = generator.points(&region, 7).collect();
+      let other: Vec<Point> = generator.points(&region, 8).collect();
+      assert_eq!(first, second, "{} should repeat itself given the same seed", name);
+      assert_ne!(first, other, "{} should depend on its seed", name);
+    }
+    let sobol = Generator::new("sobol", 16, Some(20), &Region::default()).unwrap();
+    assert!(!sobol.is_randomized());
+    assert!(sobol.points(&Region::default(), 7).eq(sobol.points(&Region::default(), 8)));
+  }
+
+  #[test]
+  fn test_generator_metadata() {
+    let generator = Generator::new("sobol", 20, Some(5), &Region::default()).unwrap();
+    let json = serde_json::to_string(&generator).unwrap();
+    assert_eq!(json, r#"{"generator":"sobol","grid_density":20,"count":5}"#);
+    assert_eq!(serde_json::from_str::<Generator>(&json).unwrap(), generator);
+    assert!(Generator::new("lattice", 20, None, &Region::default()).is_err());
+    assert!(Generator::new("lattice", 8, Some(5), &Region::default()).is_err());
+    assert!(Generator::new("stratified", 3, Some(1000), &Region::default()).is_err());
+  }
+}
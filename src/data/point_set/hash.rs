@@ -0,0 +1,58 @@
+use sha2::{Digest, Sha256};
+
+use crate::data::point_set::Point;
+
+// a set's content hash is the sha-256 of its points written one per line
+// as "x y\n", with each coordinate in lowest terms as "p/q" or "p". it
+// depends only on the points and their order, not on the format version
+// the set is stored in, so identical sets always hash alike.
+
+const PREFIX: &str = "sha256:";
+
+pub struct ContentHasher {
+  hasher: Sha256,
+}
+
+impl ContentHasher {
+  pub fn new() -> ContentHasher {
+    ContentHasher{hasher: Sha256::new()}
+  }
+
+  pub fn update(&mut self, p: &Point) {
+    self.hasher.update(format!("{} {}\n", p.0, p.1).as_bytes());
+  }
+
+  /// the hash as stored in a set's info, e.g. "sha256:e3b0c442...".
+  pub fn finish(self) -> String {
+    let digest = self.hasher.finalize();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", PREFIX, hex)
+  }
+}
+
+pub fn content_hash<'a, I: IntoIterator<Item = &'a Point>>(points: I) -> String {
+  let mut hasher = ContentHasher::new();
+  for p in points {
+    hasher.update(p);
+  }
+  hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+
+  #[test]
+  fn test_content_hash() {
+    // the sha-256 of the empty string.
+    assert_eq!(content_hash(&[]),
+      "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    let a = (Rational::from((1, 2)), Rational::from((1, 4)));
+    let b = (Rational::from((2, 4)), Rational::from((3, 12)));
+    let c = (Rational::from((1, 4)), Rational::from((1, 2)));
+    assert_eq!(content_hash(std::slice::from_ref(&a)), content_hash(&[b]),
+      "equal rationals should hash alike");
+    assert_ne!(content_hash(&[a.clone(), c.clone()]), content_hash(&[c, a]));
+  }
+}
@@ -0,0 +1,341 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::BufReader;
+
+use rug::Rational;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{write_atomically, DataError, Result};
+use crate::data::point_set::{format, hash, Manager, Point, Region};
+
+// a set's spatial index is a k-d tree over its points, stored next to the
+// set as two files:
+//
+//   <root>/<name>/index         the points in tree order, as a points file
+//   <root>/<name>/index.json    an `IndexInfo` naming the hash of the
+//                               points the tree was built from
+//
+// the tree is implicit in the order of the points: the point in the middle
+// of any range splits that range on x at even depths and on y at odd ones,
+// with every point before it no greater and every point after it no less
+// on that axis. an index whose hash doesn't match its set's is rebuilt.
+
+const INDEX_FILE: &str = "index";
+const INDEX_INFO_FILE: &str = "index.json";
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct IndexInfo {
+  version: u32,
+  source_hash: String,
+}
+
+/// a k-d tree over exact points. all queries compare coordinates exactly,
+/// and boundaries are inclusive.
+pub struct KdTree {
+  points: Vec<Point>,
+}
+
+fn coord(p: &Point, axis: usize) -> &Rational {
+  if axis == 0 { &p.0 } else { &p.1 }
+}
+
+fn squared_distance(a: &Point, b: &Point) -> Rational {
+  let dx = Rational::from(&a.0 - &b.0);
+  let dy = Rational::from(&a.1 - &b.1);
+  dx.square() + dy.square()
+}
+
+// orders candidates for the k nearest points by distance, so that the
+// farthest one sits on top of the heap.
+struct Candidate<'a> {
+  distance: Rational,
+  point: &'a Point,
+}
+
+impl PartialEq for Candidate<'_> {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Candidate<'_> {}
+
+impl PartialOrd for Candidate<'_> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Candidate<'_> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.distance.cmp(&other.distance).then_with(|| self.point.cmp(other.point))
+  }
+}
+
+fn build(points: &mut [Point], depth: usize) {
+  if points.len() <= 1 {
+    return;
+  }
+  let axis = depth % 2;
+  let mid = points.len() / 2;
+  points.select_nth_unstable_by(mid, |a, b| coord(a, axis).cmp(coord(b, axis)));
+  let (left, rest) = points.split_at_mut(mid);
+  build(left, depth + 1);
+  build(&mut rest[1..], depth + 1);
+}
+
+impl KdTree {
+  pub fn new(mut points: Vec<Point>) -> KdTree {
+    build(&mut points, 0);
+    KdTree{points}
+  }
+
+  pub fn len(&self) -> usize {
+    self.points.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.points.is_empty()
+  }
+
+  /// every point in the closed box from `min` to `max`.
+  pub fn in_box(&self, min: &Point, max: &Point) -> Vec<&Point> {
+    let mut result = Vec::new();
+    self.visit_box(&self.points, 0, min, max, &mut result);
+    result
+  }
+
+  fn visit_box<'a>(
+      &self, points: &'a [Point], depth: usize, min: &Point, max: &Point,
+      result: &mut Vec<&'a Point>,
+  ) {
+    if points.is_empty() {
+      return;
+    }
+    let axis = depth % 2;
+    let mid = points.len() / 2;
+    let p = &points[mid];
+    if p.0 >= min.0 && p.0 <= max.0 && p.1 >= min.1 && p.1 <= max.1 {
+      result.push(p);
+    }
+    if coord(min, axis) <= coord(p, axis) {
+      self.visit_box(&points[..mid], depth + 1, min, max, result);
+    }
+    if coord(p, axis) <= coord(max, axis) {
+      self.visit_box(&points[mid + 1..], depth + 1, min, max, result);
+    }
+  }
+
+  /// every point within distance `radius` of `center`.
+  pub fn within(&self, center: &Point, radius: &Rational) -> Vec<&Point> {
+    let min = (Rational::from(&center.0 - radius), Rational::from(&center.1 - radius));
+    let max = (Rational::from(&center.0 + radius), Rational::from(&center.1 + radius));
+    let squared_radius = Rational::from(radius.square_ref());
+    self.in_box(&min, &max).into_iter()
+      .filter(|p| squared_distance(p, center) <= squared_radius)
+      .collect()
+  }
+
+  /// the `count` points nearest to `target`, nearest first, with their
+  /// squared distances. ties are broken by comparing the points.
+  pub fn nearest(&self, target: &Point, count: usize) -> Vec<(&Point, Rational)> {
+    let mut heap = BinaryHeap::with_capacity(count + 1);
+    if count > 0 {
+      self.visit_nearest(&self.points, 0, target, count, &mut heap);
+    }
+    heap.into_sorted_vec().into_iter()
+      .map(|c| (c.point, c.distance))
+      .collect()
+  }
+
+  fn visit_nearest<'a>(
+      &self, points: &'a [Point], depth: usize, target: &Point, count: usize,
+      heap: &mut BinaryHeap<Candidate<'a>>,
+  ) {
+    if points.is_empty() {
+      return;
+    }
+    let axis = depth % 2;
+    let mid = points.len() / 2;
+    let p = &points[mid];
+    heap.push(Candidate{distance: squared_distance(p, target), point: p});
+    if heap.len() > count {
+      heap.pop();
+    }
+    let (near, far) = if coord(target, axis) <= coord(p, axis) {
+      (&points[..mid], &points[mid + 1..])
+    } else {
+      (&points[mid + 1..], &points[..mid])
+    };
+    self.visit_nearest(near, depth + 1, target, count, heap);
+    // the far side can only hold closer points if the splitting line is
+    // closer than the current worst candidate.
+    let gap = Rational::from(coord(target, axis) - coord(p, axis)).square();
+    if heap.len() < count || gap <= heap.peek().unwrap().distance {
+      self.visit_nearest(far, depth + 1, target, count, heap);
+    }
+  }
+
+  /// every point in `region`, skipping subtrees whose bounding boxes the
+  /// region can't meet.
+  pub fn in_region(&self, region: &Region) -> Vec<&Point> {
+    let mut result = Vec::new();
+    if self.points.is_empty() {
+      return result;
+    }
+    let min_of = |axis| self.points.iter().map(|p| coord(p, axis)).min().unwrap().clone();
+    let max_of = |axis| self.points.iter().map(|p| coord(p, axis)).max().unwrap().clone();
+    let min = (min_of(0), min_of(1));
+    let max = (max_of(0), max_of(1));
+    self.visit_region(&self.points, 0, region, min, max, &mut result);
+    result
+  }
+
+  fn visit_region<'a>(
+      &self, points: &'a [Point], depth: usize, region: &Region, min: Point, max: Point,
+      result: &mut Vec<&'a Point>,
+  ) {
+    if points.is_empty() || !region.may_meet_box(&min, &max) {
+      return;
+    }
+    let axis = depth % 2;
+    let mid = points.len() / 2;
+    let p = &points[mid];
+    if region.contains(p) {
+      result.push(p);
+    }
+    let mut left_max = max.clone();
+    let mut right_min = min.clone();
+    if axis == 0 {
+      left_max.0 = p.0.clone();
+      right_min.0 = p.0.clone();
+    } else {
+      left_max.1 = p.1.clone();
+      right_min.1 = p.1.clone();
+    }
+    self.visit_region(&points[..mid], depth + 1, region, min, left_max, result);
+    self.visit_region(&points[mid + 1..], depth + 1, region, right_min, max, result);
+  }
+}
+
+impl Manager {
+  /// the spatial index of set `name`, loaded from disk if it's up to date
+  /// and otherwise built and saved.
+  pub fn index(&self, name: &str) -> Result<KdTree> {
+    let info = self.info(name)?;
+    let path = self.set_path(name);
+    let index_info: Option<IndexInfo> = File::open(path.join(INDEX_INFO_FILE)).ok()
+      .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok());
+    let fresh = match (&index_info, &info.content_hash) {
+      (Some(index_info), Some(hash)) =>
+        index_info.version == INDEX_VERSION && index_info.source_hash == *hash,
+      _ => false,
+    };
+    if fresh {
+      // an unreadable index is rebuilt like a stale one.
+      let points = File::open(path.join(INDEX_FILE)).map_err(DataError::from)
+        .and_then(|f| format::read_points(BufReader::new(f)));
+      if let Ok(points) = points {
+        if points.len() as u64 == info.count {
+          return Ok(KdTree{points});
+        }
+      }
+    }
+    self.build_index(name)
+  }
+
+  /// builds the spatial index of set `name` and saves it alongside the set.
+  pub fn build_index(&self, name: &str) -> Result<KdTree> {
+    let point_set = self.load(name)?;
+    let source_hash = point_set.info.content_hash.clone()
+      .unwrap_or_else(|| hash::content_hash(&point_set.points));
+    let tree = KdTree::new(point_set.points);
+
+    let path = self.set_path(name);
+    write_atomically(&path.join(INDEX_FILE), |writer| {
+      format::write_points(writer, tree.points.iter().cloned().map(Ok))?;
+      Ok(())
+    })?;
+    let index_info = IndexInfo{version: INDEX_VERSION, source_hash};
+    write_atomically(&path.join(INDEX_INFO_FILE), |writer| {
+      Ok(serde_json::to_writer_pretty(writer, &index_info)?)
+    })?;
+    Ok(tree)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use rand::SeedableRng;
+  use crate::data::point_set::{content_hash, SeededRng};
+  use crate::data::point_set::generate::random_in_region;
+  use crate::data::point_set::tests::test_manager;
+
+  // seeded, so that a failure can be reproduced.
+  fn random_points(grid_density: u32, count: u32, seed: u64) -> Vec<Point> {
+    random_in_region(grid_density, count, Region::default(), SeededRng::seed_from_u64(seed))
+      .collect()
+  }
+
+  fn sorted(mut points: Vec<&Point>) -> Vec<&Point> {
+    points.sort();
+    points
+  }
+
+  #[test]
+  fn test_queries() {
+    // a coarse grid makes ties on both axes likely.
+    let points: Vec<Point> = random_points(5, 300, 1);
+    let tree = KdTree::new(points.clone());
+    let q = |x: i32, y: i32| (Rational::from((x, 32)), Rational::from((y, 32)));
+
+    let (min, max) = (q(8, 2), q(20, 9));
+    let expected: Vec<&Point> = points.iter()
+      .filter(|p| p.0 >= min.0 && p.0 <= max.0 && p.1 >= min.1 && p.1 <= max.1)
+      .collect();
+    assert_eq!(sorted(tree.in_box(&min, &max)), sorted(expected));
+
+    let center = q(16, 4);
+    let radius = Rational::from((5, 32));
+    let expected: Vec<&Point> = points.iter()
+      .filter(|p| squared_distance(p, &center) <= Rational::from(radius.square_ref()))
+      .collect();
+    assert_eq!(sorted(tree.within(&center, &radius)), sorted(expected));
+
+    let mut by_distance: Vec<(Rational, &Point)> = points.iter()
+      .map(|p| (squared_distance(p, &center), p))
+      .collect();
+    by_distance.sort();
+    let nearest = tree.nearest(&center, 10);
+    let expected: Vec<&Point> = by_distance.iter().take(10).map(|(_, p)| *p).collect();
+    assert_eq!(nearest.iter().map(|(p, _)| *p).collect::<Vec<_>>(), expected);
+
+    let region: Region = "annulus 1/2,0 1/4 5/16".parse().unwrap();
+    let expected: Vec<&Point> = points.iter().filter(|p| region.contains(p)).collect();
+    assert_eq!(sorted(tree.in_region(&region)), sorted(expected));
+  }
+
+  #[test]
+  fn test_persisted_index() {
+    let manager = test_manager("index");
+    let points: Vec<Point> = random_points(12, 200, 2);
+    manager.save("set", false, None, points.clone()).unwrap();
+    let built = manager.build_index("set").unwrap();
+    let loaded = manager.index("set").unwrap();
+    assert_eq!(built.points, loaded.points, "a fresh index should load as saved");
+
+    // an index built from other points is rebuilt.
+    let index_info = IndexInfo{version: INDEX_VERSION, source_hash: "sha256:0".to_string()};
+    let info_path = manager.set_path("set").join(INDEX_INFO_FILE);
+    fs::write(&info_path, serde_json::to_string(&index_info).unwrap()).unwrap();
+    fs::write(manager.set_path("set").join(INDEX_FILE), "").unwrap();
+    assert_eq!(manager.index("set").unwrap().len(), 200);
+    assert!(fs::read_to_string(&info_path).unwrap().contains(&content_hash(&points)));
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use crate::data::{DataError, Result};
+use crate::data::point_set::hash::ContentHasher;
+use crate::data::point_set::{Manager, PointSetInfo};
+
+/// the outcome of checking a set whose points could all be read back.
+#[derive(Debug, PartialEq)]
+pub enum Verification {
+  // the points match the set's stored hash.
+  Matched,
+  // the set predates content hashes, so only its format and count could be
+  // checked. `hash` is the hash of the points as they are now.
+  Unhashed { hash: String },
+}
+
+impl Manager {
+  /// reads a set back in full and checks that its points file is well
+  /// formed, declares the format version its info does, holds as many
+  /// points as its info says and hashes to its stored hash. any mismatch
+  /// is reported as `DataError::Corrupt`.
+  pub fn verify(&self, name: &str) -> Result<Verification> {
+    let mut stream = self.stream(name)?;
+    let info = stream.info.clone();
+    if stream.format_version() != info.format_version {
+      return Err(DataError::Corrupt(format!(
+        "{}: info says format version {} but the points file has version {}",
+        name, info.format_version, stream.format_version())));
+    }
+    let mut hasher = ContentHasher::new();
+    for p in &mut stream {
+      hasher.update(&p?);
+    }
+    let hash = hasher.finish();
+    match info.content_hash {
+      None => Ok(Verification::Unhashed{hash}),
+      Some(stored) if stored == hash => Ok(Verification::Matched),
+      Some(stored) => Err(DataError::Corrupt(format!(
+        "{}: content hash mismatch (stored {}, found {})", name, stored, hash))),
+    }
+  }
+
+  /// verifies every set, including those whose info can't be read, so one
+  /// damaged set doesn't hide problems with the others.
+  pub fn verify_all(&self) -> Result<Vec<(String, Result<Verification>)>> {
+    let mut names = self.names()?;
+    names.sort();
+    Ok(names.into_iter()
+      .map(|name| {
+        let result = self.verify(&name);
+        (name, result)
+      })
+      .collect())
+  }
+
+  /// the sets whose stored content hash is `hash`.
+  pub fn find_by_hash(&self, hash: &str) -> Result<Vec<PointSetInfo>> {
+    Ok(self.list()?.into_iter()
+      .filter_map(|(_, info)| info.ok())
+      .filter(|info| info.content_hash.as_deref() == Some(hash))
+      .collect())
+  }
+
+  /// groups of two or more sets with identical contents, each ordered
+  /// oldest first. sets without a stored hash are never grouped.
+  pub fn duplicates(&self) -> Result<Vec<Vec<PointSetInfo>>> {
+    let mut by_hash: HashMap<String, Vec<PointSetInfo>> = HashMap::new();
+    for info in self.list()?.into_iter().filter_map(|(_, info)| info.ok()) {
+      if let Some(hash) = info.content_hash.clone() {
+        by_hash.entry(hash).or_default().push(info);
+      }
+    }
+    let mut groups: Vec<Vec<PointSetInfo>> = by_hash.into_values()
+      .map(|mut group| {
+        group.sort_by(|a, b| (a.created, &a.name).cmp(&(b.created, &b.name)));
+        group
+      })
+      .filter(|group| group.len() > 1)
+      .collect();
+    groups.sort_by(|a, b| a[0].name.cmp(&b[0].name));
+    Ok(groups)
+  }
+
+  /// moves every set that duplicates an older one to the trash, returning
+  /// each removed set with the name of the set it duplicated.
+  pub fn dedupe(&self) -> Result<Vec<(PointSetInfo, String)>> {
+    let mut removed = Vec::new();
+    for group in self.duplicates()? {
+      let (kept, rest) = group.split_first().unwrap();
+      for info in rest {
+        self.delete(&info.name)?;
+        removed.push((info.clone(), kept.name.clone()));
+      }
+    }
+    Ok(removed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use crate::data::point_set::tests::{sample_points, test_manager};
+  use crate::data::point_set::{content_hash, POINTS_FILE};
+
+  #[test]
+  fn test_verify() {
+    let manager = test_manager("verify");
+    let info = manager.save("good", false, None, sample_points()).unwrap();
+    assert_eq!(info.content_hash, Some(content_hash(&sample_points())));
+    assert_eq!(manager.verify("good").unwrap(), Verification::Matched);
+
+    // swap two points: the file is still well formed, with the right
+    // count, but its contents changed.
+    let mut edited = sample_points();
+    edited.swap(0, 1);
+    manager.save("edited", false, None, edited).unwrap();
+    let points_path = |name: &str| manager.set_path(name).join(POINTS_FILE);
+    fs::copy(points_path("edited"), points_path("good")).unwrap();
+    match manager.verify("good") {
+      Err(DataError::Corrupt(e)) => assert!(e.contains("hash mismatch")),
+      r => panic!("an edited set should fail verification, got {:?}", r),
+    }
+
+    let results = manager.verify_all().unwrap();
+    let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["edited", "good"]);
+    assert!(results[0].1.is_ok() && results[1].1.is_err());
+
+    let points_path = points_path("edited");
+    let mut contents = fs::read(&points_path).unwrap();
+    contents.push(0);
+    fs::write(&points_path, contents).unwrap();
+    assert!(manager.verify("edited").is_err(), "trailing data should fail verification");
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+
+  #[test]
+  fn test_dedupe() {
+    let manager = test_manager("dedupe");
+    manager.save("first", false, None, sample_points()).unwrap();
+    manager.save("other", false, None, sample_points()[1..].to_vec()).unwrap();
+    manager.save("second", false, None, sample_points()).unwrap();
+    manager.copy("first", "third", false).unwrap();
+
+    let groups = manager.duplicates().unwrap();
+    assert_eq!(groups.len(), 1);
+    let names: Vec<&str> = groups[0].iter().map(|info| info.name.as_str()).collect();
+    assert_eq!(names, vec!["first", "second", "third"]);
+
+    let hash = content_hash(&sample_points());
+    assert_eq!(manager.find_by_hash(&hash).unwrap().len(), 3);
+
+    let removed = manager.dedupe().unwrap();
+    assert_eq!(removed.len(), 2);
+    assert!(removed.iter().all(|(_, kept)| kept == "first"));
+    let remaining: Vec<String> = manager.list().unwrap().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(remaining, vec!["first".to_string(), "other".to_string()]);
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+}
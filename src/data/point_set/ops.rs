@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+use std::fs;
+
+use rand::SeedableRng;
+
+use crate::data::{DataError, Result};
+use crate::data::point_set::{
+  validate_name, write_info, Manager, Origin, Point, PointSetInfo, Region, SeededRng};
+
+// set operations compare points exactly, so two points are the same only if
+// both rational coordinates are equal. outputs keep the order in which their
+// points first appear in the inputs and never contain duplicates.
+
+fn dedup(points: impl IntoIterator<Item = Point>) -> Vec<Point> {
+  let mut seen = HashSet::new();
+  points.into_iter().filter(|p| seen.insert(p.clone())).collect()
+}
+
+impl Manager {
+  fn load_points(&self, name: &str) -> Result<Vec<Point>> {
+    Ok(self.load(name)?.points)
+  }
+
+  pub fn copy(&self, source: &str, dest: &str, overwrite: bool) -> Result<PointSetInfo> {
+    let points = self.load_points(source)?;
+    self.save(dest, overwrite, Some(Origin::derived("copy", &[source])), points)
+  }
+
+  /// renames a set in place. the set keeps its creation time and origin.
+  pub fn rename(&self, source: &str, dest: &str) -> Result<PointSetInfo> {
+    validate_name(dest)?;
+    let mut info = self.info(source)?;
+    if self.set_path(dest).exists() {
+      return Err(DataError::AlreadyExists(dest.to_string()));
+    }
+    fs::rename(self.set_path(source), self.set_path(dest))?;
+    info.name = dest.to_string();
+    write_info(&self.set_path(dest), &info)?;
+    Ok(info)
+  }
+
+  /// the union of all `sources`.
+  pub fn merge(&self, dest: &str, sources: &[&str], overwrite: bool) -> Result<PointSetInfo> {
+    let mut points = Vec::new();
+    for source in sources {
+      points.extend(self.load_points(source)?);
+    }
+    self.save(dest, overwrite, Some(Origin::derived("merge", sources)),
+      dedup(points))
+  }
+
+  /// the points of `source` that don't appear in any of `removed`.
+  pub fn subtract(
+      &self, dest: &str, source: &str, removed: &[&str], overwrite: bool,
+  ) -> Result<PointSetInfo> {
+    let mut excluded = HashSet::new();
+    for name in removed {
+      excluded.extend(self.load_points(name)?);
+    }
+    let points = self.load_points(source)?.into_iter()
+      .filter(|p| !excluded.contains(p));
+    let sources: Vec<&str> = std::iter::once(source).chain(removed.iter().cloned()).collect();
+    self.save(dest, overwrite, Some(Origin::derived("subtract", &sources)),
+      dedup(points))
+  }
+
+  /// the points that appear in every one of `sources`.
+  pub fn intersect(&self, dest: &str, sources: &[&str], overwrite: bool) -> Result<PointSetInfo> {
+    let (first, rest) = sources.split_first()
+      .ok_or_else(|| DataError::InvalidArgument("no point sets to intersect".to_string()))?;
+    let mut points = dedup(self.load_points(first)?);
+    for name in rest {
+      let other: HashSet<Point> = self.load_points(name)?.into_iter().collect();
+      points.retain(|p| other.contains(p));
+    }
+    self.save(dest, overwrite, Some(Origin::derived("intersect", sources)), points)
+  }
+
+  /// the points of `source` that lie in `region`. the source is streamed,
+  /// so it needn't fit in memory.
+  pub fn filter(
+      &self, dest: &str, source: &str, region: &Region, overwrite: bool,
+  ) -> Result<PointSetInfo> {
+    let points = self.stream(source)?
+      .filter(|p| p.as_ref().map_or(true, |p| region.contains(p)));
+    let origin = Origin::Derived{
+      operation: "filter".to_string(),
+      sources: vec![source.to_string()],
+      region: Some(region.to_string()),
+      seed: None,
+    };
+    self.try_save(dest, overwrite, Some(origin), points)
+  }
+
+  /// `count` distinct points chosen uniformly at random from `source`, in
+  /// their original order. if `source` has fewer than `count` points, the
+  /// result is all of them. the same `seed` picks the same points.
+  pub fn sample(
+      &self, dest: &str, source: &str, count: usize, overwrite: bool, seed: u64,
+  ) -> Result<PointSetInfo> {
+    let points = dedup(self.load_points(source)?);
+    let count = count.min(points.len());
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let mut indices = rand::seq::index::sample(&mut rng, points.len(), count).into_vec();
+    indices.sort_unstable();
+    let sampled: Vec<Point> = indices.into_iter().map(|i| points[i].clone()).collect();
+    let origin = Origin::Derived{
+      operation: "sample".to_string(),
+      sources: vec![source.to_string()],
+      region: None,
+      seed: Some(seed),
+    };
+    self.save(dest, overwrite, Some(origin), sampled)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+  use crate::data::point_set::tests::test_manager;
+
+  fn points(coords: &[(i32, i32)]) -> Vec<Point> {
+    coords.iter().map(|&(x, y)| (Rational::from((x, 8)), Rational::from((y, 8)))).collect()
+  }
+
+  #[test]
+  fn test_set_operations() {
+    let manager = test_manager("ops");
+    manager.save("a", false, None, points(&[(1, 1), (2, 1), (3, 1)])).unwrap();
+    manager.save("b", false, None, points(&[(3, 1), (4, 1), (1, 1)])).unwrap();
+
+    manager.merge("union", &["a", "b"], false).unwrap();
+    assert_eq!(manager.load("union").unwrap().points,
+      points(&[(1, 1), (2, 1), (3, 1), (4, 1)]));
+
+    manager.subtract("diff", "a", &["b"], false).unwrap();
+    assert_eq!(manager.load("diff").unwrap().points, points(&[(2, 1)]));
+
+    let info = manager.intersect("both", &["a", "b"], false).unwrap();
+    assert_eq!(manager.load("both").unwrap().points, points(&[(1, 1), (3, 1)]));
+    match info.origin {
+      Some(Origin::Derived{operation, sources, ..}) => {
+        assert_eq!(operation, "intersect");
+        assert_eq!(sources, vec!["a".to_string(), "b".to_string()]);
+      },
+      _ => panic!("derived sets should record their origin"),
+    }
+
+    manager.copy("a", "c", false).unwrap();
+    manager.rename("c", "d").unwrap();
+    assert!(!manager.exists("c"));
+    assert_eq!(manager.info("d").unwrap().name, "d");
+    assert_eq!(manager.load("d").unwrap().points, manager.load("a").unwrap().points);
+
+    let region: Region = "polygon 0,0 3/16,0 3/16,1 0,1".parse().unwrap();
+    manager.filter("left", "union", &region, false).unwrap();
+    assert_eq!(manager.load("left").unwrap().points, points(&[(1, 1)]));
+
+    assert!(matches!(manager.intersect("none", &[], false), Err(DataError::InvalidArgument(_))));
+
+    let info = manager.sample("s", "union", 2, false, 7).unwrap();
+    assert_eq!(info.count, 2);
+    assert!(matches!(info.origin, Some(Origin::Derived{seed: Some(7), ..})));
+    let sampled = manager.load("s").unwrap().points;
+    manager.sample("s", "union", 2, true, 7).unwrap();
+    assert_eq!(manager.load("s").unwrap().points, sampled, "a seed should pick the same points");
+    let info = manager.sample("s", "union", 10, true, 8).unwrap();
+    assert_eq!(info.count, 4, "sampling more points than exist should take all of them");
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+}
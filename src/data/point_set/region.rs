@@ -0,0 +1,488 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use rug::{Float, Rational};
+use rug::float::Constant;
+
+use crate::data::point_set::Point;
+use crate::util::rational::{parse_rational, parse_rational_pair};
+
+// a region of the apex plane, given as a conjunction of constraints on the
+// apex. membership is decided exactly for rational apexes: shapes use exact
+// rational arithmetic, and angle bounds are compared either exactly (when
+// cos^2 of the bound is rational) or by refining floating point intervals
+// until they separate, which always happens since an angle of a triangle
+// with rational vertices can't equal such a bound.
+//
+// the spec syntax joins constraints with "and":
+//
+//   angle <vertex> <op> <degrees>        vertex: a0, b0, b1, largest, smallest
+//   angle <vertex> between <lo> <hi>     op: <, <=, >, >=
+//   disc <cx>,<cy> <radius>
+//   annulus <cx>,<cy> <inner> <outer>
+//   polygon <x>,<y> <x>,<y> <x>,<y> ...
+//
+// e.g. "angle largest between 90 100 and disc 0.5,0 0.25". shapes include
+// their boundary.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AngleVertex {
+  // the apex
+  A0,
+  B0,
+  B1,
+  Largest,
+  Smallest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+  Less,
+  LessOrEqual,
+  Greater,
+  GreaterOrEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constraint {
+  Angle { vertex: AngleVertex, op: Comparison, degrees: Rational },
+  Disc { center: Point, radius: Rational },
+  Annulus { center: Point, inner: Rational, outer: Rational },
+  Polygon(Vec<Point>),
+}
+
+/// the points satisfying every constraint. an empty region is the whole
+/// plane.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Region(pub Vec<Constraint>);
+
+fn sub(a: &Point, b: &Point) -> Point {
+  (Rational::from(&a.0 - &b.0), Rational::from(&a.1 - &b.1))
+}
+
+fn dot(a: &Point, b: &Point) -> Rational {
+  Rational::from(&a.0 * &b.0) + Rational::from(&a.1 * &b.1)
+}
+
+fn cross(a: &Point, b: &Point) -> Rational {
+  Rational::from(&a.0 * &b.1) - Rational::from(&a.1 * &b.0)
+}
+
+fn squared_distance(a: &Point, b: &Point) -> Rational {
+  let d = sub(a, b);
+  dot(&d, &d)
+}
+
+// the triangle's vertices, as (vertex, previous neighbor, next neighbor).
+fn triangle(apex: &Point) -> [(Point, Point, Point); 3] {
+  let b0 = (Rational::from(0), Rational::from(0));
+  let b1 = (Rational::from(1), Rational::from(0));
+  [
+    (apex.clone(), b0.clone(), b1.clone()),
+    (b0.clone(), b1.clone(), apex.clone()),
+    (b1, apex.clone(), b0),
+  ]
+}
+
+// cos^2 and the sign of cos for the angles whose cos^2 is rational, which
+// by Niven's theorem are the multiples of 30 and 45 degrees.
+fn exact_cosine(degrees: &Rational) -> Option<(Ordering, Rational)> {
+  let table: [(i32, i32, (i32, i32)); 9] = [
+    (0, 1, (1, 1)), (30, 1, (3, 4)), (45, 1, (1, 2)), (60, 1, (1, 4)),
+    (90, 0, (0, 1)), (120, -1, (1, 4)), (135, -1, (1, 2)), (150, -1, (3, 4)),
+    (180, -1, (1, 1)),
+  ];
+  table.iter()
+    .find(|(d, _, _)| *degrees == *d)
+    .map(|(_, sign, square)| (sign.cmp(&0), Rational::from(*square)))
+}
+
+// compares the angle at `vertex` between the edges to `prev` and `next`
+// with `degrees`, which must lie in [0, 180].
+fn compare_angle(vertex: &Point, prev: &Point, next: &Point, degrees: &Rational) -> Ordering {
+  let u = sub(prev, vertex);
+  let v = sub(next, vertex);
+  // the angle is arccos(d / sqrt(n)); cos is decreasing, so larger angles
+  // have smaller cosines.
+  let d = dot(&u, &v);
+  let n = dot(&u, &u) * dot(&v, &v);
+  let sign = d.cmp0();
+  let cos_squared = Rational::from(d.square_ref()) / &n;
+  if let Some((bound_sign, bound_squared)) = exact_cosine(degrees) {
+    let cos_order = match (sign, bound_sign) {
+      (Ordering::Equal, _) | (_, Ordering::Equal) => sign.cmp(&bound_sign),
+      (s, b) if s != b => s.cmp(&b),
+      (Ordering::Greater, _) => cos_squared.cmp(&bound_squared),
+      _ => bound_squared.cmp(&cos_squared),
+    };
+    return cos_order.reverse();
+  }
+  // f64 alone is accurate to far better than this margin, so it settles
+  // everything except angles extremely close to the bound.
+  let approx_cos = d.to_f64().signum() * cos_squared.to_f64().sqrt();
+  let approx_diff = approx_cos - (degrees.to_f64() * std::f64::consts::PI / 180.0).cos();
+  if approx_diff.abs() > 1e-9 {
+    return if approx_diff > 0.0 { Ordering::Less } else { Ordering::Greater };
+  }
+  let mut prec = 64;
+  loop {
+    let radians: Float = Float::with_val(prec, degrees) * Float::with_val(prec, Constant::Pi) / 180;
+    let bound_cos = radians.cos();
+    let mut cos = Float::with_val(prec, &cos_squared).sqrt();
+    if sign == Ordering::Less {
+      cos = -cos;
+    }
+    // generous bounds on the accumulated rounding error of each side.
+    let error = Float::with_val(prec, Float::i_exp(1, 7 - prec as i32));
+    let diff = Float::with_val(prec, &cos - &bound_cos);
+    if diff.clone().abs() > error {
+      return if diff > 0 { Ordering::Less } else { Ordering::Greater };
+    }
+    prec *= 2;
+  }
+}
+
+impl Comparison {
+  fn accepts(&self, order: Ordering) -> bool {
+    match self {
+      Comparison::Less => order == Ordering::Less,
+      Comparison::LessOrEqual => order != Ordering::Greater,
+      Comparison::Greater => order == Ordering::Greater,
+      Comparison::GreaterOrEqual => order != Ordering::Less,
+    }
+  }
+}
+
+fn on_segment(p: &Point, a: &Point, b: &Point) -> bool {
+  cross(&sub(b, a), &sub(p, a)) == 0 &&
+    dot(&sub(p, a), &sub(p, b)) <= 0
+}
+
+// even-odd rule, with points on an edge counted as inside.
+fn in_polygon(p: &Point, vertices: &[Point]) -> bool {
+  let mut inside = false;
+  for i in 0..vertices.len() {
+    let a = &vertices[i];
+    let b = &vertices[(i + 1) % vertices.len()];
+    if on_segment(p, a, b) {
+      return true;
+    }
+    if (a.1 > p.1) != (b.1 > p.1) {
+      // the x coordinate where the edge crosses the horizontal through p.
+      let t = Rational::from(&p.1 - &a.1) / Rational::from(&b.1 - &a.1);
+      let x = t * Rational::from(&b.0 - &a.0) + &a.0;
+      if p.0 < x {
+        inside = !inside;
+      }
+    }
+  }
+  inside
+}
+
+impl Constraint {
+  pub fn contains(&self, p: &Point) -> bool {
+    match self {
+      Constraint::Angle{vertex, op, degrees} => {
+        let corners = triangle(p);
+        let (v, prev, next) = match vertex {
+          AngleVertex::A0 => &corners[0],
+          AngleVertex::B0 => &corners[1],
+          AngleVertex::B1 => &corners[2],
+          // the largest angle is opposite the longest side, i.e. the side
+          // between the vertex's two neighbors.
+          AngleVertex::Largest => corners.iter()
+            .max_by(|a, b| squared_distance(&a.1, &a.2).cmp(&squared_distance(&b.1, &b.2)))
+            .unwrap(),
+          AngleVertex::Smallest => corners.iter()
+            .min_by(|a, b| squared_distance(&a.1, &a.2).cmp(&squared_distance(&b.1, &b.2)))
+            .unwrap(),
+        };
+        op.accepts(compare_angle(v, prev, next, degrees))
+      },
+      Constraint::Disc{center, radius} =>
+        squared_distance(p, center) <= Rational::from(radius.square_ref()),
+      Constraint::Annulus{center, inner, outer} => {
+        let d = squared_distance(p, center);
+        Rational::from(inner.square_ref()) <= d && d <= Rational::from(outer.square_ref())
+      },
+      Constraint::Polygon(vertices) => in_polygon(p, vertices),
+    }
+  }
+
+  /// a conservative test for whether the constraint holds anywhere in the
+  /// closed box [min.0, max.0] x [min.1, max.1]: false only if it certainly
+  /// doesn't.
+  pub fn may_meet_box(&self, min: &Point, max: &Point) -> bool {
+    let clamp = |v: &Rational, lo: &Rational, hi: &Rational| {
+      v.clone().clamp(lo, hi)
+    };
+    let nearest = |c: &Point| (clamp(&c.0, &min.0, &max.0), clamp(&c.1, &min.1, &max.1));
+    let farthest = |c: &Point| {
+      let far = |v: &Rational, lo: &Rational, hi: &Rational| {
+        if Rational::from(v - lo) > Rational::from(hi - v) { lo.clone() } else { hi.clone() }
+      };
+      (far(&c.0, &min.0, &max.0), far(&c.1, &min.1, &max.1))
+    };
+    match self {
+      Constraint::Angle{..} => true,
+      Constraint::Disc{center, radius} =>
+        squared_distance(&nearest(center), center) <= Rational::from(radius.square_ref()),
+      Constraint::Annulus{center, inner, outer} =>
+        squared_distance(&nearest(center), center) <= Rational::from(outer.square_ref()) &&
+          squared_distance(&farthest(center), center) >= Rational::from(inner.square_ref()),
+      Constraint::Polygon(vertices) => {
+        let (lo, hi) = self.bounding_box().unwrap();
+        lo.0 <= max.0 && hi.0 >= min.0 && lo.1 <= max.1 && hi.1 >= min.1
+      },
+    }
+  }
+
+  /// the smallest box [min.0, max.0] x [min.1, max.1] holding every point
+  /// satisfying the constraint, or `None` for an angle bound, which
+  /// doesn't bound the apex.
+  pub fn bounding_box(&self) -> Option<(Point, Point)> {
+    let around = |c: &Point, r: &Rational| Some((
+      (Rational::from(&c.0 - r), Rational::from(&c.1 - r)),
+      (Rational::from(&c.0 + r), Rational::from(&c.1 + r))));
+    match self {
+      Constraint::Angle{..} => None,
+      Constraint::Disc{center, radius} => around(center, radius),
+      Constraint::Annulus{center, outer, ..} => around(center, outer),
+      Constraint::Polygon(vertices) => {
+        let lo_x = vertices.iter().map(|v| &v.0).min().unwrap();
+        let hi_x = vertices.iter().map(|v| &v.0).max().unwrap();
+        let lo_y = vertices.iter().map(|v| &v.1).min().unwrap();
+        let hi_y = vertices.iter().map(|v| &v.1).max().unwrap();
+        Some(((lo_x.clone(), lo_y.clone()), (hi_x.clone(), hi_y.clone())))
+      },
+    }
+  }
+}
+
+impl Region {
+  pub fn is_everything(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn contains(&self, p: &Point) -> bool {
+    // shapes are cheaper to test than angles, so they go first.
+    let is_angle = |c: &&Constraint| matches!(c, Constraint::Angle{..});
+    self.0.iter().filter(|c| !is_angle(c)).all(|c| c.contains(p)) &&
+      self.0.iter().filter(is_angle).all(|c| c.contains(p))
+  }
+
+  pub fn may_meet_box(&self, min: &Point, max: &Point) -> bool {
+    self.0.iter().all(|c| c.may_meet_box(min, max))
+  }
+
+  /// a box holding the whole region, the overlap of its constraints' boxes,
+  /// or `None` if no constraint bounds it. the box is empty, with min
+  /// beyond max, if those don't overlap.
+  pub fn bounding_box(&self) -> Option<(Point, Point)> {
+    self.0.iter()
+      .filter_map(Constraint::bounding_box)
+      .reduce(|(lo, hi), (min, max)| (
+        (lo.0.max(min.0), lo.1.max(min.1)),
+        (hi.0.min(max.0), hi.1.min(max.1))))
+  }
+}
+
+impl fmt::Display for AngleVertex {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let name = match self {
+      AngleVertex::A0 => "a0",
+      AngleVertex::B0 => "b0",
+      AngleVertex::B1 => "b1",
+      AngleVertex::Largest => "largest",
+      AngleVertex::Smallest => "smallest",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+impl fmt::Display for Comparison {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let op = match self {
+      Comparison::Less => "<",
+      Comparison::LessOrEqual => "<=",
+      Comparison::Greater => ">",
+      Comparison::GreaterOrEqual => ">=",
+    };
+    write!(f, "{}", op)
+  }
+}
+
+impl fmt::Display for Constraint {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Constraint::Angle{vertex, op, degrees} =>
+        write!(f, "angle {} {} {}", vertex, op, degrees),
+      Constraint::Disc{center, radius} =>
+        write!(f, "disc {},{} {}", center.0, center.1, radius),
+      Constraint::Annulus{center, inner, outer} =>
+        write!(f, "annulus {},{} {} {}", center.0, center.1, inner, outer),
+      Constraint::Polygon(vertices) => {
+        write!(f, "polygon")?;
+        for v in vertices {
+          write!(f, " {},{}", v.0, v.1)?;
+        }
+        Ok(())
+      },
+    }
+  }
+}
+
+impl fmt::Display for Region {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let constraints: Vec<String> = self.0.iter().map(|c| c.to_string()).collect();
+    write!(f, "{}", constraints.join(" and "))
+  }
+}
+
+fn parse_degrees(s: &str) -> Result<Rational, String> {
+  let degrees = parse_rational(s).ok_or_else(|| format!("expected an angle, got '{}'", s))?;
+  if !(0..=180).contains(&degrees) {
+    return Err(format!("angle {} isn't between 0 and 180 degrees", s));
+  }
+  Ok(degrees)
+}
+
+fn parse_nonnegative(s: &str) -> Result<Rational, String> {
+  match parse_rational(s) {
+    Some(r) if r >= 0 => Ok(r),
+    _ => Err(format!("expected a nonnegative number, got '{}'", s)),
+  }
+}
+
+fn parse_point(s: &str) -> Result<Point, String> {
+  parse_rational_pair(s).ok_or_else(|| format!("expected a point x,y, got '{}'", s))
+}
+
+fn parse_constraints(words: &[&str]) -> Result<Vec<Constraint>, String> {
+  let bad = || format!("couldn't parse region constraint '{}'", words.join(" "));
+  match words {
+    ["angle", vertex, rest @ ..] => {
+      let vertex = match *vertex {
+        "a0" => AngleVertex::A0,
+        "b0" => AngleVertex::B0,
+        "b1" => AngleVertex::B1,
+        "largest" => AngleVertex::Largest,
+        "smallest" => AngleVertex::Smallest,
+        _ => return Err(format!("unknown vertex '{}'", vertex)),
+      };
+      let angle = |op, degrees| Constraint::Angle{vertex, op, degrees};
+      match rest {
+        ["between", lo, hi] => Ok(vec![
+          angle(Comparison::GreaterOrEqual, parse_degrees(lo)?),
+          angle(Comparison::LessOrEqual, parse_degrees(hi)?),
+        ]),
+        [op, degrees] => {
+          let op = match *op {
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            _ => return Err(format!("unknown comparison '{}'", op)),
+          };
+          Ok(vec![angle(op, parse_degrees(degrees)?)])
+        },
+        _ => Err(bad()),
+      }
+    },
+    ["disc", center, radius] => Ok(vec![Constraint::Disc{
+      center: parse_point(center)?,
+      radius: parse_nonnegative(radius)?,
+    }]),
+    ["annulus", center, inner, outer] => Ok(vec![Constraint::Annulus{
+      center: parse_point(center)?,
+      inner: parse_nonnegative(inner)?,
+      outer: parse_nonnegative(outer)?,
+    }]),
+    ["polygon", vertices @ ..] if vertices.len() >= 3 => {
+      let vertices: Result<Vec<Point>, String> =
+        vertices.iter().map(|v| parse_point(v)).collect();
+      Ok(vec![Constraint::Polygon(vertices?)])
+    },
+    _ => Err(bad()),
+  }
+}
+
+impl FromStr for Region {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Region, String> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    let mut constraints = Vec::new();
+    for clause in words.split(|w| *w == "and") {
+      constraints.extend(parse_constraints(clause)?);
+    }
+    Ok(Region(constraints))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn point(x: (i32, i32), y: (i32, i32)) -> Point {
+    (Rational::from(x), Rational::from(y))
+  }
+
+  #[test]
+  fn test_angles() {
+    // the apex (1/2, 1/2) gives a right isosceles triangle.
+    let right = point((1, 2), (1, 2));
+    let region: Region = "angle a0 >= 90 and angle a0 <= 90".parse().unwrap();
+    assert!(region.contains(&right), "apex angle should be exactly 90 degrees");
+    assert!(!"angle a0 > 90".parse::<Region>().unwrap().contains(&right));
+    assert!("angle b0 <= 45 and angle b1 >= 45".parse::<Region>().unwrap().contains(&right));
+    assert!("angle largest between 89.9 90.1".parse::<Region>().unwrap().contains(&right));
+
+    // the apex (1/2, 1/4) has apex angle 2 * atan(2) ~ 126.87 degrees.
+    let obtuse = point((1, 2), (1, 4));
+    let region: Region = "angle largest between 126.8 126.9".parse().unwrap();
+    assert!(region.contains(&obtuse));
+    assert!(!"angle a0 < 126.86".parse::<Region>().unwrap().contains(&obtuse));
+    assert!("angle smallest < 27".parse::<Region>().unwrap().contains(&obtuse));
+    // bounds within 1e-13 degrees of the angle need more than f64.
+    let region: Region = "angle a0 between 126.869897645844 126.8698976458441".parse().unwrap();
+    assert!(region.contains(&obtuse));
+    assert!(!"angle a0 >= 126.8698976458441".parse::<Region>().unwrap().contains(&obtuse));
+  }
+
+  #[test]
+  fn test_shapes() {
+    let region: Region = "disc 0.5,0 1/4".parse().unwrap();
+    assert!(region.contains(&point((1, 2), (1, 4))), "boundary should be included");
+    assert!(!region.contains(&point((1, 2), (1, 3))));
+
+    let region: Region = "annulus 1/2,0 0.25 0.5".parse().unwrap();
+    assert!(!region.contains(&point((1, 2), (1, 8))));
+    assert!(region.contains(&point((1, 2), (3, 8))));
+
+    let region: Region = "polygon 0,0 1,0 1/2,1/2".parse().unwrap();
+    assert!(region.contains(&point((1, 2), (1, 4))));
+    assert!(region.contains(&point((1, 4), (1, 4))), "edges should be included");
+    assert!(!region.contains(&point((1, 8), (1, 4))));
+    assert!(!region.may_meet_box(&point((0, 1), (3, 4)), &point((1, 1), (1, 1))));
+
+    // only the shapes bound a region.
+    let region: Region = "angle a0 > 90 and disc 1/2,1/4 1/8 and polygon 0,0 1,0 1/2,1/2"
+      .parse().unwrap();
+    assert_eq!(region.bounding_box(), Some((point((3, 8), (1, 8)), point((5, 8), (3, 8)))));
+    assert_eq!("angle a0 > 90".parse::<Region>().unwrap().bounding_box(), None);
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let spec = "angle largest >= 90 and angle largest <= 195/2 and disc 1/2,0 1/4 \
+      and annulus 0,0 1 2 and polygon 0,0 1,0 1/2,1/2";
+    let region: Region = "angle largest between 90 97.5 and disc 0.5,0 0.25 \
+      and annulus 0,0 1 2 and polygon 0,0 1,0 1/2,1/2".parse().unwrap();
+    assert_eq!(region.to_string(), spec);
+    assert_eq!(spec.parse::<Region>().unwrap(), region);
+    assert!("disc 0.5 0.5".parse::<Region>().is_err());
+    assert!("angle a0 between 90".parse::<Region>().is_err());
+  }
+}
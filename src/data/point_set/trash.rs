@@ -0,0 +1,156 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use chrono::Duration;
+
+use crate::data::{validate_name, DataError, Result};
+use crate::data::point_set::{Manager, PointSetInfo};
+
+// deleted sets are moved, unchanged, to <root>/.trash/<name>.<millis>, where
+// <millis> is the deletion time in milliseconds since the unix epoch. the
+// same name can be deleted any number of times; restoring it brings back
+// the most recent copy.
+const TRASH_DIR: &str = ".trash";
+
+pub struct TrashedPointSet {
+  pub name: String,
+  // the set's info, or why it can't be read. unreadable sets can still be
+  // purged.
+  pub info: Result<PointSetInfo>,
+  pub deleted: DateTime<Utc>,
+  path: PathBuf,
+}
+
+fn parse_entry_name(entry_name: &str) -> Option<(&str, DateTime<Utc>)> {
+  let (name, millis) = entry_name.rsplit_once('.')?;
+  let millis = millis.parse::<i64>().ok()?;
+  Some((name, Utc.timestamp_millis_opt(millis).single()?))
+}
+
+impl Manager {
+  fn trash_path(&self) -> PathBuf {
+    self.root.join(TRASH_DIR)
+  }
+
+  /// moves the named set to the trash, from where it can be restored
+  /// until it is purged.
+  pub fn delete(&self, name: &str) -> Result<()> {
+    validate_name(name)?;
+    if !self.exists(name) {
+      return Err(DataError::NotFound(name.to_string()));
+    }
+    fs::create_dir_all(self.trash_path())?;
+    let mut deleted = Utc::now();
+    let mut dest;
+    loop {
+      dest = self.trash_path().join(
+        format!("{}.{}", name, deleted.timestamp_millis()));
+      if !dest.exists() {
+        break;
+      }
+      deleted += Duration::milliseconds(1);
+    }
+    fs::rename(self.set_path(name), dest)?;
+    Ok(())
+  }
+
+  /// all sets in the trash, most recently deleted first.
+  pub fn list_trash(&self) -> Result<Vec<TrashedPointSet>> {
+    let mut result = Vec::new();
+    if !self.trash_path().is_dir() {
+      return Ok(result);
+    }
+    for entry in fs::read_dir(self.trash_path())? {
+      let entry = entry?;
+      let entry_name = entry.file_name().into_string()?;
+      let (name, deleted) = match parse_entry_name(&entry_name) {
+        Some(parsed) => parsed,
+        None => continue,
+      };
+      let info = self.read_info(name, &entry.path());
+      result.push(TrashedPointSet{name: name.to_string(), info, deleted, path: entry.path()});
+    }
+    result.sort_by_key(|t| std::cmp::Reverse(t.deleted));
+    Ok(result)
+  }
+
+  /// moves the most recently deleted set called `name` out of the trash,
+  /// unless its info can't be read.
+  pub fn restore(&self, name: &str) -> Result<PointSetInfo> {
+    validate_name(name)?;
+    if self.exists(name) {
+      return Err(DataError::AlreadyExists(name.to_string()));
+    }
+    let trashed = self.list_trash()?.into_iter()
+      .find(|t| t.name == name)
+      .ok_or_else(|| DataError::NotFound(format!("{} (in trash)", name)))?;
+    let info = trashed.info?;
+    fs::rename(&trashed.path, self.set_path(name))?;
+    Ok(info)
+  }
+
+  /// permanently removes trashed sets deleted more than `older_than` ago,
+  /// or every trashed set if `older_than` is `None`. returns the sets that
+  /// were removed.
+  pub fn purge(&self, older_than: Option<Duration>) -> Result<Vec<TrashedPointSet>> {
+    let now = Utc::now();
+    let mut purged = Vec::new();
+    for trashed in self.list_trash()? {
+      let expired = match older_than {
+        Some(age) => now - trashed.deleted > age,
+        None => true,
+      };
+      if expired {
+        fs::remove_dir_all(&trashed.path)?;
+        purged.push(trashed);
+      }
+    }
+    Ok(purged)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::data::point_set::tests::{sample_points, test_manager};
+
+  #[test]
+  fn test_delete_and_restore() {
+    let manager = test_manager("trash");
+    manager.save("test", false, None, sample_points()).unwrap();
+    manager.delete("test").unwrap();
+    assert!(!manager.exists("test"));
+    assert_eq!(manager.list().unwrap().len(), 0, "trashed sets shouldn't be listed");
+    assert_eq!(manager.list_trash().unwrap().len(), 1);
+
+    manager.restore("test").unwrap();
+    assert_eq!(manager.load("test").unwrap().points, sample_points());
+    assert_eq!(manager.list_trash().unwrap().len(), 0);
+
+    manager.delete("test").unwrap();
+    manager.save("test", false, None, vec![]).unwrap();
+    match manager.restore("test") {
+      Err(DataError::AlreadyExists(_)) => {},
+      _ => panic!("restoring over an existing set should fail"),
+    }
+    assert_eq!(manager.purge(Some(Duration::days(1))).unwrap().len(), 0);
+    assert_eq!(manager.purge(None).unwrap().len(), 1);
+    assert_eq!(manager.list_trash().unwrap().len(), 0);
+
+    // a trashed set with unreadable info is listed with its error, and
+    // doesn't stop the others being restored or purged.
+    manager.save("bad", false, None, sample_points()).unwrap();
+    manager.delete("bad").unwrap();
+    manager.delete("test").unwrap();
+    let bad = manager.list_trash().unwrap().into_iter().find(|t| t.name == "bad").unwrap();
+    fs::write(bad.path.join("info.json"), "{").unwrap();
+    assert!(matches!(manager.restore("bad"), Err(DataError::Corrupt(_))));
+    manager.restore("test").unwrap();
+    manager.delete("test").unwrap();
+    assert_eq!(manager.purge(None).unwrap().len(), 2);
+    assert!(matches!(manager.delete("../test"), Err(DataError::InvalidName(_))));
+
+    fs::remove_dir_all(&manager.root).unwrap();
+  }
+}
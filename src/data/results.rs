@@ -0,0 +1,261 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::billiards::orbit::CHECK_VERSION;
+use crate::billiards::staged::{StagedParams, StageStats};
+use crate::billiards::turn_path::TurnPath;
+use crate::data::{write_atomically, DataError, Result};
+use crate::data::point_set;
+use crate::vector::V2;
+
+// whether a word is a periodic orbit at a given exact apex never changes,
+// so the outcome of checking a word at every point of a set is stored
+// under the set's content hash:
+//
+//   <root>/<hash>/<word>    a `ResultsHeader` line, then one json
+//                           `PointResult` per point, in order
+//
+// where <word> is the word's `canonical_unmirrored` form, which stands for
+// every rotation and reversal of it. results from another version of the
+// check (see `CHECK_VERSION`) or of this layout (see `RESULTS_FORMAT`), or
+// that can't be read back, are computed again.
+//
+// words are checked in stages, with floats first and exactly only when
+// the floats look close (see `staged`). the stored feasibility is only as
+// reliable as that staging, whose float stage trusts a heuristic bound on
+// its rounding error, and the margins are f64 approximations rather than
+// exact rationals.
+
+// the layout of a stored `PointResult`. format 1, whose headers don't
+// record it, stored exact margins.
+const RESULTS_FORMAT: u32 = 2;
+
+fn legacy_format() -> u32 {
+  1
+}
+
+#[derive(Serialize, Deserialize)]
+struct ResultsHeader {
+  version: u32,
+  #[serde(default = "legacy_format")]
+  format: u32,
+  word: TurnPath,
+  count: u64,
+}
+
+/// the outcome of checking one word at one point.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PointResult {
+  pub feasible: bool,
+  // the corridor margin, approximately, if the word could be unfolded at
+  // all.
+  pub margin: Option<f64>,
+}
+
+/// stores the results of checking words at every point of a set.
+pub struct Manager {
+  root: PathBuf,
+}
+
+pub fn manager(root: PathBuf) -> Manager {
+  Manager{root}
+}
+
+// a file name for a word starting around B1, e.g. "-2_2_2_-2".
+fn word_key(word: &TurnPath) -> String {
+  word.to_string().replace(' ', "_")
+}
+
+impl Manager {
+  fn results_path(&self, hash: &str, word: &TurnPath) -> PathBuf {
+    let hex = hash.rsplit(':').next().unwrap();
+    self.root.join(hex).join(word_key(word))
+  }
+
+  // the stored results for the points with `hash`, if they're current.
+  fn read(&self, hash: &str, word: &TurnPath, count: u64) -> Option<Vec<PointResult>> {
+    let file = File::open(self.results_path(hash, word)).ok()?;
+    let mut lines = BufReader::new(file).lines();
+    let header: ResultsHeader = serde_json::from_str(&lines.next()?.ok()?).ok()?;
+    let current = header.version == CHECK_VERSION && header.format == RESULTS_FORMAT;
+    if !current || header.word != *word || header.count != count {
+      return None;
+    }
+    let results = lines
+      .map(|line| serde_json::from_str(&line.ok()?).ok())
+      .collect::<Option<Vec<PointResult>>>()?;
+    Some(results).filter(|results| results.len() as u64 == count)
+  }
+
+  fn write(&self, hash: &str, word: &TurnPath, results: &[PointResult]) -> Result<()> {
+    write_atomically(&self.results_path(hash, word), |writer| {
+      let header = ResultsHeader{
+        version: CHECK_VERSION, format: RESULTS_FORMAT, word: word.clone(), count: results.len() as u64,
+      };
+      serde_json::to_writer(&mut *writer, &header)?;
+      writeln!(writer)?;
+      for result in results {
+        serde_json::to_writer(&mut *writer, result)?;
+        writeln!(writer)?;
+      }
+      Ok(())
+    })
+  }
+
+  /// the results of checking each of `words` at every point of set `name`
+  /// of `points`, in order, checking only the words with no stored
+  /// results and storing what they give. the checks made are counted in
+  /// `stats`.
+  pub fn results(
+      &self, points: &point_set::Manager, name: &str, words: &[TurnPath], stats: &mut StageStats,
+  ) -> Result<Vec<Vec<PointResult>>> {
+    let hash = points.content_hash(name)?;
+    let count = points.info(name)?.count;
+    let canonical: Vec<TurnPath> = words.iter().map(|word| word.canonical_unmirrored()).collect();
+    let mut results: Vec<Option<Vec<PointResult>>> = canonical.iter()
+      .map(|word| self.read(&hash, word, count))
+      .collect();
+    let missing: Vec<usize> = (0..words.len()).filter(|&i| results[i].is_none()).collect();
+    if missing.is_empty() {
+      return Ok(results.into_iter().map(Option::unwrap).collect());
+    }
+    let mut computed = vec![vec![]; missing.len()];
+    for p in points.stream(name)? {
+      let mut params = StagedParams::new(V2::from(&p?));
+      for (&i, point_results) in missing.iter().zip(&mut computed) {
+        let check = params.check(&canonical[i], stats);
+        point_results.push(PointResult{
+          feasible: check.is_feasible(),
+          margin: check.margin(),
+        });
+      }
+    }
+    for (&i, point_results) in missing.iter().zip(computed) {
+      self.write(&hash, &canonical[i], &point_results)?;
+      results[i] = Some(point_results);
+    }
+    Ok(results.into_iter().map(Option::unwrap).collect())
+  }
+
+  /// every word with current stored results for set `name` of `points`,
+  /// shortest first, with its results.
+  pub fn stored(
+      &self, points: &point_set::Manager, name: &str,
+  ) -> Result<Vec<(TurnPath, Vec<PointResult>)>> {
+    let hash = points.content_hash(name)?;
+    let count = points.info(name)?.count;
+    let dir = self.root.join(hash.rsplit(':').next().unwrap());
+    if !dir.is_dir() {
+      return Ok(vec![]);
+    }
+    let mut stored = vec![];
+    for entry in fs::read_dir(&dir)? {
+      let path = entry?.path();
+      // skip files still being written.
+      if path.file_name().and_then(|s| s.to_str()).is_none_or(|s| s.starts_with('.')) {
+        continue;
+      }
+      // the header names the word, and `read` checks the rest.
+      let header = File::open(&path).ok()
+        .and_then(|file| BufReader::new(file).lines().next()?.ok())
+        .and_then(|line| serde_json::from_str::<ResultsHeader>(&line).ok());
+      if let Some(header) = header {
+        if let Some(results) = self.read(&hash, &header.word, count) {
+          stored.push((header.word, results));
+        }
+      }
+    }
+    stored.sort_by(|(a, _), (b, _)| (a.len(), a).cmp(&(b.len(), b)));
+    Ok(stored)
+  }
+
+  /// the positions of the points of set `name` of `points` at which each
+  /// of `words` is a periodic orbit.
+  pub fn feasibility(
+      &self, points: &point_set::Manager, name: &str, words: &[TurnPath], stats: &mut StageStats,
+  ) -> Result<Vec<Vec<usize>>> {
+    Ok(self.results(points, name, words, stats)?.into_iter()
+      .map(|results| results.iter().enumerate()
+        .filter(|(_, result)| result.feasible)
+        .map(|(i, _)| i)
+        .collect())
+      .collect())
+  }
+
+  /// forgets every stored result.
+  pub fn clear(&self) -> Result<()> {
+    if self.root.exists() {
+      fs::remove_dir_all(&self.root).map_err(DataError::from)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+  use crate::billiards::Params;
+  use crate::billiards::orbit::check_path;
+  use crate::data::point_set::tests::test_manager;
+
+  #[test]
+  fn test_results() {
+    let points = test_manager("results");
+    let results = manager(std::env::temp_dir().join(
+      format!("billiards-test-results-store-{}", std::process::id())));
+    results.clear().unwrap();
+    let q = |x: i32, y: i32| (Rational::from((x, 32)), Rational::from((y, 32)));
+    points.save("s", false, None, vec![q(16, 12), q(16, 10), q(19, 10)]).unwrap();
+
+    // a rotation of a word shares its results, but its mirror image
+    // doesn't.
+    let words: Vec<TurnPath> = [
+      "-2 2 2 -2", "2 -2 -2 2", "1", "B0^-3 B1^-2 B0^3 B1^2", "-3 -2 3 2",
+    ].iter()
+      .map(|word| word.parse().unwrap())
+      .collect();
+    let mut stats = StageStats::default();
+    assert_eq!(results.feasibility(&points, "s", &words, &mut stats).unwrap(),
+      vec![vec![0], vec![0], vec![], vec![2], vec![]]);
+    assert_eq!(stats.total(), 15);
+    let hash = points.content_hash("s").unwrap();
+    let stored = results.results_path(&hash, &words[0].canonical_unmirrored());
+    assert!(stored.is_file());
+    let computed = results.results(&points, "s", &words[..1], &mut stats).unwrap();
+    assert_eq!(stats.total(), 15, "stored results aren't checked again");
+    let margin = computed[0][0].margin.unwrap();
+    assert!(margin > 0.0);
+    let exact = check_path(&mut Params::new(V2::from(&q(16, 12))), &words[0]);
+    assert!((margin - exact.margin().unwrap().to_f64()).abs() < 1e-12);
+    assert_eq!(results.results(&points, "s", &words[2..], &mut stats).unwrap()[0][0],
+      PointResult{feasible: false, margin: None});
+
+    // stored results are what's read back, and results from another
+    // version are replaced.
+    let forged = PointResult{feasible: true, margin: Some(1.0)};
+    let write_forged = |version: u32, format: u32| {
+      let mut file = File::create(&stored).unwrap();
+      let header = ResultsHeader{version, format, word: words[0].canonical_unmirrored(), count: 3};
+      serde_json::to_writer(&mut file, &header).unwrap();
+      for _ in 0..3 {
+        writeln!(file).unwrap();
+        serde_json::to_writer(&mut file, &forged).unwrap();
+      }
+    };
+    write_forged(CHECK_VERSION, RESULTS_FORMAT);
+    assert_eq!(results.feasibility(&points, "s", &words[..1], &mut stats).unwrap(), vec![vec![0, 1, 2]]);
+    write_forged(CHECK_VERSION + 1, RESULTS_FORMAT);
+    assert_eq!(results.feasibility(&points, "s", &words[..1], &mut stats).unwrap(), vec![vec![0]]);
+    write_forged(CHECK_VERSION, legacy_format());
+    assert_eq!(results.feasibility(&points, "s", &words[..1], &mut stats).unwrap(), vec![vec![0]]);
+
+    // headers from before the format was recorded are of the legacy one.
+    let header = format!("{{\"version\":{},\"word\":\"-1 1\",\"count\":3}}", CHECK_VERSION);
+    assert_eq!(serde_json::from_str::<ResultsHeader>(&header).unwrap().format, legacy_format());
+    results.clear().unwrap();
+  }
+}
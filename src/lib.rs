@@ -0,0 +1,10 @@
+#![allow(dead_code, unused_variables, unused_imports)]
+
+pub mod algebra;
+pub mod billiards;
+pub mod command_line;
+pub mod data;
+pub mod geometry;
+mod repl;
+pub mod util;
+pub mod vector;
@@ -1,16 +1,7 @@
-#![allow(dead_code, unused_variables, unused_imports)]
-
-mod algebra;
-mod billiards;
-mod command_line;
-mod data;
-mod geometry;
-mod repl;
-mod util;
-mod vector;
-
 use std::env;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+
+use billiards::command_line;
 
 fn find_root_from_path(mut path: PathBuf) -> Option<PathBuf> {
 	let mut filepath = path.clone();
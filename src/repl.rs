@@ -5,6 +5,9 @@ use std::collections::HashMap;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
+use crate::billiards::singularity::BaseSingularity::{B0, B1};
+use crate::billiards::turn_path::TurnPath;
+
 pub enum NamespaceEntry {
 	PointSet,
 	PathSet,
@@ -27,6 +30,8 @@ impl ReplState {
 				self.cmd_plot(words);
 			} else if command == "load" {
 				self.cmd_load(words);
+			} else if command == "word" {
+				self.cmd_word(words);
 			}
 		}
 	}
@@ -43,7 +48,26 @@ impl ReplState {
 		}
 	}
 
-	fn cmd_load<'a, WordIter>(&self, mut words: WordIter)
+	fn cmd_word<'a, WordIter>(&self, words: WordIter)
+	where
+		WordIter: Iterator<Item = &'a str>
+	{
+		let spec: Vec<&str> = words.collect();
+		match spec.join(" ").parse::<TurnPath>() {
+			Ok(path) => {
+				let sums = path.turning_by_singularity();
+				println!("{}", path);
+				println!("  turns: {}, total turning: {}, absolute turning: {}",
+					path.len(), path.total_turning(), path.absolute_turning());
+				println!("  turning around B0: {}, around B1: {}, around the apexes: {}",
+					sums[B0], sums[B1], path.apex_turning());
+				println!("  canonical form: {}, mirror image: {}", path.canonical(), path.mirrored());
+			},
+			Err(e) => println!("word: {} (e.g. 'word -2 2 2 -2' or 'word B1^-2 B0^2')", e),
+		}
+	}
+
+	fn cmd_load<'a, WordIter>(&self, words: WordIter)
 	where
 		WordIter: Iterator<Item = &'a str>
 	{
@@ -76,7 +100,7 @@ impl fmt::Display for LoadSource {
 }
 
 fn parse_load_source(source: &str) -> Result<LoadSource, String> {
-	return Ok(LoadSource::PointSet(source.to_owned()))
+	Ok(LoadSource::PointSet(source.to_owned()))
 }
 
 /*fn parse_plot_source(source: &str) -> Result< > {
@@ -90,15 +114,15 @@ impl ReplState {
 }
 
 fn make_repl_state() -> ReplState {
-	return ReplState{
+	ReplState{
 		namespace: HashMap::new(),
 		cancelled: false,
-	};
+	}
 }
 
-pub fn run(root_path: &PathBuf) {
+pub fn run(root_path: &Path) {
 	let mut rl = Editor::<()>::new();
-	if rl.load_history("repl_history.txt").is_err() { }
+	let _ = rl.load_history("repl_history.txt");
 
 	let mut repl_state = make_repl_state();
 
@@ -1 +1,3 @@
-pub mod power_cache;
\ No newline at end of file
+pub mod power_cache;
+pub mod rational;
+pub mod set_cover;
@@ -2,11 +2,11 @@ use std::ops::{Add, Sub, Neg, Mul, Div, MulAssign};
 use rug::Rational;
 
 use crate::vector::V2;
-use crate::algebra::One;
+use crate::algebra::{One, RefOps};
 
 pub struct PowerCache<T>
 where
-    T: Mul<Output=T> + One + Clone
+    T: RefOps + One + Clone
 {
   base: T,
   powers: Vec<T>
@@ -20,7 +20,7 @@ pub struct TurnCache<T>
 
 impl<T> PowerCache<T>
 where
-    T: Mul<Output=T> + One + Clone
+    T: RefOps + One + Clone
 {
   pub fn new(base: T) -> PowerCache<T> {
     let powers: Vec<T> = vec![T::one(), base.clone()];
@@ -30,9 +30,8 @@ where
  pub fn get(&mut self, degree: u32) -> &T {
     let degree = degree as usize;
     while self.powers.len() <= degree {
-      let last: T = self.powers.last().unwrap().clone();
-      let base: T = self.base.clone();
-      self.powers.push(last * base);
+      let next = self.powers.last().unwrap().mul_ref(&self.base);
+      self.powers.push(next);
     }
     &self.powers[degree]
   }
@@ -0,0 +1,53 @@
+use rug::{Integer, Rational};
+
+/// parses an exact rational from an integer ("-3"), a fraction ("7/16") or
+/// a finite decimal ("0.4375").
+pub fn parse_rational(s: &str) -> Option<Rational> {
+  let s = s.trim();
+  if let Some((int_part, frac_part)) = s.split_once('.') {
+    // at most one sign.
+    let (negative, digits) = match int_part.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, int_part.strip_prefix('+').unwrap_or(int_part)),
+    };
+    if frac_part.is_empty() || !frac_part.chars().all(|c| c.is_ascii_digit()) ||
+        !digits.chars().all(|c| c.is_ascii_digit()) {
+      return None;
+    }
+    let numer: Integer = format!("{}{}", digits, frac_part).parse().ok()?;
+    let denom = Integer::from(Integer::u_pow_u(10, frac_part.len() as u32));
+    let value = Rational::from((numer, denom));
+    Some(if negative { -value } else { value })
+  } else {
+    s.parse::<Rational>().ok()
+  }
+}
+
+/// parses a point written as "x,y", with each coordinate accepted by
+/// `parse_rational`.
+pub fn parse_rational_pair(s: &str) -> Option<(Rational, Rational)> {
+  let (x, y) = s.split_once(',')?;
+  Some((parse_rational(x)?, parse_rational(y)?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_rational() {
+    assert_eq!(parse_rational("3"), Some(Rational::from(3)));
+    assert_eq!(parse_rational("-7/16"), Some(Rational::from((-7, 16))));
+    assert_eq!(parse_rational("0.4375"), Some(Rational::from((7, 16))));
+    assert_eq!(parse_rational("-.5"), Some(Rational::from((-1, 2))));
+    assert_eq!(parse_rational("97.5"), Some(Rational::from((195, 2))));
+    assert_eq!(parse_rational("+1.5"), Some(Rational::from((3, 2))));
+    assert_eq!(parse_rational("1.2.3"), None);
+    assert_eq!(parse_rational("--5.1"), None);
+    assert_eq!(parse_rational("-+5.1"), None);
+    assert_eq!(parse_rational("x"), None);
+    assert_eq!(
+      parse_rational_pair("0.5,1/3"),
+      Some((Rational::from((1, 2)), Rational::from((1, 3)))));
+  }
+}
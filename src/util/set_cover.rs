@@ -0,0 +1,190 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// choosing a few sets that together cover every element any of them
+// covers. each set is a list of elements, numbered from 0, and a cover is
+// listed in order of choice along with what each set adds to the sets
+// before it, so that its first few sets are the most useful ones.
+
+/// one set of a cover.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pick {
+  // the set's position in the list of sets.
+  pub set: usize,
+  // the elements it covers that no earlier set of the cover does.
+  pub gain: usize,
+}
+
+fn universe(sets: &[Vec<usize>]) -> usize {
+  sets.iter().flatten().map(|&e| e + 1).max().unwrap_or(0)
+}
+
+fn gain(set: &[usize], covered: &[bool]) -> usize {
+  set.iter().filter(|&&e| !covered[e]).count()
+}
+
+/// the cover found by repeatedly taking the set that covers the most
+/// elements not yet covered, preferring earlier sets on ties. it has at
+/// most H(n) times as many sets as the smallest cover, where n is the size
+/// of the largest set.
+pub fn greedy(sets: &[Vec<usize>]) -> Vec<Pick> {
+  let mut covered = vec![false; universe(sets)];
+  // a set's gain never grows, so its last known gain is an upper bound,
+  // and it only needs recounting when it comes out on top.
+  let mut heap: BinaryHeap<(usize, Reverse<usize>)> = sets.iter().enumerate()
+    .map(|(i, set)| (gain(set, &covered), Reverse(i)))
+    .collect();
+  let mut picks = vec![];
+  while let Some((bound, Reverse(i))) = heap.pop() {
+    if bound == 0 {
+      break;
+    }
+    let gain = gain(&sets[i], &covered);
+    if gain < bound {
+      heap.push((gain, Reverse(i)));
+      continue;
+    }
+    for &e in &sets[i] {
+      covered[e] = true;
+    }
+    picks.push(Pick{set: i, gain});
+  }
+  picks
+}
+
+/// a cover with as few sets as possible, ranked as `greedy` would rank
+/// them, or none if finding it takes more than `max_steps` steps of the
+/// search. the search is exponential in the worst case, so this is only
+/// meant for small instances.
+pub fn exact(sets: &[Vec<usize>], max_steps: u64) -> Option<Vec<Pick>> {
+  let universe = universe(sets);
+  let mut containing = vec![vec![]; universe];
+  for (i, set) in sets.iter().enumerate() {
+    for &e in set {
+      containing[e].push(i);
+    }
+  }
+  let mut search = ExactSearch{
+    sets,
+    containing,
+    covered: vec![false; universe],
+    uncovered: containing_count(sets, universe),
+    chosen: vec![],
+    best: greedy(sets).into_iter().map(|pick| pick.set).collect(),
+    steps: 0,
+    max_steps,
+  };
+  if !search.search() {
+    return None;
+  }
+  let mut best = search.best;
+  best.sort_unstable();
+  let chosen: Vec<Vec<usize>> = best.iter().map(|&i| sets[i].clone()).collect();
+  Some(greedy(&chosen).into_iter()
+    .map(|pick| Pick{set: best[pick.set], gain: pick.gain})
+    .collect())
+}
+
+// the number of elements in at least one set.
+fn containing_count(sets: &[Vec<usize>], universe: usize) -> usize {
+  let mut seen = vec![false; universe];
+  for &e in sets.iter().flatten() {
+    seen[e] = true;
+  }
+  seen.into_iter().filter(|&seen| seen).count()
+}
+
+// a depth first branch and bound search, starting from the greedy cover
+// as the best so far.
+struct ExactSearch<'a> {
+  sets: &'a [Vec<usize>],
+  // the sets containing each element.
+  containing: Vec<Vec<usize>>,
+  covered: Vec<bool>,
+  uncovered: usize,
+  chosen: Vec<usize>,
+  best: Vec<usize>,
+  steps: u64,
+  max_steps: u64,
+}
+
+impl<'a> ExactSearch<'a> {
+  // searches for a cover smaller than the best so far that extends the
+  // sets chosen so far, returning false if it ran out of steps.
+  fn search(&mut self) -> bool {
+    if self.uncovered == 0 {
+      self.best = self.chosen.clone();
+      return true;
+    }
+    self.steps += 1;
+    if self.steps > self.max_steps {
+      return false;
+    }
+    let gains: Vec<usize> = self.sets.iter().map(|set| gain(set, &self.covered)).collect();
+    let max_gain = *gains.iter().max().unwrap();
+    // every further set covers at most max_gain more elements.
+    let needed = self.uncovered.div_ceil(max_gain);
+    if self.chosen.len() + needed >= self.best.len() {
+      return true;
+    }
+    // some set must cover the element that the fewest sets cover.
+    let element = (0..self.covered.len())
+      .filter(|&e| !self.covered[e])
+      .min_by_key(|&e| self.containing[e].len())
+      .unwrap();
+    let mut options = self.containing[element].clone();
+    options.sort_by_key(|&i| Reverse(gains[i]));
+    for i in options {
+      let newly: Vec<usize> = self.sets[i].iter().cloned()
+        .filter(|&e| !self.covered[e])
+        .collect();
+      for &e in &newly {
+        self.covered[e] = true;
+      }
+      self.uncovered -= newly.len();
+      self.chosen.push(i);
+      let finished = self.search();
+      self.chosen.pop();
+      self.uncovered += newly.len();
+      for &e in &newly {
+        self.covered[e] = false;
+      }
+      if !finished {
+        return false;
+      }
+    }
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn picks(picks: &[Pick]) -> Vec<(usize, usize)> {
+    picks.iter().map(|pick| (pick.set, pick.gain)).collect()
+  }
+
+  #[test]
+  fn test_set_cover() {
+    assert!(greedy(&[]).is_empty());
+    assert_eq!(exact(&[], 10), Some(vec![]));
+
+    // ties go to the earlier set, and sets adding nothing are left out.
+    let sets = vec![vec![0, 1], vec![2, 3], vec![1, 2], vec![0]];
+    assert_eq!(picks(&greedy(&sets)), vec![(0, 2), (1, 2)]);
+
+    // greedy takes the big middle set first and then needs both halves,
+    // while the halves alone would do.
+    let sets = vec![
+      vec![0, 1, 2, 3, 4, 5, 6],
+      vec![7, 8, 9, 10, 11, 12, 13],
+      vec![0, 1, 2, 3, 7, 8, 9, 10],
+      vec![4, 5, 11, 12],
+      vec![6, 13],
+    ];
+    assert_eq!(picks(&greedy(&sets)), vec![(2, 8), (3, 4), (4, 2)]);
+    assert_eq!(picks(&exact(&sets, 1000).unwrap()), vec![(0, 7), (1, 7)]);
+    assert_eq!(exact(&sets, 1), None);
+  }
+}
@@ -1,7 +1,7 @@
 use std::borrow::{Borrow, ToOwned};
 use std::ops::{Add, Sub, Neg, Mul, Div, AddAssign, MulAssign};
 
-use crate::algebra::{Zero, One, SquaredNorm};
+use crate::algebra::{Zero, One, RefOps, SquaredNorm};
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct V2<R>(pub R, pub R);
@@ -56,6 +56,27 @@ where
   }
 }
 
+// as complex numbers, so multiplying rotates and scales.
+impl<R> RefOps for V2<R>
+where
+    R: RefOps + Add<Output=R> + Neg<Output=R>
+{
+  fn add_ref(&self, v: &V2<R>) -> V2<R> {
+    V2(self.0.add_ref(&v.0), self.1.add_ref(&v.1))
+  }
+
+  fn sub_ref(&self, v: &V2<R>) -> V2<R> {
+    V2(self.0.sub_ref(&v.0), self.1.sub_ref(&v.1))
+  }
+
+  fn mul_ref(&self, v: &V2<R>) -> V2<R> {
+    V2(
+      self.0.mul_ref(&v.0) + -self.1.mul_ref(&v.1),
+      self.0.mul_ref(&v.1) + self.1.mul_ref(&v.0)
+    )
+  }
+}
+
 impl<R> Zero for V2<R>
 where R: Zero
 {
@@ -129,12 +150,34 @@ where
 
 impl<'a, R> Sub<&'a V2<R>> for V2<R>
 where
-    R: Add<R, Output=R> + Neg<Output=R> + Clone
+    R: RefOps
 {
   type Output = V2<R>;
 
   fn sub(self, v: &'a V2<R>) -> V2<R> {
-    V2(self.0 + (-v.0.clone()), self.1 + (-v.1.clone()))
+    V2(self.0.sub_ref(&v.0), self.1.sub_ref(&v.1))
+  }
+}
+
+impl<'b, R> Add<&'b V2<R>> for &V2<R>
+where
+    V2<R>: RefOps
+{
+  type Output = V2<R>;
+
+  fn add(self, v: &'b V2<R>) -> V2<R> {
+    self.add_ref(v)
+  }
+}
+
+impl<'b, R> Sub<&'b V2<R>> for &V2<R>
+where
+    V2<R>: RefOps
+{
+  type Output = V2<R>;
+
+  fn sub(self, v: &'b V2<R>) -> V2<R> {
+    self.sub_ref(v)
   }
 }
 
@@ -189,68 +232,56 @@ where
 
 impl<R> Mul<V2<R>> for V2<R>
 where
-    R: Add<Output=R> + Mul<Output=R> + Neg<Output=R> + Clone
+    V2<R>: RefOps
 {
   type Output = V2<R>;
   fn mul(self, v: V2<R>) -> V2<R> {
-    V2(
-      self.0.clone() * v.0.clone() + (-self.1.clone() * v.1.clone()),
-      self.0.clone() * v.1.clone() + self.1.clone() * v.0.clone()
-    )
+    self.mul_ref(&v)
   }
 }
 
-impl<R> Div<V2<R>> for V2<R>
+impl<'b, R> Mul<&'b V2<R>> for &V2<R>
 where
-    V2<R>: Mul<V2<R>> + SquaredNorm<Output=R>,
-    R: Add<Output=R> + Mul<Output=R> + Div<Output=R> + Neg<Output=R> + Clone,
+    V2<R>: RefOps
 {
-  type Output = <V2<R> as Mul>::Output;
-  fn div(self, v: V2<R>) -> Self::Output {
-    let conj = v.clone().complex_conjugate();
-    let norm = v.squared_norm();
-    let v_inv = V2(conj.0 / norm.clone(), conj.1 / norm);
-    self * v_inv
+  type Output = V2<R>;
+  fn mul(self, v: &'b V2<R>) -> V2<R> {
+    self.mul_ref(v)
   }
 }
 
-
-/*impl<'a, 'b, R, T> Mul<&'b T> for &'a V2<R>
+impl<R> MulAssign<V2<R>> for V2<R>
 where
-    T: Borrow<V2<R>>,
-    R: Add<Output=R> + Mul<Output=R> + Neg<Output=R> + Clone
+    V2<R>: RefOps
 {
-  type Output = V2<R>;
-  fn mul(&'a self, v: &'b V2<R>) -> V2<R> {
-    let v: &'b V2<R> = v.borrow();
-    V2(
-      self.0.clone() * v.0.clone() + (-self.1.clone() * v.1.clone()),
-      self.0.clone() * v.1.clone() + self.1.clone() * v.0.clone()
-    )
+  fn mul_assign(&mut self, v: V2<R>) {
+    *self = self.mul_ref(&v);
   }
-}*/
+}
 
-/*
-impl<R> MulAssign<V2<R>> for V2<R>
+impl<'a, R> MulAssign<&'a V2<R>> for V2<R>
 where
-    R: Ring
+    V2<R>: RefOps
 {
-  fn mul_assign(&mut self, v: V2<R>) {
-    *self = *self * v;
+  fn mul_assign(&mut self, v: &'a V2<R>) {
+    *self = self.mul_ref(v);
   }
 }
-*/
 
-/*
-impl<'a, R> MulAssign<&'a V2<R>> for V2<R>
+impl<R> Div<V2<R>> for V2<R>
 where
-    R: Mul<R, Output=R> + Add<R, Output=R> + Neg<Output=R> + Clone + 'a
+    V2<R>: Mul<V2<R>> + SquaredNorm<Output=R>,
+    R: Add<Output=R> + Mul<Output=R> + Div<Output=R> + Neg<Output=R> + Clone,
 {
-  fn mul_assign(self, rhs: V2<R>) {
-    self = rhs;
+  type Output = <V2<R> as Mul>::Output;
+  fn div(self, v: V2<R>) -> Self::Output {
+    let conj = v.clone().complex_conjugate();
+    let norm = v.squared_norm();
+    let v_inv = V2(conj.0 / norm.clone(), conj.1 / norm);
+    self * v_inv
   }
 }
-*/
+
 /*impl<'a, R, V> Mul<&'a V> for V2<R>
 where
     V: AsRef<V2<R>> + 'a,
@@ -276,3 +307,32 @@ impl<R> From<&(R,R)> for V2<R> where R: Clone {
     V2(v.0.clone(), v.1.clone())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rug::Rational;
+
+  fn v(x: (i32, i32), y: (i32, i32)) -> V2<Rational> {
+    V2(Rational::from(x), Rational::from(y))
+  }
+
+  #[test]
+  fn test_ref_ops() {
+    let u = v((1, 2), (3, 4));
+    let w = v((-2, 3), (1, 5));
+    // (1/2 + 3i/4)(-2/3 + i/5) = -1/3 - 3/20 + (1/10 - 1/2)i
+    assert_eq!(&u * &w, v((-29, 60), (-2, 5)));
+    assert_eq!(&u * &w, u.clone() * w.clone());
+    assert_eq!(&u + &w, v((-1, 6), (19, 20)));
+    assert_eq!(&u - &w, u.clone() - &w);
+    assert_eq!(&u - &w, v((7, 6), (11, 20)));
+
+    let mut z = u.clone();
+    z *= &w;
+    z += &w;
+    assert_eq!(z, &(&u * &w) + &w);
+    z *= V2::one();
+    assert_eq!(z, &(&u * &w) + &w);
+  }
+}